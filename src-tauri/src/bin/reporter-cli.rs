@@ -0,0 +1,708 @@
+//! Headless entry point, drives the `Director` without the Tauri window.
+//!
+//! ```sh
+//! reporter-cli list
+//! reporter-cli generate 0 -o first.mp4
+//! reporter-cli generate https://www.thepaper.cn/newsDetail_forward_1
+//! reporter-cli batch --count 5 --out-dir ./videos
+//! reporter-cli local release.md --image stage.jpg
+//! reporter-cli --set TTS_CONCURRENCY=4 config
+//! reporter-cli daemon
+//! ```
+
+use std::{
+    path::{Path, PathBuf},
+    process::ExitCode,
+    sync::Arc,
+};
+
+use clap::{Parser, Subcommand};
+use reporter_lib::{
+    config::{CONFIG_FILE, ConfigError, ConfigLoader, GlobalConfig},
+    director::{
+        Director, DirectorError, DirectorResult, NewsShortVideo, RankedNews,
+        source::SourceRegistry, workspace::Workspace,
+    },
+    library::Library,
+    mpeg::runner::ProcessRunner,
+    news::{
+        NEWS_MODEL_VERSION, NewsPage, NewsTitle,
+        local_news::{LOCAL_DIR, LocalNewsInput},
+    },
+    progress::{ProgressEvent, Stage},
+    schedule::{self, SCHEDULE_FILE, ScheduleSettings, ScheduledRun, Scheduler},
+    secrets::{self, SharedSecretStore, VAULT_PASSPHRASE_ENV},
+};
+use tokio::fs;
+use tokio_util::sync::CancellationToken;
+
+/// The Tauri identifier, the app config dir is named after it
+const APP_IDENTIFIER: &str = "com.aiursoft.reporter";
+
+#[derive(Parser)]
+#[command(
+    name = "reporter-cli",
+    version,
+    about = "Generate news short videos from the command line"
+)]
+struct Cli {
+    /// news source name
+    #[arg(long, short, global = true, default_value = reporter_lib::news::pengpai_news::SOURCE_NAME)]
+    source: String,
+
+    /// the ffmpeg binary, overrides `FFMPEG_PATH` in the config
+    #[arg(long, global = true)]
+    ffmpeg: Option<PathBuf>,
+
+    /// a config file, over the one in the app config dir
+    #[arg(long, global = true)]
+    config: Option<PathBuf>,
+
+    /// set a config value over every other layer, e.g. `--set TTS_CONCURRENCY=4`
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE", value_parser = parse_key_value)]
+    overrides: Vec<(String, String)>,
+
+    /// the config profile to render with, `DEFAULT_PROFILE` when omitted
+    #[arg(long, short, global = true)]
+    profile: Option<String>,
+
+    /// fetch the hot news again even if the cached list is still fresh
+    #[arg(long, global = true)]
+    refresh: bool,
+
+    #[command(subcommand)]
+    command: Commands,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    /// Print the hot news list with its indexes, or a page of a channel
+    List {
+        /// a channel id of `channels`, the hot list when omitted
+        #[arg(long)]
+        channel: Option<String>,
+        /// the page of the channel, from 1
+        #[arg(long, default_value_t = 1, requires = "channel")]
+        page: u32,
+    },
+    /// Print the channels of the source
+    Channels,
+    /// Print a page of the news found by a keyword
+    Search {
+        keyword: String,
+        /// the page of the results, from 1
+        #[arg(long, default_value_t = 1)]
+        page: u32,
+    },
+    /// Generate one video from a hot news index or a news url
+    Generate {
+        /// index in `list`, or the url of the news
+        target: String,
+        /// where to write the final mp4, defaults to `<title>.mp4`
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Generate videos for the top hot news
+    Batch {
+        /// how many news to render, all of them when omitted
+        #[arg(long, short)]
+        count: Option<usize>,
+        /// the directory to write the final mp4 files to
+        #[arg(long, short, default_value = ".")]
+        out_dir: PathBuf,
+    },
+    /// Generate one video from a local HTML, Markdown or text file, or a text
+    Local {
+        /// the document, `--text` is used when omitted
+        file: Option<PathBuf>,
+        /// the text of the news
+        #[arg(long, conflicts_with = "file")]
+        text: Option<String>,
+        /// the title, the first heading or line of the document when omitted
+        #[arg(long)]
+        title: Option<String>,
+        /// a picture to show, can be repeated
+        #[arg(long = "image", value_name = "FILE")]
+        images: Vec<PathBuf>,
+        /// where to write the final mp4, defaults to `<title>.mp4`
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+    },
+    /// Print which layer each config value came from
+    Config,
+    /// Print the enabled news sources, for `--source`
+    Sources,
+    /// Move the API keys out of the config files into the keyring or the vault
+    MigrateSecrets,
+    /// Generate the news picked on the `[SCHEDULE]` of the config until Ctrl-C,
+    /// the videos are added to the library of the app
+    Daemon,
+}
+
+#[tokio::main]
+async fn main() -> ExitCode {
+    let cli = Cli::parse();
+
+    let secrets = match app_config_dir().map(|dir| secrets::open_store(&dir)) {
+        Some(Ok(secrets)) => secrets,
+        Some(Err(e)) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+        None => None,
+    };
+
+    if let Commands::MigrateSecrets = cli.command {
+        return match migrate_secrets(&cli, secrets) {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let config = match load_config(&cli, secrets) {
+        Ok(config) => config,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if let Commands::Sources = cli.command {
+        let settings = config.settings();
+        return match SourceRegistry::from_settings(settings).list(&settings.sources) {
+            Ok(sources) => {
+                for source in sources {
+                    println!("{:<16} {}", source.name, source.display_name);
+                }
+                ExitCode::SUCCESS
+            }
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    if let Commands::Config = cli.command {
+        for (key, layer) in config.sources() {
+            println!("{:<24} {}", key, layer);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let ffmpeg = config
+        .get_ffmpeg_path()
+        .map(|path| ProcessRunner::new(PathBuf::from(path)))
+        .unwrap_or_default();
+
+    if let Commands::Daemon = cli.command {
+        return match daemon(config, ffmpeg).await {
+            Ok(()) => ExitCode::SUCCESS,
+            Err(e) => {
+                eprintln!("error: {}", e);
+                ExitCode::FAILURE
+            }
+        };
+    }
+
+    let Some(data_dir) = app_data_dir() else {
+        eprintln!("error: no data dir");
+        return ExitCode::FAILURE;
+    };
+    let director = match new_director(&config, cli.profile.as_deref(), ffmpeg, &data_dir) {
+        Ok(director) => director,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    // the videos of the app, for the `SKIP_RENDERED` filter
+    let library = Library::load(data_dir).ok();
+    let rendered = |url: &str| {
+        library
+            .as_ref()
+            .is_some_and(|library| library.find_by_url(url).is_some())
+    };
+
+    let res = match cli.command {
+        Commands::List {
+            channel: None,
+            page: _,
+        } => list(&director, &cli.source, cli.refresh, &rendered).await,
+        Commands::List {
+            channel: Some(channel),
+            page,
+        } => {
+            let res = director.get_channel_news(&cli.source, &channel, page).await;
+            print_page(res)
+        }
+        Commands::Channels => channels(&director, &cli.source),
+        Commands::Search { keyword, page } => {
+            let res = director.search_news(&cli.source, &keyword, page).await;
+            print_page(res)
+        }
+        Commands::Generate { target, output } => {
+            generate(
+                &director,
+                &cli.source,
+                cli.refresh,
+                &rendered,
+                &target,
+                output,
+            )
+            .await
+        }
+        Commands::Batch { count, out_dir } => {
+            batch(
+                &director,
+                &cli.source,
+                cli.refresh,
+                &rendered,
+                count,
+                &out_dir,
+            )
+            .await
+        }
+        Commands::Local {
+            file,
+            text,
+            title,
+            images,
+            output,
+        } => {
+            let input = LocalNewsInput {
+                title,
+                file,
+                text,
+                images,
+            };
+            local(&director, input, output).await
+        }
+        Commands::Config | Commands::Sources | Commands::MigrateSecrets | Commands::Daemon => {
+            unreachable!("handled before building the director")
+        }
+    };
+
+    match res {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+async fn list(
+    director: &Director,
+    source: &String,
+    refresh: bool,
+    rendered: &dyn Fn(&str) -> bool,
+) -> Result<(), String> {
+    let list = director
+        .get_hot_news_list(source, refresh, rendered)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    for (i, news) in list.iter().enumerate() {
+        println!("{:>3}  {}\n     {}", i, news.title, news.url);
+    }
+
+    Ok(())
+}
+
+fn channels(director: &Director, source: &String) -> Result<(), String> {
+    let channels = director.channels(source).map_err(|e| e.to_string())?;
+    if channels.is_empty() {
+        return Err(format!("{} has no channels", source));
+    }
+
+    for channel in channels {
+        println!("{:<10} {}", channel.id, channel.name);
+    }
+
+    Ok(())
+}
+
+/// Print a page of news with what is known of each.
+fn print_page(page: DirectorResult<NewsPage>) -> Result<(), String> {
+    let page = page.map_err(|e| e.to_string())?;
+
+    for news in &page.titles {
+        println!("{}\n  {}", news.title, news.url);
+        let meta = &news.meta;
+        let about: Vec<&str> = [meta.channel.as_deref(), meta.author.as_deref()]
+            .into_iter()
+            .flatten()
+            .collect();
+        if !about.is_empty() {
+            println!("  {}", about.join(" · "));
+        }
+        if let Some(summary) = &meta.summary {
+            println!("  {}", summary);
+        }
+    }
+    if page.has_next {
+        println!("-- more with --page {}", page.page + 1);
+    }
+
+    Ok(())
+}
+
+async fn generate(
+    director: &Director,
+    source: &String,
+    refresh: bool,
+    rendered: &dyn Fn(&str) -> bool,
+    target: &str,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    let list = director
+        .get_hot_news_list(source, refresh, rendered)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let news_title = if let Ok(index) = target.parse::<usize>() {
+        list.into_iter()
+            .nth(index)
+            .ok_or(format!("no hot news at index {}", index))?
+    } else {
+        list.into_iter()
+            .find(|n| n.url == target)
+            .unwrap_or_else(|| NewsTitle {
+                version: NEWS_MODEL_VERSION,
+                source: source.clone(),
+                title: target.to_owned(),
+                url: target.to_owned(),
+                pics: vec![],
+                videos: vec![],
+                meta: Default::default(),
+            })
+    };
+
+    let output =
+        output.unwrap_or_else(|| PathBuf::from(format!("{}.mp4", file_name(&news_title.title))));
+    let path = shot(director, news_title, &output).await?;
+    println!("{}", path.display());
+
+    Ok(())
+}
+
+async fn local(
+    director: &Director,
+    input: LocalNewsInput,
+    output: Option<PathBuf>,
+) -> Result<(), String> {
+    // the texts are kept next to the ones of the app
+    let dir = app_data_dir().ok_or("no data dir")?.join(LOCAL_DIR);
+    let news_title = input
+        .into_news_title(&dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let output =
+        output.unwrap_or_else(|| PathBuf::from(format!("{}.mp4", file_name(&news_title.title))));
+    let path = shot(director, news_title, &output).await?;
+    println!("{}", path.display());
+
+    Ok(())
+}
+
+async fn batch(
+    director: &Director,
+    source: &String,
+    refresh: bool,
+    rendered: &dyn Fn(&str) -> bool,
+    count: Option<usize>,
+    out_dir: &Path,
+) -> Result<(), String> {
+    let list = director
+        .get_hot_news_list(source, refresh, rendered)
+        .await
+        .map_err(|e| e.to_string())?;
+    let count = count.unwrap_or(list.len());
+
+    fs::create_dir_all(out_dir)
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut failed = 0usize;
+    for (i, news_title) in list.into_iter().take(count).enumerate() {
+        let output = out_dir.join(format!("{:03}-{}.mp4", i, file_name(&news_title.title)));
+        println!("[{}/{}] {}", i + 1, count, news_title.title);
+
+        match shot(director, news_title, &output).await {
+            Ok(path) => println!("{}", path.display()),
+            Err(e) => {
+                failed += 1;
+                eprintln!("error: {}", e);
+            }
+        }
+    }
+
+    if failed > 0 {
+        return Err(format!("{} of {} videos failed", failed, count));
+    }
+
+    Ok(())
+}
+
+/// Run the schedule of the config until Ctrl-C, with the state and the library of the app.
+async fn daemon(config: GlobalConfig, ffmpeg: ProcessRunner) -> Result<(), String> {
+    let settings = &config.settings().schedule;
+    if !settings.enabled {
+        return Err("the schedule is not enabled, set `ENABLED=true` in `[SCHEDULE]`".to_owned());
+    }
+
+    let data_dir = app_data_dir().ok_or("no data dir")?;
+    let scheduler = Scheduler::load(data_dir.join(SCHEDULE_FILE)).map_err(|e| e.to_string())?;
+    let library = Library::load(data_dir.clone()).map_err(|e| e.to_string())?;
+    library.set_videos_dir(config.get_output_dir());
+
+    let status = scheduler.status(settings);
+    eprintln!("scheduled on {}", settings.cron.join(", "));
+    if let Some(quiet_hours) = &settings.quiet_hours {
+        eprintln!("quiet hours {}", quiet_hours);
+    }
+    if let Some(at) = status
+        .next_run_at
+        .and_then(|at| chrono::DateTime::from_timestamp(at as i64, 0))
+    {
+        eprintln!(
+            "next run at {}",
+            at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        );
+    }
+
+    let cancel = cancel_on_ctrl_c();
+    let target = DaemonRun {
+        config,
+        ffmpeg: Arc::new(ffmpeg),
+        data_dir,
+        library,
+        cancel: cancel.clone(),
+    };
+    scheduler.run(&target, &cancel).await;
+
+    Ok(())
+}
+
+/// Renders the news of the scheduled runs one after the other into the library.
+struct DaemonRun {
+    config: GlobalConfig,
+    ffmpeg: Arc<ProcessRunner>,
+    data_dir: PathBuf,
+    library: Library,
+    cancel: CancellationToken,
+}
+
+#[async_trait::async_trait]
+impl ScheduledRun for DaemonRun {
+    async fn settings(&self) -> ScheduleSettings {
+        self.config.settings().schedule.clone()
+    }
+
+    async fn run(&self, settings: &ScheduleSettings) -> DirectorResult<RankedNews> {
+        let director = Director::default(
+            &self.config,
+            settings.profile.as_deref(),
+            self.ffmpeg.clone(),
+            &self.data_dir,
+        )?
+        .build()?;
+        let rendered = |url: &str| self.library.find_by_url(url).is_some();
+        let picked = schedule::pick(
+            &director,
+            &self.config.settings().sources,
+            settings,
+            &rendered,
+        )
+        .await?;
+        for failure in &picked.failures {
+            eprintln!("error: {}: {}", failure.source, failure.message);
+        }
+
+        for news_title in picked.titles.clone() {
+            eprintln!("==> {}", news_title.title);
+            let video = match director
+                .shot_single(news_title, &print_progress, &self.cancel)
+                .await
+            {
+                Ok(video) => video,
+                Err(DirectorError::Cancelled) => return Err(DirectorError::Cancelled),
+                Err(e) => {
+                    eprintln!("error: {}", e);
+                    continue;
+                }
+            };
+
+            match self.library.add(video).await {
+                Ok(entry) => println!("{}", entry.path.display()),
+                Err(e) => eprintln!("error: failed to add the video to the library: {}", e),
+            }
+        }
+
+        Ok(picked)
+    }
+}
+
+/// The layers of the app, then `--config`, the env vars, `--set` and `--ffmpeg`.
+/// The config dir of the desktop app, shared with the CLI.
+fn app_config_dir() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// The data dir of the desktop app, the library, the workspaces and the caches are shared.
+fn app_data_dir() -> Option<PathBuf> {
+    dirs::data_dir().map(|dir| dir.join(APP_IDENTIFIER))
+}
+
+/// The app config file and then `--config`.
+fn config_files(cli: &Cli) -> Vec<PathBuf> {
+    app_config_dir()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .into_iter()
+        .chain(cli.config.clone())
+        .collect()
+}
+
+fn load_config(cli: &Cli, secrets: Option<SharedSecretStore>) -> Result<GlobalConfig, ConfigError> {
+    let mut loader = ConfigLoader::new();
+    for file in config_files(cli) {
+        loader = loader.with_file(file);
+    }
+    if let Some(store) = secrets {
+        loader = loader.with_secrets(store);
+    }
+    for (key, value) in &cli.overrides {
+        loader = loader.with_override(key, value);
+    }
+    if let Some(ffmpeg) = &cli.ffmpeg {
+        loader = loader.with_override("FFMPEG_PATH", ffmpeg.display().to_string());
+    }
+
+    loader.load()
+}
+
+fn migrate_secrets(cli: &Cli, secrets: Option<SharedSecretStore>) -> Result<(), String> {
+    let store = secrets.ok_or(format!(
+        "no keyring found, set {} to use the encrypted vault",
+        VAULT_PASSPHRASE_ENV
+    ))?;
+
+    for file in config_files(cli) {
+        let moved = secrets::migrate(&file, &*store).map_err(|e| e.to_string())?;
+        if !moved.is_empty() {
+            println!(
+                "moved {} from {} to the {}",
+                moved.join(", "),
+                file.display(),
+                store.name()
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn parse_key_value(arg: &str) -> Result<(String, String), String> {
+    arg.split_once('=')
+        .map(|(key, value)| (key.to_owned(), value.to_owned()))
+        .ok_or(format!("expected KEY=VALUE, got `{}`", arg))
+}
+
+fn new_director(
+    config: &GlobalConfig,
+    profile: Option<&str>,
+    ffmpeg: ProcessRunner,
+    data_dir: &Path,
+) -> DirectorResult<Director> {
+    Ok(Director::default(config, profile, Arc::new(ffmpeg), data_dir)?.build()?)
+}
+
+/// Generate the video and move it from the temp dir to `output`.
+async fn shot(
+    director: &Director,
+    news_title: NewsTitle,
+    output: &Path,
+) -> Result<PathBuf, String> {
+    let NewsShortVideo { path, .. } = director
+        .shot_single(news_title, &print_progress, &cancel_on_ctrl_c())
+        .await
+        .map_err(|e| e.to_string())?;
+
+    if let Some(parent) = output.parent()
+        && !parent.as_os_str().is_empty()
+    {
+        fs::create_dir_all(parent)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    // rename does not work across file systems
+    if fs::rename(&path, output).await.is_err() {
+        fs::copy(&path, output).await.map_err(|e| e.to_string())?;
+        _ = fs::remove_file(&path).await;
+    }
+    _ = Workspace::prune(&path).await;
+
+    output.canonicalize().map_err(|e| e.to_string())
+}
+
+/// A token cancelled by Ctrl-C, so the temp files are cleaned up.
+fn cancel_on_ctrl_c() -> CancellationToken {
+    let cancel = CancellationToken::new();
+
+    let token = cancel.clone();
+    tokio::spawn(async move {
+        if tokio::signal::ctrl_c().await.is_ok() {
+            eprintln!("cancelling...");
+            token.cancel();
+        }
+    });
+
+    cancel
+}
+
+/// Print the stages to stderr, the ffmpeg output is only shown when it fails.
+fn print_progress(event: ProgressEvent) {
+    let stage_name = |stage: Stage| match stage {
+        Stage::Material => "summarizing the news",
+        Stage::Dubbing => "dubbing",
+        Stage::Subtitle => "writing subtitles",
+        Stage::Video => "rendering the slideshow",
+        Stage::Compose => "composing the final video",
+    };
+
+    match event {
+        ProgressEvent::StageStarted { stage } => eprintln!("==> {}", stage_name(stage)),
+        ProgressEvent::StageCached { stage } => {
+            eprintln!("==> {} (cached)", stage_name(stage))
+        }
+        ProgressEvent::SentenceSynthesized { index, total } => {
+            eprintln!("    sentence {}/{} synthesized", index, total)
+        }
+        ProgressEvent::ImageDownloaded { index, total } => {
+            eprintln!("    image {}/{} downloaded", index, total)
+        }
+        _ => {}
+    }
+}
+
+/// Make a title safe to use as a file name.
+fn file_name(title: &str) -> String {
+    title
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() => '_',
+            c => c,
+        })
+        .take(60)
+        .collect::<String>()
+        .trim()
+        .to_owned()
+}
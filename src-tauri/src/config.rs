@@ -1,36 +1,793 @@
-// use std::collections::HashMap;
+use std::{
+    collections::BTreeMap,
+    fmt,
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex, RwLock},
+    time::Duration,
+};
 
-use config::{Config, File};
+use config::{Case, Config, Environment, File, FileFormat, Map, Source, Value};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
 
-const DEFAULT_CONFIG_FILE: &str = "config";
+use crate::{
+    llm::{LlmProvider, LlmSettings},
+    news::{feed_news::FeedSettings, filter::FilterSettings},
+    profile::{Profile, ProfileSettings, Resolution},
+    schedule::ScheduleSettings,
+    secrets::{SECRET_KEYS, SecretError, SharedSecretStore},
+};
+
+/// The defaults bundled into the app
+const DEFAULT_CONFIG: &str = include_str!("../config.default.toml");
+/// The name of the config file in the app config dir
+pub const CONFIG_FILE: &str = "config.toml";
+/// `REPORTER_OPENAI_KEY` overrides `OPENAI_KEY`
+pub const ENV_PREFIX: &str = "REPORTER";
+
+#[derive(Error, Debug)]
+pub enum ConfigError {
+    #[error("failed to load the config: {0}")]
+    Load(#[from] config::ConfigError),
+    #[error("invalid config: {}", .0.join(", "))]
+    Invalid(Vec<String>),
+    #[error("`{0}` is not set in the config")]
+    Missing(&'static str),
+    #[error("no profile named `{0}` in the config")]
+    UnknownProfile(String),
+    #[error("failed to read the secrets: {0}")]
+    Secret(#[from] SecretError),
+}
+
+/// The config, deserialized once from all the layers.
+/// The keys are the ones of the config file, `TTS_URL` for `tts_url`.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct Settings {
+    pub openai_key: String,
+    pub tts_url: String,
+    pub ali_dashscope_api_key: String,
+    /// the voice of the dubbing
+    pub voice: String,
+    pub tts_model: String,
+    /// the chat model summarizing the news
+    pub llm_model: String,
+    /// extra instructions on the style of the summary
+    pub llm_prompt: Option<String>,
+    /// the endpoint serving `LLM_MODEL`
+    #[serde(default)]
+    pub llm: LlmSettings,
+    /// the size of the video, `720x1280`
+    pub resolution: String,
+    /// the ASS style of the subtitles, e.g. `FontSize=24,PrimaryColour=&H00FFFFFF`
+    pub subtitle_style: Option<String>,
+    /// the text drawn in a corner of the video
+    pub watermark: Option<String>,
+    /// the profile used when none is given, the top level keys alone when unset
+    pub default_profile: Option<String>,
+    /// the named profiles, each overriding some of the keys above
+    #[serde(default)]
+    pub profiles: BTreeMap<String, ProfileSettings>,
+    /// the names of the news sources shown in the app, in order
+    pub sources: Vec<String>,
+    /// the RSS and Atom feeds, each a source named after its key
+    #[serde(default)]
+    pub feeds: BTreeMap<String, FeedSettings>,
+    /// the rules the hot news lists are filtered and ranked by
+    #[serde(default)]
+    pub filter: FilterSettings,
+    /// the unattended generation
+    #[serde(default)]
+    pub schedule: ScheduleSettings,
+    /// where the videos are moved to, `videos` in the app data dir when unset
+    pub output_dir: Option<String>,
+    /// the ffmpeg binary used outside the Tauri app
+    pub ffmpeg_path: Option<String>,
+    /// how many jobs of the queue may summarize with the LLM at once
+    pub llm_concurrency: usize,
+    /// how many jobs of the queue may request TTS at once
+    pub tts_concurrency: usize,
+    /// how many ffmpeg processes may run at once
+    pub ffmpeg_concurrency: usize,
+    /// how long a hot news list is shown before it is fetched again, in seconds
+    pub hot_news_ttl: u64,
+}
+
+impl Settings {
+    /// Check the values that are set but wrong, the keys are only required when used.
+    fn validate(&self, sources: &BTreeMap<String, ConfigLayer>) -> Result<(), ConfigError> {
+        let mut errors = vec![];
+        let mut invalid = |key: &str, message: &str| {
+            // the keys of a profile are recorded as `PROFILES`
+            let error = match sources.get(key.split('.').next().unwrap_or(key)) {
+                Some(layer) => format!("`{}` {} (set by {})", key, message, layer),
+                None => format!("`{}` {}", key, message),
+            };
+            errors.push(error);
+        };
+
+        if reqwest::Url::parse(&self.tts_url).is_err() {
+            invalid("TTS_URL", "is not a url");
+        }
+        for (key, value) in [
+            ("VOICE", &self.voice),
+            ("TTS_MODEL", &self.tts_model),
+            ("LLM_MODEL", &self.llm_model),
+        ] {
+            if value.is_empty() {
+                invalid(key, "is empty");
+            }
+        }
+        for (key, value) in [
+            ("LLM_CONCURRENCY", self.llm_concurrency),
+            ("TTS_CONCURRENCY", self.tts_concurrency),
+            ("FFMPEG_CONCURRENCY", self.ffmpeg_concurrency),
+        ] {
+            if value == 0 {
+                invalid(key, "must be at least 1");
+            }
+        }
+        for (key, value) in [
+            ("OUTPUT_DIR", &self.output_dir),
+            ("FFMPEG_PATH", &self.ffmpeg_path),
+        ] {
+            if value.as_deref() == Some("") {
+                invalid(key, "is empty");
+            }
+        }
+        if self.sources.is_empty() {
+            invalid("SOURCES", "is empty");
+        }
+        for (name, feed) in &self.feeds {
+            if reqwest::Url::parse(&feed.url).is_err() {
+                invalid(&format!("FEEDS.{}.URL", name), "is not a url");
+            }
+        }
+        if let Err(e) = self.resolution.parse::<Resolution>() {
+            invalid("RESOLUTION", &e);
+        }
+        for (key, message) in self.llm.invalid() {
+            invalid(&format!("LLM.{}", key), &message);
+        }
+        for (key, message) in self.filter.invalid() {
+            invalid(&format!("FILTER.{}", key), &message);
+        }
+        for (key, message) in self.schedule.invalid() {
+            invalid(&format!("SCHEDULE.{}", key), &message);
+        }
+        if let Some(name) = &self.schedule.profile
+            && !self.profiles.contains_key(name)
+        {
+            invalid("SCHEDULE.PROFILE", &format!("names no profile `{}`", name));
+        }
+        if let Some(name) = &self.default_profile
+            && !self.profiles.contains_key(name)
+        {
+            invalid("DEFAULT_PROFILE", &format!("names no profile `{}`", name));
+        }
+        for (name, profile) in &self.profiles {
+            let key = |key: &str| format!("PROFILES.{}.{}", name, key);
+            for (k, value) in [
+                ("VOICE", &profile.voice),
+                ("TTS_MODEL", &profile.tts_model),
+                ("LLM_MODEL", &profile.llm_model),
+            ] {
+                if value.as_deref() == Some("") {
+                    invalid(&key(k), "is empty");
+                }
+            }
+            if let Some(Err(e)) = profile.resolution.as_deref().map(str::parse::<Resolution>) {
+                invalid(&key("RESOLUTION"), &e);
+            }
+            for (k, message) in profile.filter.iter().flat_map(FilterSettings::invalid) {
+                invalid(&key(&format!("FILTER.{}", k)), &message);
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(errors))
+        }
+    }
+}
+
+/// Where a config value came from.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ConfigLayer {
+    /// bundled into the app
+    Default,
+    File {
+        path: PathBuf,
+    },
+    /// the keyring or the vault
+    Secret {
+        store: String,
+    },
+    Env {
+        var: String,
+    },
+    /// given on the command line
+    Override,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Default => write!(f, "the defaults"),
+            Self::File { path } => write!(f, "{}", path.display()),
+            Self::Secret { store } => write!(f, "the {}", store),
+            Self::Env { var } => write!(f, "${}", var),
+            Self::Override => write!(f, "the command line"),
+        }
+    }
+}
+
+/// Layers the config, each layer overriding the ones before:
+/// the bundled defaults, the files in the order they are added, the secret store,
+/// the `REPORTER_` env vars, then the overrides.
+#[derive(Default)]
+pub struct ConfigLoader {
+    files: Vec<PathBuf>,
+    secrets: Option<SharedSecretStore>,
+    /// `None` reads the env of the process
+    env: Option<Map<String, String>>,
+    overrides: Vec<(String, String)>,
+}
+
+impl ConfigLoader {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a config file, skipped when absent.
+    pub fn with_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.files.push(path.into());
+        self
+    }
+
+    /// Read the API keys from `store`, over the files.
+    pub fn with_secrets(self, store: SharedSecretStore) -> Self {
+        Self {
+            secrets: Some(store),
+            ..self
+        }
+    }
+
+    /// Read the env vars from `vars` instead of the env of the process.
+    pub fn with_env(self, vars: Map<String, String>) -> Self {
+        Self {
+            env: Some(vars),
+            ..self
+        }
+    }
+
+    /// Set `key`, e.g. `FFMPEG_PATH`, over every other layer.
+    pub fn with_override(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.overrides.push((key.into(), value.into()));
+        self
+    }
+
+    pub fn load(self) -> Result<GlobalConfig, ConfigError> {
+        let mut sources = BTreeMap::new();
+
+        let defaults = File::from_str(DEFAULT_CONFIG, FileFormat::Toml);
+        record(&mut sources, &defaults, |_| ConfigLayer::Default)?;
+        let mut builder = Config::builder().add_source(defaults);
+
+        for path in self.files {
+            let file = File::from(path.as_path()).required(false);
+            record(&mut sources, &file, |_| ConfigLayer::File {
+                path: path.clone(),
+            })?;
+            builder = builder.add_source(file);
+        }
+
+        if let Some(store) = &self.secrets {
+            let mut values = Map::new();
+            for key in SECRET_KEYS {
+                if let Some(value) = store.get(key)? {
+                    values.insert(key.to_owned(), value);
+                }
+            }
+
+            let secrets = SecretValues {
+                store: store.name(),
+                values,
+            };
+            record(&mut sources, &secrets, |_| ConfigLayer::Secret {
+                store: store.name().to_owned(),
+            })?;
+            builder = builder.add_source(secrets);
+        }
+
+        let env = Environment::with_prefix(ENV_PREFIX)
+            .convert_case(Case::UpperSnake)
+            .source(self.env);
+        record(&mut sources, &env, |key| ConfigLayer::Env {
+            var: format!("{}_{}", ENV_PREFIX, key),
+        })?;
+        builder = builder.add_source(env);
+
+        for (key, value) in self.overrides {
+            sources.insert(key.clone(), ConfigLayer::Override);
+            builder = builder.set_override(key, value)?;
+        }
+
+        let settings: Settings = builder.build()?.try_deserialize()?;
+        settings.validate(&sources)?;
+
+        Ok(GlobalConfig {
+            settings,
+            sources,
+            secrets: self.secrets,
+        })
+    }
+}
+
+/// The keys read from a `SecretStore`, as a config source.
+#[derive(Clone, Debug)]
+struct SecretValues {
+    store: &'static str,
+    values: Map<String, String>,
+}
+
+impl Source for SecretValues {
+    fn clone_into_box(&self) -> Box<dyn Source + Send + Sync> {
+        Box::new(self.clone())
+    }
+
+    fn collect(&self) -> Result<Map<String, Value>, config::ConfigError> {
+        let origin = self.store.to_owned();
+        Ok(self
+            .values
+            .iter()
+            .map(|(key, value)| (key.clone(), Value::new(Some(&origin), value.clone())))
+            .collect())
+    }
+}
+
+/// Note `layer` as the source of every key of `source`.
+fn record(
+    sources: &mut BTreeMap<String, ConfigLayer>,
+    source: &dyn Source,
+    layer: impl Fn(&str) -> ConfigLayer,
+) -> Result<(), ConfigError> {
+    for key in source.collect()?.into_keys() {
+        let value = layer(&key);
+        sources.insert(key, value);
+    }
+
+    Ok(())
+}
 
 pub struct GlobalConfig {
-    config: Config,
-    // cache: HashMap<String, String>,
+    settings: Settings,
+    sources: BTreeMap<String, ConfigLayer>,
+    secrets: Option<SharedSecretStore>,
 }
 
 impl GlobalConfig {
-    pub fn new() -> Self {
-        let config = Config::builder()
-            .add_source(File::with_name(DEFAULT_CONFIG_FILE))
-            .build()
-            .unwrap();
+    /// Load the defaults, `file` if it exists, the keys in `secrets` and the env vars.
+    pub fn load(file: &Path, secrets: Option<SharedSecretStore>) -> Result<Self, ConfigError> {
+        let loader = ConfigLoader::new().with_file(file);
+        match secrets {
+            Some(store) => loader.with_secrets(store),
+            None => loader,
+        }
+        .load()
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Where the API keys are kept, `None` when they are in the config file.
+    pub fn secrets(&self) -> Option<&SharedSecretStore> {
+        self.secrets.as_ref()
+    }
+
+    /// The layer each value came from, by key.
+    pub fn sources(&self) -> &BTreeMap<String, ConfigLayer> {
+        &self.sources
+    }
+
+    pub fn get_tts_url(&self) -> Result<String, ConfigError> {
+        required("TTS_URL", &self.settings.tts_url)
+    }
+
+    pub fn get_ali_dashscope_api_key(&self) -> Result<String, ConfigError> {
+        required(
+            "ALI_DASHSCOPE_API_KEY",
+            &self.settings.ali_dashscope_api_key,
+        )
+    }
+
+    pub fn get_deepseek_api_key(&self) -> Result<String, ConfigError> {
+        required("OPENAI_KEY", &self.settings.openai_key)
+    }
+
+    /// The key of the LLM provider, `ALI_DASHSCOPE_API_KEY` for DashScope, else `OPENAI_KEY`,
+    /// which the local servers do not require.
+    pub fn get_llm_api_key(&self) -> Result<Option<String>, ConfigError> {
+        match self.settings.llm.provider {
+            LlmProvider::Deepseek => self.get_deepseek_api_key().map(Some),
+            LlmProvider::Dashscope => self.get_ali_dashscope_api_key().map(Some),
+            LlmProvider::Ollama | LlmProvider::LlamaCpp => {
+                Ok(Some(self.settings.openai_key.clone()).filter(|key| !key.is_empty()))
+            }
+        }
+    }
+
+    /// The profile `name`, else the default profile, else the top level keys.
+    pub fn profile(&self, name: Option<&str>) -> Result<Profile, ConfigError> {
+        let Some(name) = name.or(self.settings.default_profile.as_deref()) else {
+            return Ok(Profile::resolve(
+                &self.settings,
+                None,
+                &ProfileSettings::default(),
+            ));
+        };
+
+        let profile = self
+            .settings
+            .profiles
+            .get(name)
+            .ok_or_else(|| ConfigError::UnknownProfile(name.to_owned()))?;
+
+        Ok(Profile::resolve(&self.settings, Some(name), profile))
+    }
+
+    /// How many jobs of the queue may summarize with the LLM at once
+    pub fn get_llm_concurrency(&self) -> usize {
+        self.settings.llm_concurrency
+    }
+
+    /// How many jobs of the queue may request TTS at once
+    pub fn get_tts_concurrency(&self) -> usize {
+        self.settings.tts_concurrency
+    }
+
+    /// How many ffmpeg processes may run at once
+    pub fn get_ffmpeg_concurrency(&self) -> usize {
+        self.settings.ffmpeg_concurrency
+    }
 
-        Self { 
-            config,
-            // cache: HashMap::new(),
+    /// How long a hot news list is shown before it is fetched again
+    pub fn get_hot_news_ttl(&self) -> Duration {
+        Duration::from_secs(self.settings.hot_news_ttl)
+    }
+
+    /// Where the videos are moved to, optional
+    pub fn get_output_dir(&self) -> Option<PathBuf> {
+        self.settings.output_dir.as_ref().map(PathBuf::from)
+    }
+
+    /// The ffmpeg binary used outside the Tauri app, optional
+    pub fn get_ffmpeg_path(&self) -> Option<String> {
+        self.settings.ffmpeg_path.clone()
+    }
+}
+
+/// The config of the app, shared by the commands and the jobs.
+/// A reader takes a snapshot and holds no lock while it works,
+/// a change swaps the snapshot, what has started keeps the one it took.
+pub struct SharedConfig {
+    current: RwLock<Arc<GlobalConfig>>,
+    /// held while a change is made, two changes do not overwrite each other
+    changing: Mutex<()>,
+}
+
+impl SharedConfig {
+    pub fn new(config: GlobalConfig) -> Self {
+        Self {
+            current: RwLock::new(Arc::new(config)),
+            changing: Mutex::new(()),
         }
     }
 
-    pub fn get_tts_url(&self) -> String {
-        self.config.get_string("TTS_URL").unwrap()
+    /// The config as it is now.
+    pub fn get(&self) -> Arc<GlobalConfig> {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replace the config with what `change` makes of the current one, returns the new one.
+    /// The config is left as is when `change` fails.
+    pub fn change<E>(
+        &self,
+        change: impl FnOnce(&GlobalConfig) -> Result<GlobalConfig, E>,
+    ) -> Result<Arc<GlobalConfig>, E> {
+        let _changing = self.changing.lock().unwrap();
+        let config = Arc::new(change(&self.get())?);
+        *self.current.write().unwrap() = config.clone();
+        Ok(config)
     }
+}
+
+/// An empty value is as good as missing.
+fn required(key: &'static str, value: &str) -> Result<String, ConfigError> {
+    if value.is_empty() {
+        return Err(ConfigError::Missing(key));
+    }
+
+    Ok(value.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
 
-    pub fn get_ali_dashscope_api_key(&self) -> String {
-        self.config.get_string("ALI_DASHSCOPE_API_KEY").unwrap()
+    fn env(vars: &[(&str, &str)]) -> Map<String, String> {
+        vars.iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
     }
 
-    pub fn get_deepseek_api_key(&self) -> String {
-        self.config.get_string("OPENAI_KEY").unwrap()
+    #[test]
+    fn defaults_only() {
+        let config = ConfigLoader::new().with_env(env(&[])).load().unwrap();
+
+        assert_eq!(2, config.get_llm_concurrency());
+        assert_eq!(None, config.get_ffmpeg_path());
+        assert!(matches!(
+            config.get_deepseek_api_key(),
+            Err(ConfigError::Missing("OPENAI_KEY"))
+        ));
+        assert_eq!(Some(&ConfigLayer::Default), config.sources().get("TTS_URL"));
+    }
+
+    #[test]
+    fn layers_override_in_order() {
+        let dir = TempDir::new("config").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &file,
+            "OPENAI_KEY=\"from file\"\nTTS_CONCURRENCY=4\nFFMPEG_PATH=\"/usr/bin/ffmpeg\"",
+        )
+        .unwrap();
+
+        let config = ConfigLoader::new()
+            .with_file(dir.path().join("absent.toml"))
+            .with_file(&file)
+            .with_env(env(&[
+                ("REPORTER_OPENAI_KEY", "from env"),
+                ("REPORTER_FFMPEG_PATH", "/opt/ffmpeg"),
+                ("OPENAI_KEY", "not prefixed"),
+            ]))
+            .with_override("FFMPEG_PATH", "./ffmpeg")
+            .load()
+            .unwrap();
+
+        assert_eq!("from env", config.get_deepseek_api_key().unwrap());
+        assert_eq!(4, config.get_tts_concurrency());
+        assert_eq!(Some("./ffmpeg".to_owned()), config.get_ffmpeg_path());
+
+        let sources = config.sources();
+        assert_eq!(
+            Some(&ConfigLayer::Env {
+                var: "REPORTER_OPENAI_KEY".to_owned()
+            }),
+            sources.get("OPENAI_KEY")
+        );
+        assert_eq!(
+            Some(&ConfigLayer::File { path: file }),
+            sources.get("TTS_CONCURRENCY")
+        );
+        assert_eq!(Some(&ConfigLayer::Override), sources.get("FFMPEG_PATH"));
+        assert_eq!(Some(&ConfigLayer::Default), sources.get("LLM_CONCURRENCY"));
+    }
+
+    #[test]
+    fn invalid_values() {
+        let res = ConfigLoader::new()
+            .with_env(env(&[
+                ("REPORTER_TTS_URL", "not a url"),
+                ("REPORTER_FFMPEG_CONCURRENCY", "0"),
+            ]))
+            .load();
+
+        let Err(ConfigError::Invalid(errors)) = res else {
+            panic!("expected the config to be invalid");
+        };
+        assert_eq!(
+            vec![
+                "`TTS_URL` is not a url (set by $REPORTER_TTS_URL)".to_owned(),
+                "`FFMPEG_CONCURRENCY` must be at least 1 (set by $REPORTER_FFMPEG_CONCURRENCY)"
+                    .to_owned(),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn profiles() {
+        let dir = TempDir::new("config").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &file,
+            r#"
+VOICE="Serena"
+WATERMARK="@reporter"
+DEFAULT_PROFILE="news"
+
+[PROFILES.news]
+VOICE="Cherry"
+RESOLUTION="1080x1920"
+
+[PROFILES.quiet]
+WATERMARK=""
+"#,
+        )
+        .unwrap();
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load()
+            .unwrap();
+
+        let news = config.profile(None).unwrap();
+        assert_eq!(Some("news".to_owned()), news.name);
+        assert_eq!("Cherry", news.voice);
+        assert_eq!("1080x1920", news.resolution.to_string());
+        assert_eq!(Some("@reporter".to_owned()), news.watermark);
+
+        let quiet = config.profile(Some("quiet")).unwrap();
+        assert_eq!("Serena", quiet.voice);
+        assert_eq!("720x1280", quiet.resolution.to_string());
+        // turned off by the profile
+        assert_eq!(None, quiet.watermark);
+
+        assert!(matches!(
+            config.profile(Some("absent")),
+            Err(ConfigError::UnknownProfile(_))
+        ));
+    }
+
+    #[test]
+    fn invalid_profiles() {
+        let dir = TempDir::new("config").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &file,
+            "DEFAULT_PROFILE=\"absent\"\n[PROFILES.news]\nRESOLUTION=\"big\"\n",
+        )
+        .unwrap();
+
+        let res = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load();
+
+        let Err(ConfigError::Invalid(errors)) = res else {
+            panic!("expected the config to be invalid");
+        };
+        assert_eq!(
+            vec![
+                format!(
+                    "`DEFAULT_PROFILE` names no profile `absent` (set by {})",
+                    file.display()
+                ),
+                format!(
+                    "`PROFILES.news.RESOLUTION` is not like `720x1280` (set by {})",
+                    file.display()
+                ),
+            ],
+            errors
+        );
+    }
+
+    #[test]
+    fn filters() {
+        let dir = TempDir::new("config").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &file,
+            r#"
+[FILTER]
+EXCLUDE=["娱乐"]
+
+[FILTER.CATEGORIES.pengpai]
+INCLUDE=["国际"]
+
+[PROFILES.tech.FILTER]
+INCLUDE=["火箭"]
+"#,
+        )
+        .unwrap();
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load()
+            .unwrap();
+
+        let top = config.profile(None).unwrap().filter;
+        assert_eq!(vec!["娱乐".to_owned()], top.exclude);
+        assert_eq!(vec!["国际".to_owned()], top.categories["pengpai"].include);
+
+        // replaced as a whole
+        let tech = config.profile(Some("tech")).unwrap().filter;
+        assert_eq!(vec!["火箭".to_owned()], tech.include);
+        assert!(tech.exclude.is_empty());
+
+        std::fs::write(&file, "[PROFILES.tech.FILTER]\nINCLUDE_REGEX=[\"(\"]\n").unwrap();
+        let res = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load();
+        let Err(ConfigError::Invalid(errors)) = res else {
+            panic!("expected the config to be invalid");
+        };
+        assert_eq!(
+            vec![format!(
+                "`PROFILES.tech.FILTER.INCLUDE_REGEX` has an invalid regex `(` (set by {})",
+                file.display()
+            )],
+            errors
+        );
+    }
+
+    #[test]
+    fn llm_providers() {
+        let dir = TempDir::new("config").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(&file, "[LLM]\nPROVIDER=\"ollama\"\nTEMPERATURE=0.3\n").unwrap();
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load()
+            .unwrap();
+
+        let llm = &config.settings().llm;
+        assert_eq!("http://localhost:11434/v1", llm.base_url());
+        assert_eq!(Some(0.3), llm.temperature);
+        assert_eq!(120, llm.timeout);
+        // the local servers need no key
+        assert_eq!(None, config.get_llm_api_key().unwrap());
+
+        std::fs::write(&file, "[LLM]\nPROVIDER=\"dashscope\"\n").unwrap();
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[("REPORTER_ALI_DASHSCOPE_API_KEY", "sk-ali")]))
+            .load()
+            .unwrap();
+        assert_eq!(Some("sk-ali".to_owned()), config.get_llm_api_key().unwrap());
+
+        std::fs::write(
+            &file,
+            "[LLM]\nBASE_URL=\"localhost\"\nTEMPERATURE=3\nTIMEOUT=0\n",
+        )
+        .unwrap();
+        let res = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(env(&[]))
+            .load();
+        let Err(ConfigError::Invalid(errors)) = res else {
+            panic!("expected the config to be invalid");
+        };
+        assert_eq!(3, errors.len());
+        assert!(errors[0].starts_with("`LLM.BASE_URL` is not a url"));
+    }
+
+    #[test]
+    fn shared_config_snapshots() {
+        let load = |concurrency: &str| {
+            ConfigLoader::new()
+                .with_env(env(&[("REPORTER_TTS_CONCURRENCY", concurrency)]))
+                .load()
+        };
+        let shared = SharedConfig::new(load("1").unwrap());
+        let before = shared.get();
+
+        let after = shared.change(|_| load("3")).unwrap();
+        assert_eq!(3, after.get_tts_concurrency());
+        assert_eq!(3, shared.get().get_tts_concurrency());
+        // taken before the change
+        assert_eq!(1, before.get_tts_concurrency());
+
+        assert!(shared.change(|_| load("0")).is_err());
+        assert_eq!(3, shared.get().get_tts_concurrency());
     }
 }
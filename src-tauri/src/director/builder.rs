@@ -0,0 +1,160 @@
+use std::{env, path::PathBuf};
+
+use thiserror::Error;
+
+use crate::{
+    director::{Director, WORKSPACE_DIR, source::NewsSource},
+    limits::StageLimits,
+    mpeg::{ComposeTool, VoiceEditTool},
+    news::{cache::NewsCache, filter::NewsFilter},
+    subtitle::Subtitle,
+    tts::TTSService,
+    video::VideoEditor,
+};
+
+#[derive(Error, Debug)]
+pub enum DirectorBuildError {
+    #[error("the video editor has not been set")]
+    NoVideoEditor,
+    #[error("the compose tool has not been set")]
+    NoComposeTool,
+    #[error("subtitles are timed by the dubbing, a TTS must be set")]
+    SubtitleWithoutTTS,
+    #[error("the voice effect applies to the dubbing, a TTS must be set")]
+    VoiceEffectWithoutTTS,
+}
+
+/// Collects the components of a `Director`, `build` checks that they make a pipeline.
+///
+/// The video editor and the compose tool are required,
+/// without a TTS the video is a silent slideshow,
+/// without a subtitle nothing is burnt in, without a voice edit tool the voice is left as is.
+pub struct DirectorBuilder {
+    sources: Vec<NewsSource>,
+    tts: Option<Box<dyn TTSService + Sync + Send + 'static>>,
+    subtitle: Option<Box<dyn Subtitle + Sync + Send + 'static>>,
+    video_editor: Option<Box<dyn VideoEditor + Sync + Send + 'static>>,
+    voice_edit_tool: Option<Box<dyn VoiceEditTool + Sync + Send + 'static>>,
+    compose_tool: Option<Box<dyn ComposeTool + Sync + Send + 'static>>,
+    limits: Option<StageLimits>,
+    workspace_dir: PathBuf,
+    news_cache: Option<NewsCache>,
+    filter: NewsFilter,
+}
+
+impl DirectorBuilder {
+    pub fn new(sources: Vec<NewsSource>) -> Self {
+        Self {
+            sources,
+            tts: None,
+            subtitle: None,
+            video_editor: None,
+            voice_edit_tool: None,
+            compose_tool: None,
+            limits: None,
+            workspace_dir: env::temp_dir().join(WORKSPACE_DIR),
+            news_cache: None,
+            filter: NewsFilter::default(),
+        }
+    }
+
+    /// Add a source, replacing the one of the same name.
+    pub fn with_source(mut self, source: NewsSource) -> Self {
+        self.sources.retain(|s| s.name != source.name);
+        self.sources.push(source);
+        self
+    }
+
+    pub fn with_tts(self, tts: impl TTSService + Sync + Send + 'static) -> Self {
+        Self {
+            tts: Some(Box::new(tts)),
+            ..self
+        }
+    }
+
+    pub fn with_subtitle(self, subtitle: impl Subtitle + Sync + Send + 'static) -> Self {
+        Self {
+            subtitle: Some(Box::new(subtitle)),
+            ..self
+        }
+    }
+
+    pub fn with_video_editor(self, video_editor: impl VideoEditor + Sync + Send + 'static) -> Self {
+        Self {
+            video_editor: Some(Box::new(video_editor)),
+            ..self
+        }
+    }
+
+    pub fn with_voice_edit_tool(self, tool: impl VoiceEditTool + Sync + Send + 'static) -> Self {
+        Self {
+            voice_edit_tool: Some(Box::new(tool)),
+            ..self
+        }
+    }
+
+    pub fn with_compose_tool(self, tool: impl ComposeTool + Sync + Send + 'static) -> Self {
+        Self {
+            compose_tool: Some(Box::new(tool)),
+            ..self
+        }
+    }
+
+    /// Share the LLM and TTS concurrency limits with other directors,
+    /// the ffmpeg limit is applied by wrapping the runner in a `LimitedRunner`.
+    pub fn with_limits(self, limits: StageLimits) -> Self {
+        Self {
+            limits: Some(limits),
+            ..self
+        }
+    }
+
+    /// The dir the workspaces of the news are kept in, `temp/workspace` in the system temp dir
+    /// by default.
+    pub fn with_workspace_dir(self, workspace_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            workspace_dir: workspace_dir.into(),
+            ..self
+        }
+    }
+
+    /// Keep the hot news lists in `cache`, they are fetched on every call without it.
+    pub fn with_news_cache(self, cache: NewsCache) -> Self {
+        Self {
+            news_cache: Some(cache),
+            ..self
+        }
+    }
+
+    /// Filter and rank the hot news lists by `filter`, they are shown as crawled without it.
+    pub fn with_filter(self, filter: NewsFilter) -> Self {
+        Self { filter, ..self }
+    }
+
+    pub fn build(self) -> Result<Director, DirectorBuildError> {
+        let video_editor = self.video_editor.ok_or(DirectorBuildError::NoVideoEditor)?;
+        let compose_tool = self.compose_tool.ok_or(DirectorBuildError::NoComposeTool)?;
+
+        if self.tts.is_none() {
+            if self.subtitle.is_some() {
+                return Err(DirectorBuildError::SubtitleWithoutTTS);
+            }
+            if self.voice_edit_tool.is_some() {
+                return Err(DirectorBuildError::VoiceEffectWithoutTTS);
+            }
+        }
+
+        Ok(Director {
+            sources: self.sources,
+            tts: self.tts,
+            subtitle: self.subtitle,
+            video_editor,
+            voice_edit_tool: self.voice_edit_tool,
+            compose_tool,
+            limits: self.limits,
+            workspace_dir: self.workspace_dir,
+            news_cache: self.news_cache,
+            filter: self.filter,
+        })
+    }
+}
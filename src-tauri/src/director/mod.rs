@@ -1,20 +1,41 @@
+pub mod builder;
 pub mod source;
-use std::{io, path::PathBuf, time::Duration};
+pub mod workspace;
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use hound::WavReader;
-use nanoid::nanoid;
-use tauri::AppHandle;
+use serde::{Deserialize, Serialize};
 use thiserror::Error;
-use tokio::fs;
+use tokio::{
+    fs,
+    sync::{Semaphore, SemaphorePermit},
+};
+use tokio_util::sync::CancellationToken;
 
 use crate::{
-    director::source::{NewsSource, SourceName},
+    config::{ConfigError, GlobalConfig},
+    director::{
+        builder::{DirectorBuildError, DirectorBuilder},
+        source::{NewsSource, SourceContext, SourceName, SourceRegistry},
+        workspace::{InputHash, Workspace},
+    },
+    limits::{StageLimits, acquire},
     mpeg::{
         ComposeTool, ComposeToolError, VideoEditToolError, VoiceEditTool, VoiceEditToolError,
-        ffmpeg_tool::{FFmpeg4Compose, FFmpeg4Video, FFmpeg4Voice},
+        ffmpeg_tool::{FFmpeg4Compose, FFmpeg4Video, FFmpeg4Voice, SharedRunner},
+    },
+    news::{
+        Channel, NewsCrawlerError, NewsMaterial, NewsMaterialError, NewsPage, NewsTitle,
+        cache::NewsCache, filter::NewsFilter, local_news, stamp_crawled,
     },
-    news::{NewsMaterial, NewsMaterialError, NewsTitle},
+    progress::{ProgressEvent, ProgressReporter, Stage},
     subtitle::{SingleSubtitle, Subtitle, SubtitleError, srt::SrtSubtitle},
+    time::now,
     tts::{TTSError, TTSFile, TTSService, ali_tts::AliTTS, get_wav_len},
     video::{VideoEditor, VideoEditorError, junior_editor::JuniorEditor},
 };
@@ -23,13 +44,15 @@ use crate::{
 pub enum DirectorError {
     #[error("fail occurred: {0}")]
     Source(String),
+    #[error("get hot news failed: {0}")]
+    Crawler(#[from] NewsCrawlerError),
     #[error("get material failed: {0}")]
     Material(#[from] NewsMaterialError),
     #[error("tts error: {0}")]
     TTS(#[from] TTSError),
     #[error("subtitle error: {0}")]
     Subtitle(#[from] SubtitleError),
-    #[error("file error")]
+    #[error("file error: {0}")]
     IO(#[from] io::Error),
     #[error("video editor error: {0}")]
     VideoEditor(#[from] VideoEditorError),
@@ -41,241 +64,587 @@ pub enum DirectorError {
     ComposeTool(#[from] ComposeToolError),
     #[error("wav error: {0}")]
     WavReader(#[from] hound::Error),
+    #[error("director is misconfigured: {0}")]
+    Build(#[from] DirectorBuildError),
+    #[error("config error: {0}")]
+    Config(#[from] ConfigError),
+    #[error("cancelled")]
+    Cancelled,
 }
 
 pub struct NewsShortVideo {
     pub title: String,
     pub path: PathBuf,
+    /// the news the video is made of
+    pub news_title: NewsTitle,
+    pub material: NewsMaterial,
+    /// `None` when there is no dubbing
+    pub voice: Option<String>,
+    /// the duration of each summary sentence
+    pub durations: Vec<Duration>,
+    /// the duration of the whole video
+    pub duration: Duration,
+}
+
+/// The hot news of several sources in one list, and the sources left out because they failed.
+#[derive(Clone, Debug, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RankedNews {
+    pub titles: Vec<NewsTitle>,
+    pub failures: Vec<SourceFailure>,
+}
+
+/// A source whose hot news could not be had.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceFailure {
+    pub source: SourceName,
+    pub message: String,
 }
 
 pub type DirectorResult<T> = Result<T, DirectorError>;
 
+/// The dir of the workspaces in the data dir, or in the system temp dir by default
+pub(crate) const WORKSPACE_DIR: &str = "temp/workspace";
+/// The dir of the hot news lists in the data dir
+const NEWS_CACHE_DIR: &str = "temp/news";
+/// The dir of the TTS and subtitle files in the data dir
+const TEMP_DIR: &str = "temp";
+
 pub struct Director {
     sources: Vec<NewsSource>,
     tts: Option<Box<dyn TTSService + Sync + Send + 'static>>,
     subtitle: Option<Box<dyn Subtitle + Sync + Send + 'static>>,
-    video_editor: Option<Box<dyn VideoEditor + Sync + Send + 'static>>,
+    video_editor: Box<dyn VideoEditor + Sync + Send + 'static>,
     voice_edit_tool: Option<Box<dyn VoiceEditTool + Sync + Send + 'static>>,
-    compose_tool: Option<Box<dyn ComposeTool + Sync + Send + 'static>>,
+    compose_tool: Box<dyn ComposeTool + Sync + Send + 'static>,
+    limits: Option<StageLimits>,
+    workspace_dir: PathBuf,
+    news_cache: Option<NewsCache>,
+    filter: NewsFilter,
 }
 
 impl Director {
+    /// The builder with every component of the app set up from `config` and its profile `profile`,
+    /// the default profile when `None`. ffmpeg is run by `ffmpeg`.
+    /// The workspaces and the caches are kept in `data_dir`, the app data dir.
     pub fn default(
-        tts_url: String,
-        ali_key: String,
-        deepseek_api_key: String,
-        app: AppHandle,
-    ) -> Self {
-        let tts = AliTTS::new(tts_url, ali_key);
-        let subtitle = SrtSubtitle::new();
-        let video_editor = JuniorEditor::new(FFmpeg4Video(app.clone()));
-        let voice_edit_tool = FFmpeg4Voice(app.clone());
-        let compose_tool = FFmpeg4Compose(app.clone());
-
-        Self::new(deepseek_api_key)
+        config: &GlobalConfig,
+        profile: Option<&str>,
+        ffmpeg: SharedRunner,
+        data_dir: &Path,
+    ) -> DirectorResult<DirectorBuilder> {
+        let profile = config.profile(profile)?;
+        // the local news can be made whatever the sources listed
+        let mut enabled = config.settings().sources.clone();
+        if !enabled.iter().any(|name| name == local_news::SOURCE_NAME) {
+            enabled.push(local_news::SOURCE_NAME.to_owned());
+        }
+        let sources = SourceRegistry::from_settings(config.settings()).build(
+            &enabled,
+            &SourceContext {
+                config,
+                profile: &profile,
+            },
+        )?;
+        let tts = AliTTS::new(config.get_tts_url()?, config.get_ali_dashscope_api_key()?)
+            .with_voice(&profile.voice)
+            .with_model(&profile.tts_model)
+            .with_temp_dir(data_dir.join(TEMP_DIR).display().to_string());
+        let subtitle = SrtSubtitle::new().with_temp_dir(data_dir.join(TEMP_DIR));
+        let video_editor = JuniorEditor::new(
+            FFmpeg4Video::new(ffmpeg.clone()).with_resolution(profile.resolution),
+        );
+        let voice_edit_tool = FFmpeg4Voice::new(ffmpeg.clone());
+        let compose_tool = FFmpeg4Compose::new(ffmpeg)
+            .with_subtitle_style(profile.subtitle_style)
+            .with_watermark(profile.watermark);
+
+        Ok(DirectorBuilder::new(sources)
             .with_tts(tts)
             .with_subtitle(subtitle)
             .with_video_editor(video_editor)
             .with_voice_edit_tool(voice_edit_tool)
             .with_compose_tool(compose_tool)
+            .with_filter(NewsFilter::new(&profile.filter))
+            .with_workspace_dir(data_dir.join(WORKSPACE_DIR))
+            .with_news_cache(NewsCache::new(
+                data_dir.join(NEWS_CACHE_DIR),
+                config.get_hot_news_ttl(),
+            )))
     }
 }
 
 impl Director {
-    pub fn new(deepseek_api_key: impl Into<String>) -> Self {
-        Self {
-            sources: Self::get_all_sources(deepseek_api_key),
-            tts: None,
-            subtitle: None,
-            video_editor: None,
-            voice_edit_tool: None,
-            compose_tool: None,
-        }
+    /// The hot news of the source, the cached list unless it is stale or `refresh`,
+    /// filtered and ranked by the rules of the profile.
+    /// `rendered` tells whether the news at a url is in the library.
+    pub async fn get_hot_news_list(
+        &self,
+        source_name: &SourceName,
+        refresh: bool,
+        rendered: &dyn Fn(&str) -> bool,
+    ) -> DirectorResult<Vec<NewsTitle>> {
+        let list = self.crawl_hot_news_list(source_name, refresh).await?;
+        Ok(self.filter.apply(list, rendered))
     }
 
-    fn get_all_sources(deepseek_api_key: impl Into<String>) -> Vec<NewsSource> {
-        let mut sources = vec![];
-
-        sources.push(NewsSource {
-            name: crate::news::pengpai_news::SOURCE_NAME.to_owned(),
-            crawler: Box::new(crate::news::pengpai_news::PengPaiNews::new()),
-            extractor: Box::new(
-                crate::news::pengpai_news::PengPaiNewsMaterialExtractor::from_deepseek(
-                    deepseek_api_key.into(),
-                ),
-            ),
-        });
+    /// The hot news of all of `source_names` in one list, filtered and ranked by the rules
+    /// of the profile, the same news listed by several sources shown once.
+    /// A source that fails is left out and listed in the failures,
+    /// the error of the first one is returned when they all fail.
+    pub async fn get_ranked_news(
+        &self,
+        source_names: &[SourceName],
+        refresh: bool,
+        rendered: &dyn Fn(&str) -> bool,
+    ) -> DirectorResult<RankedNews> {
+        let mut list = vec![];
+        let mut failures = vec![];
+        let mut first_error = None;
+        for source_name in source_names {
+            match self.crawl_hot_news_list(source_name, refresh).await {
+                Ok(titles) => list.extend(titles),
+                Err(e) => {
+                    failures.push(SourceFailure {
+                        source: source_name.clone(),
+                        message: e.to_string(),
+                    });
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
 
-        sources
+        match first_error {
+            Some(e) if failures.len() == source_names.len() => Err(e),
+            _ => Ok(RankedNews {
+                titles: self.filter.apply(list, rendered),
+                failures,
+            }),
+        }
     }
 
-    pub fn with_tts(self, tts: impl TTSService + Sync + Send + 'static) -> Self {
-        Self {
-            tts: Some(Box::new(tts)),
-            ..self
-        }
+    /// The hot news of the source as crawled.
+    async fn crawl_hot_news_list(
+        &self,
+        source_name: &SourceName,
+        refresh: bool,
+    ) -> DirectorResult<Vec<NewsTitle>> {
+        let source = self.source(source_name)?;
+
+        let list = match &self.news_cache {
+            Some(cache) => {
+                cache
+                    .hot_news_list(&source.name, &*source.crawler, refresh)
+                    .await?
+            }
+            None => {
+                let mut list = source.crawler.get_hot_news_list().await?;
+                stamp_crawled(&mut list, now());
+                list
+            }
+        };
+
+        Ok(list)
     }
 
-    pub fn with_subtitle(self, subtitle: impl Subtitle + Sync + Send + 'static) -> Self {
-        Self {
-            subtitle: Some(Box::new(subtitle)),
-            ..self
-        }
+    /// The channels of the source, empty when it has none.
+    pub fn channels(&self, source_name: &SourceName) -> DirectorResult<Vec<Channel>> {
+        Ok(self.source(source_name)?.crawler.channels())
     }
 
-    pub fn with_voice_edit_tool(self, tool: impl VoiceEditTool + Sync + Send + 'static) -> Self {
-        Self {
-            voice_edit_tool: Some(Box::new(tool)),
-            ..self
-        }
+    /// The `page` of the news of `channel`, from 1, never cached.
+    pub async fn get_channel_news(
+        &self,
+        source_name: &SourceName,
+        channel: &str,
+        page: u32,
+    ) -> DirectorResult<NewsPage> {
+        let source = self.source(source_name)?;
+        let mut page = source.crawler.get_channel_news(channel, page).await?;
+        stamp_crawled(&mut page.titles, now());
+        Ok(page)
     }
 
-    pub fn with_compose_tool(self, tool: impl ComposeTool + Sync + Send + 'static) -> Self {
-        Self {
-            compose_tool: Some(Box::new(tool)),
-            ..self
-        }
+    /// The `page` of the news found by `keyword`, from 1, never cached.
+    pub async fn search_news(
+        &self,
+        source_name: &SourceName,
+        keyword: &str,
+        page: u32,
+    ) -> DirectorResult<NewsPage> {
+        let source = self.source(source_name)?;
+        let mut page = source.crawler.search_news(keyword, page).await?;
+        stamp_crawled(&mut page.titles, now());
+        Ok(page)
     }
 
-    pub fn with_video_editor(self, video_editor: impl VideoEditor + Sync + Send + 'static) -> Self {
-        Self {
-            video_editor: Some(Box::new(video_editor)),
-            ..self
-        }
+    fn source(&self, source_name: &SourceName) -> DirectorResult<&NewsSource> {
+        self.sources
+            .iter()
+            .find(|s| s.name == *source_name)
+            .ok_or(DirectorError::Source(format!(
+                "Failed to find source: {}",
+                source_name
+            )))
     }
 
-    pub async fn get_hot_news_list(&self, source_name: &SourceName) -> Vec<NewsTitle> {
-        let source = self.sources.iter().find(|s| s.name == *source_name);
-        if source.is_none() {
-            return vec![];
+    /// Generate the short video of `news_title`, reporting the stages to `progress`.
+    /// The artifacts of each stage are kept in the `Workspace` of the news,
+    /// so a rerun only redoes the stages whose input changed.
+    /// When `cancel` is cancelled the running stage is stopped, its partial files are removed
+    /// and `DirectorError::Cancelled` is returned.
+    pub async fn shot_single(
+        &self,
+        news_title: NewsTitle,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<NewsShortVideo> {
+        let res = self.shoot(news_title, progress, cancel).await;
+
+        if cancel.is_cancelled() {
+            return Err(DirectorError::Cancelled);
         }
 
-        let source = source.unwrap();
-
-        source.crawler.get_hot_news_list().await
+        res
     }
 
-    pub async fn shot_single(&self, news_title: NewsTitle) -> DirectorResult<NewsShortVideo> {
-        // let temp_dir = self.get_temp_dir().await?;
+    /// The stages of `shot_single`.
+    async fn shoot(
+        &self,
+        news_title: NewsTitle,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<NewsShortVideo> {
+        let source = self.source(&news_title.source)?;
 
-        let source = self
-            .sources
-            .iter()
-            .find(|s| s.name == *news_title.source)
-            .ok_or(DirectorError::Source(format!(
-                "Failed to find source: {}",
-                news_title.source
-            )))?;
+        let mut workspace = Workspace::open(&self.workspace_dir, &news_title.url).await?;
 
-        let material = source.extractor.get_material(&news_title).await?;
+        let material = self
+            .get_material(source, &news_title, &mut workspace, progress, cancel)
+            .await?;
+        // a local text or a feed entry may have no picture, fail before dubbing it
+        if material.pics.is_empty() {
+            return Err(VideoEditorError::Image("no pics to compose".to_owned()).into());
+        }
 
-        let dubbing_path = if self.tts.is_some() {
-            Some(self.gen_dubbing(&material).await?)
+        let dubbing = if self.tts.is_some() {
+            Some(
+                self.gen_dubbing(&material, &mut workspace, progress, cancel)
+                    .await?,
+            )
         } else {
             None
         };
 
-        let subtitle_path = if let Some(ref subtitle_handler) = self.subtitle
-            && let Some(ref dubbing_subtitle) = dubbing_path
+        let subtitle = if let Some(ref subtitle_handler) = self.subtitle
+            && let Some(ref dubbing) = dubbing
         {
             Some(
-                subtitle_handler
-                    .write_subtitle(&dubbing_subtitle.tts_files)
+                self.gen_subtitle(&**subtitle_handler, dubbing, &mut workspace, progress)
                     .await?,
             )
         } else {
             None
         };
 
-        let video_path = {
-            let dur = if let Some(ref dubbing) = dubbing_path {
-                Some(get_wav_len(&dubbing.dubbing_path).await?)
-            } else {
-                None
-            };
-
-            if self.video_editor.is_some() {
-                Some(self.gen_video(&material, dur).await?)
-            } else {
-                None
-            }
+        let dur = if let Some(ref dubbing) = dubbing {
+            Some(get_wav_len(&dubbing.dubbing_path).await?)
+        } else {
+            None
         };
+        let durations = dubbing
+            .as_ref()
+            .map(|d| d.tts_files.iter().map(|s| s.duration).collect())
+            .unwrap_or_default();
 
-        let video_path =
-            video_path.ok_or(DirectorError::VideoEditor(VideoEditorError::Duration))?;
+        let video = self
+            .gen_video(&material, dur, &mut workspace, progress, cancel)
+            .await?;
 
         let final_path = self
             .compose_all(
-                video_path,
-                dubbing_path.unwrap().dubbing_path,
-                subtitle_path.unwrap(),
+                &video,
+                dubbing.as_ref(),
+                subtitle.as_ref(),
+                &mut workspace,
+                progress,
+                cancel,
             )
             .await?;
 
         Ok(NewsShortVideo {
             title: material.title.clone(),
             path: final_path,
+            news_title,
+            voice: self.tts.as_ref().map(|tts| tts.voice()),
+            durations,
+            duration: dur.unwrap_or_else(|| Self::silent_duration(&material)),
+            material,
         })
+    }
 
+    /// Wait for a permit of the stage limit chosen by `semaphore`,
+    /// `None` when the director has no limits.
+    async fn acquire<'a>(
+        &'a self,
+        semaphore: impl FnOnce(&'a StageLimits) -> &'a Arc<Semaphore>,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<Option<SemaphorePermit<'a>>> {
+        let Some(limits) = &self.limits else {
+            return Ok(None);
+        };
+
+        acquire(semaphore(limits), cancel)
+            .await
+            .map(Some)
+            .ok_or(DirectorError::Cancelled)
     }
 
-    async fn gen_dubbing(&self, material: &NewsMaterial) -> DirectorResult<DubbingSubtitle> {
-        let mut tts_files = self
-            .tts
-            .as_ref()
-            .expect("Has no TTS setted")
-            .tts(&material.summary)
-            .await?;
+    async fn get_material(
+        &self,
+        source: &NewsSource,
+        news_title: &NewsTitle,
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<NewsMaterial> {
+        let input = InputHash::new("material")
+            .with(source.extractor.cache_key())
+            .with(&news_title.source)
+            .with(&news_title.url)
+            .with(&news_title.title)
+            .finish();
+
+        if let Some((_, material)) = workspace.cached("material", &input) {
+            progress.report(ProgressEvent::StageCached {
+                stage: Stage::Material,
+            });
+            return Ok(material);
+        }
+
+        let permit = self.acquire(|l| &l.llm, cancel).await?;
+        progress.report(ProgressEvent::StageStarted {
+            stage: Stage::Material,
+        });
+        let material = cancel
+            .run_until_cancelled(source.extractor.get_material(news_title))
+            .await
+            .ok_or(DirectorError::Cancelled)??;
+        drop(permit);
+
+        workspace.store("material", &input, None, &material).await?;
+        progress.report(ProgressEvent::StageFinished {
+            stage: Stage::Material,
+        });
+
+        Ok(material)
+    }
+
+    /// Synthesize the sentences that are not cached yet, then put all of them together.
+    async fn gen_dubbing(
+        &self,
+        material: &NewsMaterial,
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<DubbingSubtitle> {
+        let Some(tts) = &self.tts else {
+            return Err(DirectorError::TTS(TTSError::NoSet));
+        };
+        let tts_key = tts.cache_key();
+        let effect = if self.voice_edit_tool.is_some() {
+            "cartoned"
+        } else {
+            ""
+        };
 
-        // carton tts files
-        for tts_file in tts_files.iter_mut() {
-            let mut new_file_path = tts_file.path.clone();
-
-            if let Some(tool) = &self.voice_edit_tool {
-                new_file_path.set_file_name(format!(
-                    "{}-cartoned.{}",
-                    new_file_path.file_stem().unwrap().to_str().unwrap(),
-                    new_file_path.extension().unwrap().to_str().unwrap()
-                ));
-                tool.cartoned_voice(&tts_file.path, &new_file_path).await?;
-                _ = fs::remove_file(&tts_file.path).await;
+        let inputs: Vec<String> = material
+            .summary
+            .iter()
+            .map(|text| {
+                InputHash::new("tts")
+                    .with(&tts_key)
+                    .with(effect)
+                    .with(text)
+                    .finish()
+            })
+            .collect();
+        let input = inputs
+            .iter()
+            .fold(InputHash::new("dubbing"), |hash, input| hash.with(input))
+            .finish();
+
+        let mut sentences: Vec<Option<(PathBuf, f64)>> = inputs
+            .iter()
+            .map(|input| {
+                workspace
+                    .cached::<f64>(&Self::sentence_key(input), input)
+                    .and_then(|(path, secs)| Some((path?, secs)))
+            })
+            .collect();
+        let missing: Vec<usize> = (0..sentences.len())
+            .filter(|&i| sentences[i].is_none())
+            .collect();
+
+        if missing.is_empty()
+            && let Some((Some(path), ())) = workspace.cached("dubbing", &input)
+        {
+            progress.report(ProgressEvent::StageCached {
+                stage: Stage::Dubbing,
+            });
+            let durations = sentences.into_iter().flatten().map(|(_, secs)| secs);
+            return Ok(DubbingSubtitle {
+                dubbing_path: path,
+                tts_files: Self::subtitles(material, durations),
+                input,
+            });
+        }
+
+        progress.report(ProgressEvent::StageStarted {
+            stage: Stage::Dubbing,
+        });
+
+        if !missing.is_empty() {
+            let permit = self.acquire(|l| &l.tts, cancel).await?;
+            let texts = missing
+                .iter()
+                .map(|&i| material.summary[i].clone())
+                .collect();
+            let tts_files = tts.tts(&texts, progress, cancel).await?;
+            drop(permit);
+
+            let mut tts_files = tts_files.into_iter();
+            for &i in &missing {
+                let Some(tts_file) = tts_files.next() else {
+                    break;
+                };
+
+                match self
+                    .keep_sentence(tts_file, &inputs[i], workspace, progress, cancel)
+                    .await
+                {
+                    Ok(sentence) => sentences[i] = Some(sentence),
+                    Err(e) => {
+                        Self::remove_tts_files(&tts_files.collect()).await;
+                        return Err(e);
+                    }
+                }
             }
+        }
 
-            tts_file.duration = get_wav_len(&new_file_path).await?;
-            tts_file.path = new_file_path;
+        // the sentences done so far stay cached for the next run
+        if cancel.is_cancelled() {
+            return Err(DirectorError::Cancelled);
         }
 
+        let sentences: Vec<(PathBuf, f64)> =
+            sentences
+                .into_iter()
+                .collect::<Option<_>>()
+                .ok_or(DirectorError::TTS(TTSError::HandleFailed(
+                    "a sentence was not synthesized".to_owned(),
+                )))?;
+
         // compose up
-        let compose_path = self.compose_audio(&tts_files).await?;
-
-        let subtitles: Vec<SingleSubtitle> = tts_files
-            .into_iter()
-            .map(|tts| {
-                _ = std::fs::remove_file(&tts.path);
-                return SingleSubtitle {
-                    text: tts.text.clone(),
-                    duration: tts.duration,
-                };
-            })
-            .collect();
+        let files = sentences.iter().map(|(path, _)| path.clone()).collect();
+        let path = self
+            .compose_audio(&files, workspace.file("dubbing.wav"))
+            .await?;
+        workspace.store("dubbing", &input, Some(&path), &()).await?;
+
+        progress.report(ProgressEvent::StageFinished {
+            stage: Stage::Dubbing,
+        });
 
         Ok(DubbingSubtitle {
-            dubbing_path: compose_path,
-            tts_files: subtitles,
+            dubbing_path: path,
+            tts_files: Self::subtitles(material, sentences.into_iter().map(|(_, secs)| secs)),
+            input,
         })
     }
 
-    async fn compose_audio(&self, tts_files: &Vec<TTSFile>) -> DirectorResult<PathBuf> {
-        if tts_files.is_empty() {
-            return Err(DirectorError::TTS(TTSError::NoSet));
+    /// Apply the voice effect to a synthesized sentence and store it in the workspace,
+    /// returns its path and its duration in seconds.
+    async fn keep_sentence(
+        &self,
+        tts_file: TTSFile,
+        input: &str,
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<(PathBuf, f64)> {
+        let mut path = tts_file.path;
+
+        // carton tts file
+        if let Some(tool) = &self.voice_edit_tool {
+            let new_file_path = path.with_file_name(format!(
+                "{}-cartoned.{}",
+                path.file_stem().unwrap_or_default().to_string_lossy(),
+                path.extension().unwrap_or_default().to_string_lossy()
+            ));
+            let res = tool
+                .cartoned_voice(&path, &new_file_path, progress, cancel)
+                .await;
+            _ = fs::remove_file(&path).await;
+            res?;
+
+            path = new_file_path;
+        }
+
+        let duration = match get_wav_len(&path).await {
+            Ok(duration) => duration.as_secs_f64(),
+            Err(e) => {
+                _ = fs::remove_file(&path).await;
+                return Err(e.into());
+            }
+        };
+
+        let path = workspace
+            .store(&Self::sentence_key(input), input, Some(&path), &duration)
+            .await?
+            .expect("stored with a file");
+
+        Ok((path, duration))
+    }
+
+    /// The same sentence with the same TTS settings is synthesized once.
+    fn sentence_key(input: &str) -> String {
+        format!("tts-{}", &input[..16])
+    }
+
+    fn subtitles(
+        material: &NewsMaterial,
+        durations: impl Iterator<Item = f64>,
+    ) -> Vec<SingleSubtitle> {
+        material
+            .summary
+            .iter()
+            .zip(durations)
+            .map(|(text, secs)| SingleSubtitle {
+                text: text.clone(),
+                duration: Duration::from_secs_f64(secs),
+            })
+            .collect()
+    }
+
+    async fn remove_tts_files(tts_files: &Vec<TTSFile>) {
+        for tts_file in tts_files {
+            _ = fs::remove_file(&tts_file.path).await;
         }
+    }
 
-        let spec = {
-            let first = tts_files.get(0).unwrap();
-            let reader = WavReader::open(&first.path)?;
-            reader.spec()
+    async fn compose_audio(
+        &self,
+        tts_files: &Vec<PathBuf>,
+        output: PathBuf,
+    ) -> DirectorResult<PathBuf> {
+        let Some(first) = tts_files.first() else {
+            return Err(DirectorError::TTS(TTSError::NoSet));
         };
 
+        let spec = WavReader::open(first)?.spec();
+
         let silence = {
             let silence_duration = 0.3;
             let num_samples = (spec.sample_rate as f32 * silence_duration) as usize;
@@ -285,145 +654,692 @@ impl Director {
         let mut compose_wav = vec![];
 
         for tts_file in tts_files {
-            let mut reader = WavReader::open(&tts_file.path).unwrap();
-            let samples: Vec<i32> = reader.samples::<i16>().map(|s| s.unwrap() as i32).collect();
+            let mut reader = WavReader::open(tts_file)?;
+            // a truncated file or samples wider than 16 bits are a `WavReader` error
+            let samples = reader
+                .samples::<i16>()
+                .collect::<Result<Vec<i16>, hound::Error>>()?;
 
-            compose_wav.extend(samples.clone());
+            compose_wav.extend(samples.into_iter().map(i32::from));
             compose_wav.extend(silence.clone());
         }
 
-        let final_wav = self
-            .get_temp_dir()
-            .await?
-            .join(format!("{}-final.wav", nanoid!()));
-
-        let mut writer = hound::WavWriter::create(&final_wav, spec)?;
+        let mut writer = hound::WavWriter::create(&output, spec)?;
 
         for sample in compose_wav {
-            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample)?;
         }
+        writer.finalize()?;
 
-        Ok(final_wav)
+        Ok(output)
     }
 
-    async fn compose_all(
+    async fn gen_subtitle(
         &self,
-        video: PathBuf,
-        dubbing: PathBuf,
-        subtitle: PathBuf,
-    ) -> DirectorResult<PathBuf> {
-        let tool = self
-            .compose_tool
-            .as_ref()
-            .expect("compose tool has not set");
+        subtitle_handler: &(dyn Subtitle + Sync + Send),
+        dubbing: &DubbingSubtitle,
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+    ) -> DirectorResult<StageOutput> {
+        let input = InputHash::new("subtitle").with(&dubbing.input).finish();
+
+        if let Some((Some(path), ())) = workspace.cached("subtitle", &input) {
+            progress.report(ProgressEvent::StageCached {
+                stage: Stage::Subtitle,
+            });
+            return Ok(StageOutput { path, input });
+        }
 
-        let output_path = self
-            .get_temp_dir()
+        progress.report(ProgressEvent::StageStarted {
+            stage: Stage::Subtitle,
+        });
+        let path = subtitle_handler.write_subtitle(&dubbing.tts_files).await?;
+        let path = workspace
+            .store("subtitle", &input, Some(&path), &())
             .await?
-            .join(format!("{}-final.mp4", nanoid::nanoid!()));
+            .expect("stored with a file");
+        progress.report(ProgressEvent::StageFinished {
+            stage: Stage::Subtitle,
+        });
 
-        tool.compose_all(&video, &dubbing, &subtitle, &output_path)
-            .await?;
+        Ok(StageOutput { path, input })
+    }
+
+    async fn compose_all(
+        &self,
+        video: &StageOutput,
+        dubbing: Option<&DubbingSubtitle>,
+        subtitle: Option<&StageOutput>,
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<PathBuf> {
+        // an absent stage hashes as empty
+        let input = InputHash::new("final")
+            .with(self.compose_tool.cache_key())
+            .with(&video.input)
+            .with(dubbing.map_or("", |d| &d.input))
+            .with(subtitle.map_or("", |s| &s.input))
+            .finish();
+
+        if let Some((Some(path), ())) = workspace.cached("final", &input) {
+            progress.report(ProgressEvent::StageCached {
+                stage: Stage::Compose,
+            });
+            return Ok(path);
+        }
 
-        _ = fs::remove_file(video).await;
-        _ = fs::remove_file(dubbing).await;
-        _ = fs::remove_file(subtitle).await;
+        progress.report(ProgressEvent::StageStarted {
+            stage: Stage::Compose,
+        });
+        let output_path = workspace.file("final.mp4");
+        self.compose_tool
+            .compose_all(
+                &video.path,
+                dubbing.map(|d| &d.dubbing_path),
+                subtitle.map(|s| &s.path),
+                &output_path,
+                progress,
+                cancel,
+            )
+            .await?;
+        workspace
+            .store("final", &input, Some(&output_path), &())
+            .await?;
+        progress.report(ProgressEvent::StageFinished {
+            stage: Stage::Compose,
+        });
 
         Ok(output_path)
     }
 
+    /// Download the pictures and render them as a slideshow lasting `dur`.
     async fn gen_video(
         &self,
         material: &NewsMaterial,
         dur: Option<Duration>,
-    ) -> DirectorResult<PathBuf> {
-        if self.video_editor.is_none() {
-            return Err(DirectorError::VideoEditor(VideoEditorError::NoSet));
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<StageOutput> {
+        let editor = &self.video_editor;
+
+        let time = dur.unwrap_or_else(|| Self::silent_duration(material));
+        let pic_urls = editor.pick_pics(&material.pic_urls(), time);
+        let input = pic_urls
+            .iter()
+            .fold(
+                InputHash::new("slideshow")
+                    .with(editor.cache_key())
+                    .with(time.as_millis().to_string()),
+                |hash, url| hash.with(url),
+            )
+            .finish();
+
+        if let Some((Some(path), ())) = workspace.cached("slideshow", &input) {
+            progress.report(ProgressEvent::StageCached {
+                stage: Stage::Video,
+            });
+            return Ok(StageOutput { path, input });
         }
 
-        let time = if let Some(time) = dur {
-            time
-        } else {
-            Duration::from_secs((material.pics.len() * 2) as u64)
-        };
+        progress.report(ProgressEvent::StageStarted {
+            stage: Stage::Video,
+        });
+        let pics = self
+            .download_pics(&**editor, &pic_urls, workspace, progress, cancel)
+            .await?;
 
-        let path = self
-            .video_editor
-            .as_ref()
-            .unwrap()
-            .do_edit(material, time)
+        let path = workspace.file("slideshow.mp4");
+        editor.compose_pics(&pics, &path, progress, cancel).await?;
+        workspace
+            .store("slideshow", &input, Some(&path), &())
             .await?;
+        progress.report(ProgressEvent::StageFinished {
+            stage: Stage::Video,
+        });
 
-        Ok(path)
+        Ok(StageOutput { path, input })
     }
 
-    async fn get_temp_dir(&self) -> DirectorResult<PathBuf> {
-        let temp_dir = PathBuf::from(".").join("temp").join(nanoid!(10));
+    /// Download each picture once, the ones in the workspace are not downloaded again.
+    async fn download_pics(
+        &self,
+        editor: &(dyn VideoEditor + Sync + Send),
+        pic_urls: &[String],
+        workspace: &mut Workspace,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> DirectorResult<Vec<PathBuf>> {
+        let mut pics = vec![];
+
+        for (i, pic_url) in pic_urls.iter().enumerate() {
+            let input = InputHash::new("pic").with(pic_url).finish();
+            let key = format!("pic-{}", &input[..16]);
+
+            let path = match workspace.cached::<()>(&key, &input) {
+                Some((Some(path), ())) => path,
+                _ => {
+                    let path = cancel
+                        .run_until_cancelled(editor.save_pic(pic_url, workspace.file(&key)))
+                        .await
+                        .ok_or(DirectorError::Cancelled)??;
+                    workspace
+                        .store(&key, &input, Some(&path), &())
+                        .await?
+                        .expect("stored with a file")
+                }
+            };
+            pics.push(path);
 
-        if !temp_dir.exists() {
-            fs::create_dir_all(&temp_dir).await?;
+            progress.report(ProgressEvent::ImageDownloaded {
+                index: i + 1,
+                total: pic_urls.len(),
+            });
         }
 
-        Ok(temp_dir)
+        Ok(pics)
+    }
+
+    /// Without dubbing every picture is shown for 2 seconds.
+    fn silent_duration(material: &NewsMaterial) -> Duration {
+        Duration::from_secs((material.pics.len() * 2) as u64)
     }
 }
 struct DubbingSubtitle {
     dubbing_path: PathBuf,
     tts_files: Vec<SingleSubtitle>,
+    /// the `InputHash` of the dubbing
+    input: String,
+}
+
+/// A file made by a stage and the `InputHash` it was made from.
+struct StageOutput {
+    path: PathBuf,
+    input: String,
 }
 
 #[cfg(test)]
 mod test {
 
+    use std::sync::{
+        Mutex,
+        atomic::{AtomicUsize, Ordering},
+    };
+
+    use tempdir::TempDir;
+
     use crate::{
-        subtitle::{SingleSubtitle, SubtitleResult},
+        llm::mock::MockLlm,
+        mpeg::runner::ProcessRunner,
+        news::{
+            MaterialExtractor, NEWS_MODEL_VERSION, NewsCrawler, NewsCrawlerError,
+            NewsMaterialResult,
+            filter::FilterSettings,
+            local_news::{LocalNews, LocalNewsInput, LocalNewsMaterialExtractor},
+            summarizer::LlmMaterialExtractor,
+        },
+        progress::NoopReporter,
         tts::TTSFile,
         video::VideoEditorResult,
     };
 
     use super::*;
 
+    /// Counts the calls to the fakes.
+    #[derive(Clone, Default)]
+    struct Calls {
+        materials: Arc<AtomicUsize>,
+        sentences: Arc<Mutex<Vec<String>>>,
+        downloads: Arc<AtomicUsize>,
+        /// whether each compose had a dubbing and a subtitle
+        composes: Arc<Mutex<Vec<(bool, bool)>>>,
+    }
+
+    struct FakeCrawler(Vec<NewsTitle>);
+    #[async_trait::async_trait]
+    impl NewsCrawler for FakeCrawler {
+        async fn get_hot_news_list(&self) -> Result<Vec<NewsTitle>, NewsCrawlerError> {
+            Ok(self.0.clone())
+        }
+    }
+
+    struct FakeExtractor(Calls);
+    #[async_trait::async_trait]
+    impl MaterialExtractor for FakeExtractor {
+        async fn get_material(&self, news_title: &NewsTitle) -> NewsMaterialResult {
+            self.0.materials.fetch_add(1, Ordering::SeqCst);
+            Ok(NewsMaterial {
+                version: NEWS_MODEL_VERSION,
+                title: news_title.title.clone(),
+                summary: vec!["第一句".to_owned(), "第二句".to_owned()],
+                pics: vec!["pic1".into(), "pic2".into()],
+                videos: vec![],
+                article: vec![],
+                provenance: None,
+            })
+        }
+    }
+
+    struct FakeTTS(Calls, PathBuf);
+    #[async_trait::async_trait]
+    impl TTSService for FakeTTS {
+        fn voice(&self) -> String {
+            "fake".to_owned()
+        }
+
+        async fn tts(
+            &self,
+            text_list: &Vec<String>,
+            _: &dyn ProgressReporter,
+            _: &CancellationToken,
+        ) -> Result<Vec<TTSFile>, TTSError> {
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate: 8000,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+
+            let mut files = vec![];
+            for text in text_list {
+                self.0.sentences.lock().unwrap().push(text.clone());
+
+                let path = self.1.join(format!("{}.wav", nanoid::nanoid!(10)));
+                let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+                for _ in 0..4000 {
+                    writer.write_sample(0i16).unwrap();
+                }
+                writer.finalize().unwrap();
+
+                files.push(TTSFile {
+                    path,
+                    text: text.clone(),
+                    duration: Duration::from_millis(500),
+                });
+            }
+
+            Ok(files)
+        }
+    }
+
+    struct FakeEditor(Calls);
+    #[async_trait::async_trait]
+    impl VideoEditor for FakeEditor {
+        fn pick_pics(&self, pics: &Vec<String>, _: Duration) -> Vec<String> {
+            pics.clone()
+        }
+
+        async fn save_pic(&self, _: &String, file_path: PathBuf) -> VideoEditorResult<PathBuf> {
+            // like a request, not ready on the first poll
+            tokio::task::yield_now().await;
+            self.0.downloads.fetch_add(1, Ordering::SeqCst);
+            let path = file_path.with_extension("png");
+            fs::write(&path, b"png").await?;
+            Ok(path)
+        }
+
+        async fn compose_pics(
+            &self,
+            _: &Vec<PathBuf>,
+            output: &PathBuf,
+            _: &dyn ProgressReporter,
+            _: &CancellationToken,
+        ) -> VideoEditorResult<()> {
+            fs::write(output, b"slideshow").await?;
+            Ok(())
+        }
+    }
+
+    /// Fails the first compose when `fail_first`.
+    struct FakeCompose {
+        calls: Calls,
+        fail_first: bool,
+    }
+    #[async_trait::async_trait]
+    impl ComposeTool for FakeCompose {
+        async fn compose_all(
+            &self,
+            _: &PathBuf,
+            voice: Option<&PathBuf>,
+            subtitle: Option<&PathBuf>,
+            output: &PathBuf,
+            _: &dyn ProgressReporter,
+            _: &CancellationToken,
+        ) -> Result<(), ComposeToolError> {
+            let mut composes = self.calls.composes.lock().unwrap();
+            composes.push((voice.is_some(), subtitle.is_some()));
+            if self.fail_first && composes.len() == 1 {
+                return Err(ComposeToolError::Fail("first compose fails".to_owned()));
+            }
+
+            std::fs::write(output, b"final").map_err(|e| ComposeToolError::Fail(e.to_string()))
+        }
+    }
+
+    /// Only the fake source, the fake editor and compose tool.
+    fn builder(calls: &Calls, temp: &TempDir, fail_first: bool) -> DirectorBuilder {
+        DirectorBuilder::new(vec![])
+            .with_source(NewsSource::new(
+                "fake".to_owned(),
+                Box::new(FakeCrawler(vec![])),
+                Box::new(FakeExtractor(calls.clone())),
+            ))
+            .with_workspace_dir(temp.path().join("workspace"))
+            .with_video_editor(FakeEditor(calls.clone()))
+            .with_compose_tool(FakeCompose {
+                calls: calls.clone(),
+                fail_first,
+            })
+    }
+
+    fn news_title() -> NewsTitle {
+        NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: "fake".to_owned(),
+            title: "TITLE".to_owned(),
+            url: "https://example.com/news".to_owned(),
+            pics: vec![],
+            videos: vec![],
+            meta: Default::default(),
+        }
+    }
+
     #[test]
-    fn new_director() {
-        let director = Director::new("Fake DeepSeek API Key");
+    fn build_requires_editor_and_compose_tool() {
+        let calls = Calls::default();
 
+        let res = DirectorBuilder::new(vec![])
+            .with_compose_tool(FakeCompose {
+                calls: calls.clone(),
+                fail_first: false,
+            })
+            .build();
+        assert!(matches!(res, Err(DirectorBuildError::NoVideoEditor)));
+
+        let res = DirectorBuilder::new(vec![])
+            .with_video_editor(FakeEditor(calls.clone()))
+            .build();
+        assert!(matches!(res, Err(DirectorBuildError::NoComposeTool)));
+
+        let director = DirectorBuilder::new(vec![NewsSource::new(
+            "fake".to_owned(),
+            Box::new(FakeCrawler(vec![])),
+            Box::new(FakeExtractor(calls.clone())),
+        )])
+        .with_video_editor(FakeEditor(calls.clone()))
+        .with_compose_tool(FakeCompose {
+            calls,
+            fail_first: false,
+        })
+        .build()
+        .unwrap();
         assert_eq!(1, director.sources.len());
         assert!(director.tts.is_none());
         assert!(director.subtitle.is_none());
-        assert!(director.video_editor.is_none());
     }
 
     #[test]
-    fn new_director_with_compoments() {
-        struct FakeTTS;
-        #[async_trait::async_trait]
-        impl TTSService for FakeTTS {
-            async fn tts(&self, _: &Vec<String>) -> Result<Vec<TTSFile>, TTSError> {
-                unimplemented!();
-            }
+    fn build_rejects_dubbing_stages_without_tts() {
+        let calls = Calls::default();
+        let temp = TempDir::new("director").unwrap();
+
+        let res = builder(&calls, &temp, false)
+            .with_subtitle(SrtSubtitle::new())
+            .build();
+        assert!(matches!(res, Err(DirectorBuildError::SubtitleWithoutTTS)));
+
+        let res = builder(&calls, &temp, false)
+            .with_voice_edit_tool(FFmpeg4Voice::new(Arc::new(ProcessRunner::default())))
+            .build();
+        assert!(matches!(
+            res,
+            Err(DirectorBuildError::VoiceEffectWithoutTTS)
+        ));
+    }
+
+    #[tokio::test]
+    async fn silent_slideshow_without_tts() {
+        let calls = Calls::default();
+        let temp = TempDir::new("director").unwrap();
+        let director = builder(&calls, &temp, false).build().unwrap();
+
+        let video = director
+            .shot_single(news_title(), &NoopReporter, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert!(video.path.exists());
+        assert!(video.voice.is_none());
+        assert!(video.durations.is_empty());
+        // 2 pictures, 2 seconds each
+        assert_eq!(Duration::from_secs(4), video.duration);
+        assert_eq!(vec![(false, false)], *calls.composes.lock().unwrap());
+        assert!(calls.sentences.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn local_text_without_pics() {
+        let calls = Calls::default();
+        let temp = TempDir::new("director").unwrap();
+        let llm = MockLlm::new("qwen-plus")
+            .with_answer(r#"{"summary": ["新品今天发布。"], "images": []}"#);
+        let director = builder(&calls, &temp, false)
+            .with_source(NewsSource::new(
+                local_news::SOURCE_NAME.to_owned(),
+                Box::new(LocalNews),
+                Box::new(LocalNewsMaterialExtractor::new(LlmMaterialExtractor::new(
+                    Box::new(llm),
+                ))),
+            ))
+            .with_tts(FakeTTS(calls.clone(), temp.path().to_path_buf()))
+            .build()
+            .unwrap();
+        let news_title = LocalNewsInput {
+            text: Some("新品今天发布，欢迎大家试用。".to_owned()),
+            ..Default::default()
         }
+        .into_news_title(&temp.path().join(local_news::LOCAL_DIR))
+        .await
+        .unwrap();
+
+        let res = director
+            .shot_single(news_title, &NoopReporter, &CancellationToken::new())
+            .await;
+
+        assert!(matches!(
+            res,
+            Err(DirectorError::VideoEditor(VideoEditorError::Image(_)))
+        ));
+        // failed before the dubbing
+        assert!(calls.sentences.lock().unwrap().is_empty());
+        assert!(calls.composes.lock().unwrap().is_empty());
+    }
 
-        let director = Director::new("Fake DeepSeek API Key").with_tts(FakeTTS);
-        assert!(director.tts.is_some());
+    #[tokio::test]
+    async fn dubbing_without_subtitle() {
+        let calls = Calls::default();
+        let temp = TempDir::new("director").unwrap();
+        let director = builder(&calls, &temp, false)
+            .with_tts(FakeTTS(calls.clone(), temp.path().to_path_buf()))
+            .build()
+            .unwrap();
+
+        director
+            .shot_single(news_title(), &NoopReporter, &CancellationToken::new())
+            .await
+            .unwrap();
+
+        assert_eq!(vec![(true, false)], *calls.composes.lock().unwrap());
+    }
 
-        struct FakeSubtitle;
-        #[async_trait::async_trait]
-        impl Subtitle for FakeSubtitle {
-            async fn write_subtitle(&self, _: &Vec<SingleSubtitle>) -> SubtitleResult {
-                unimplemented!();
-            }
+    #[tokio::test]
+    async fn rerun_reuses_workspace() {
+        let temp = TempDir::new("director").unwrap();
+        let calls = Calls::default();
+
+        let director = builder(&calls, &temp, true)
+            .with_tts(FakeTTS(calls.clone(), temp.path().to_path_buf()))
+            .with_subtitle(SrtSubtitle::new().with_temp_dir(temp.path().to_path_buf()))
+            .build()
+            .unwrap();
+        let news_title = news_title();
+
+        let res = director
+            .shot_single(news_title.clone(), &NoopReporter, &CancellationToken::new())
+            .await;
+        assert!(matches!(res, Err(DirectorError::ComposeTool(_))));
+
+        let events = Mutex::new(vec![]);
+        let video = director
+            .shot_single(
+                news_title,
+                &|event| events.lock().unwrap().push(event),
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        assert!(video.path.exists());
+        assert_eq!(1, calls.materials.load(Ordering::SeqCst));
+        assert_eq!(2, calls.sentences.lock().unwrap().len());
+        assert_eq!(2, calls.downloads.load(Ordering::SeqCst));
+        assert_eq!(2, calls.composes.lock().unwrap().len());
+
+        let events = events.into_inner().unwrap();
+        for stage in [
+            Stage::Material,
+            Stage::Dubbing,
+            Stage::Subtitle,
+            Stage::Video,
+        ] {
+            assert!(events.contains(&ProgressEvent::StageCached { stage }));
         }
-        let director = Director::new("Fake DeepSeek API Key").with_subtitle(FakeSubtitle);
-        assert!(director.subtitle.is_some());
-
-        struct FakeVideoEditor;
-        #[async_trait::async_trait]
-        impl VideoEditor for FakeVideoEditor {
-            async fn do_edit(&self, _: &NewsMaterial, _: Duration) -> VideoEditorResult<PathBuf> {
-                unimplemented!();
-            }
+        assert!(events.contains(&ProgressEvent::StageStarted {
+            stage: Stage::Compose
+        }));
+    }
+
+    #[tokio::test]
+    async fn download_pics_cancelled() {
+        let temp = TempDir::new("director").unwrap();
+        let calls = Calls::default();
+        let director = builder(&calls, &temp, false).build().unwrap();
+        let mut workspace = Workspace::open(temp.path(), "https://example.com/news")
+            .await
+            .unwrap();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let res = director
+            .download_pics(
+                &FakeEditor(calls.clone()),
+                &["pic1".to_owned()],
+                &mut workspace,
+                &NoopReporter,
+                &cancel,
+            )
+            .await;
+
+        assert!(matches!(res, Err(DirectorError::Cancelled)));
+        // nothing was stored
+        assert!(!workspace.file(workspace::MANIFEST_FILE).exists());
+    }
+
+    #[tokio::test]
+    async fn compose_audio_rejects_bad_wavs() {
+        let temp = TempDir::new("director").unwrap();
+        let director = builder(&Calls::default(), &temp, false).build().unwrap();
+
+        let spec = hound::WavSpec {
+            channels: 1,
+            sample_rate: 8000,
+            bits_per_sample: 24,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let wide = temp.path().join("wide.wav");
+        let mut writer = hound::WavWriter::create(&wide, spec).unwrap();
+        writer.write_sample(0i32).unwrap();
+        writer.finalize().unwrap();
+        let truncated = temp.path().join("truncated.wav");
+        std::fs::write(&truncated, b"RIFF").unwrap();
+
+        for file in [wide, truncated] {
+            let res = director
+                .compose_audio(&vec![file], temp.path().join("dubbing.wav"))
+                .await;
+            assert!(matches!(res, Err(DirectorError::WavReader(_))));
         }
-        let director = Director::new("Fake DeepSeek API Key").with_video_editor(FakeVideoEditor);
-        assert!(director.video_editor.is_some());
+    }
+
+    #[tokio::test]
+    async fn ranked_news_of_all_sources() {
+        let temp = TempDir::new("director").unwrap();
+        let calls = Calls::default();
+        let titled = |source: &str, title: &str, url: &str| NewsTitle {
+            source: source.to_owned(),
+            title: title.to_owned(),
+            url: url.to_owned(),
+            ..news_title()
+        };
+        let director = builder(&calls, &temp, false)
+            .with_source(NewsSource::new(
+                "fake".to_owned(),
+                Box::new(FakeCrawler(vec![
+                    titled("fake", "台风登陆", "https://example.com/1"),
+                    titled("fake", "火箭发射成功", "https://example.com/2"),
+                ])),
+                Box::new(FakeExtractor(calls.clone())),
+            ))
+            .with_source(NewsSource::new(
+                "other".to_owned(),
+                Box::new(FakeCrawler(vec![
+                    titled("other", "火箭发射成功！", "https://example.org/2"),
+                    titled("other", "明星八卦", "https://example.org/3"),
+                ])),
+                Box::new(FakeExtractor(calls.clone())),
+            ))
+            .with_filter(NewsFilter::new(&FilterSettings {
+                include: vec!["火箭".to_owned(), "台风".to_owned()],
+                dedupe_similarity: Some(0.9),
+                skip_rendered: true,
+                ..Default::default()
+            }))
+            .build()
+            .unwrap();
+        let sources = ["fake".to_owned(), "missing".to_owned(), "other".to_owned()];
+
+        let ranked = director
+            .get_ranked_news(&sources, false, &|_| false)
+            .await
+            .unwrap();
+        assert_eq!(
+            vec!["台风登陆", "火箭发射成功"],
+            ranked
+                .titles
+                .iter()
+                .map(|t| t.title.as_str())
+                .collect::<Vec<_>>()
+        );
+        // the other sources are still listed
+        assert_eq!(1, ranked.failures.len());
+        assert_eq!("missing", ranked.failures[0].source);
+
+        assert!(matches!(
+            director
+                .get_ranked_news(&["missing".to_owned()], false, &|_| false)
+                .await,
+            Err(DirectorError::Source(_))
+        ));
+
+        let list = director
+            .get_hot_news_list(&"fake".to_owned(), false, &|url| {
+                url == "https://example.com/1"
+            })
+            .await
+            .unwrap();
+        assert_eq!(1, list.len());
+        assert_eq!("火箭发射成功", list[0].title);
     }
 }
@@ -1,4 +1,18 @@
-use crate::news::{MaterialExtractor, NewsCrawler};
+use serde::Serialize;
+
+use crate::{
+    config::{ConfigError, GlobalConfig, Settings},
+    director::{DirectorError, DirectorResult},
+    llm::openai_compatible::OpenAiCompatible,
+    news::{
+        MaterialExtractor, NewsCrawler,
+        feed_news::FeedNews,
+        local_news::{self, LocalNews, LocalNewsMaterialExtractor},
+        pengpai_news::{self, PengPaiNews},
+        summarizer::LlmMaterialExtractor,
+    },
+    profile::Profile,
+};
 
 pub type SourceName = String;
 
@@ -22,3 +36,259 @@ impl NewsSource {
         }
     }
 }
+
+/// What a source can do, for the UI to show what applies.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum SourceCapability {
+    /// lists its hot news
+    HotList,
+    /// lists the news of its channels page by page
+    Channels,
+    /// searches its news by keyword page by page
+    Search,
+    /// makes a news of a local file or a pasted text
+    LocalInput,
+}
+
+/// A source as shown to the UI.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceInfo {
+    /// the name in `SOURCES` and in the news titles
+    pub name: SourceName,
+    pub display_name: String,
+    pub capabilities: Vec<SourceCapability>,
+}
+
+/// What a source is built from.
+pub struct SourceContext<'a> {
+    pub config: &'a GlobalConfig,
+    /// the profile the videos are made with, the LLM model and style of the summary
+    pub profile: &'a Profile,
+}
+
+type BuildSource =
+    Box<dyn Fn(&SourceContext) -> Result<NewsSource, ConfigError> + Sync + Send + 'static>;
+
+/// The sources known to the app, the ones enabled by `SOURCES` in the config are built.
+#[derive(Default)]
+pub struct SourceRegistry {
+    sources: Vec<(SourceInfo, BuildSource)>,
+}
+
+impl SourceRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The sources shipped with the app.
+    pub fn builtin() -> Self {
+        Self::new()
+            .with_source(
+                SourceInfo {
+                    name: pengpai_news::SOURCE_NAME.to_owned(),
+                    display_name: "澎湃新闻".to_owned(),
+                    capabilities: vec![
+                        SourceCapability::HotList,
+                        SourceCapability::Channels,
+                        SourceCapability::Search,
+                    ],
+                },
+                |ctx| {
+                    Ok(NewsSource::new(
+                        pengpai_news::SOURCE_NAME.to_owned(),
+                        Box::new(PengPaiNews::new()),
+                        Box::new(Self::article_extractor(ctx)?),
+                    ))
+                },
+            )
+            .with_source(
+                SourceInfo {
+                    name: local_news::SOURCE_NAME.to_owned(),
+                    display_name: "本地文件".to_owned(),
+                    capabilities: vec![SourceCapability::LocalInput],
+                },
+                |ctx| {
+                    Ok(NewsSource::new(
+                        local_news::SOURCE_NAME.to_owned(),
+                        Box::new(LocalNews),
+                        Box::new(LocalNewsMaterialExtractor::new(Self::article_extractor(
+                            ctx,
+                        )?)),
+                    ))
+                },
+            )
+    }
+
+    /// The builtin sources and a source for each feed of `settings`.
+    pub fn from_settings(settings: &Settings) -> Self {
+        settings
+            .feeds
+            .iter()
+            .fold(Self::builtin(), |registry, (name, feed)| {
+                let info = SourceInfo {
+                    name: name.clone(),
+                    display_name: feed.display_name.clone().unwrap_or_else(|| name.clone()),
+                    capabilities: vec![SourceCapability::HotList],
+                };
+                let (name, url) = (name.clone(), feed.url.clone());
+
+                registry.with_source(info, move |ctx| {
+                    Ok(NewsSource::new(
+                        name.clone(),
+                        Box::new(FeedNews::new(name.clone(), url.clone())),
+                        Box::new(Self::article_extractor(ctx)?),
+                    ))
+                })
+            })
+    }
+
+    /// Summarizes any news page with the LLM of the profile.
+    fn article_extractor(ctx: &SourceContext) -> Result<LlmMaterialExtractor, ConfigError> {
+        let llm = OpenAiCompatible::from_settings(
+            &ctx.config.settings().llm,
+            ctx.config.get_llm_api_key()?,
+            &ctx.profile.llm_model,
+        );
+        Ok(LlmMaterialExtractor::new(Box::new(llm)).with_style(ctx.profile.llm_prompt.clone()))
+    }
+
+    /// Register a source, `build` makes its crawler and extractor when it is enabled.
+    /// A source of the same name is replaced.
+    pub fn with_source(
+        mut self,
+        info: SourceInfo,
+        build: impl Fn(&SourceContext) -> Result<NewsSource, ConfigError> + Sync + Send + 'static,
+    ) -> Self {
+        self.sources.retain(|(i, _)| i.name != info.name);
+        self.sources.push((info, Box::new(build)));
+        self
+    }
+
+    /// The sources named in `enabled`, in its order.
+    pub fn list(&self, enabled: &[SourceName]) -> DirectorResult<Vec<SourceInfo>> {
+        enabled
+            .iter()
+            .map(|name| self.find(name).map(|(info, _)| info.clone()))
+            .collect()
+    }
+
+    /// Build the sources named in `enabled`.
+    pub fn build(
+        &self,
+        enabled: &[SourceName],
+        ctx: &SourceContext,
+    ) -> DirectorResult<Vec<NewsSource>> {
+        enabled
+            .iter()
+            .map(|name| Ok(self.find(name)?.1(ctx)?))
+            .collect()
+    }
+
+    fn find(&self, name: &str) -> DirectorResult<&(SourceInfo, BuildSource)> {
+        self.sources
+            .iter()
+            .find(|(info, _)| info.name == name)
+            .ok_or_else(|| DirectorError::Source(format!("unknown source: {}", name)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::config::ConfigLoader;
+
+    use super::*;
+
+    #[test]
+    fn builds_enabled_sources() {
+        let config = ConfigLoader::new()
+            .with_env(Default::default())
+            .with_override("OPENAI_KEY", "sk-test")
+            .load()
+            .unwrap();
+        let profile = config.profile(None).unwrap();
+        let ctx = SourceContext {
+            config: &config,
+            profile: &profile,
+        };
+
+        let registry = SourceRegistry::builtin().with_source(
+            SourceInfo {
+                name: "other".to_owned(),
+                display_name: "Other".to_owned(),
+                capabilities: vec![],
+            },
+            |_| Err(ConfigError::Missing("OTHER_KEY")),
+        );
+
+        let sources = registry.build(&["pengpai".to_owned()], &ctx).unwrap();
+        assert_eq!(
+            vec!["pengpai"],
+            sources.iter().map(|s| &s.name).collect::<Vec<_>>()
+        );
+
+        let infos = registry
+            .list(&["other".to_owned(), "pengpai".to_owned()])
+            .unwrap();
+        assert_eq!("other", infos[0].name);
+        assert_eq!(
+            vec![
+                SourceCapability::HotList,
+                SourceCapability::Channels,
+                SourceCapability::Search
+            ],
+            infos[1].capabilities
+        );
+
+        assert!(matches!(
+            registry.build(&["other".to_owned()], &ctx),
+            Err(DirectorError::Config(ConfigError::Missing("OTHER_KEY")))
+        ));
+        assert!(matches!(
+            registry.list(&["absent".to_owned()]),
+            Err(DirectorError::Source(_))
+        ));
+    }
+
+    #[test]
+    fn feeds_are_sources() {
+        let dir = tempdir::TempDir::new("source").unwrap();
+        let file = dir.path().join(crate::config::CONFIG_FILE);
+        std::fs::write(
+            &file,
+            r#"
+OPENAI_KEY="sk-test"
+SOURCES=["bbc", "pengpai"]
+
+[FEEDS.bbc]
+URL="https://feeds.bbci.co.uk/news/rss.xml"
+DISPLAY_NAME="BBC News"
+"#,
+        )
+        .unwrap();
+        let config = ConfigLoader::new()
+            .with_file(&file)
+            .with_env(Default::default())
+            .load()
+            .unwrap();
+        let profile = config.profile(None).unwrap();
+        let settings = config.settings();
+        let registry = SourceRegistry::from_settings(settings);
+
+        let infos = registry.list(&settings.sources).unwrap();
+        assert_eq!("BBC News", infos[0].display_name);
+        assert_eq!("澎湃新闻", infos[1].display_name);
+
+        let sources = registry
+            .build(
+                &settings.sources,
+                &SourceContext {
+                    config: &config,
+                    profile: &profile,
+                },
+            )
+            .unwrap();
+        assert_eq!("bbc", sources[0].name);
+    }
+}
@@ -0,0 +1,275 @@
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize, de::DeserializeOwned};
+use sha2::{Digest, Sha256};
+use tokio::fs;
+
+/// The manifest in each workspace dir
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Hashes what a stage is made from,
+/// each part is length-prefixed so that the parts cannot run into each other.
+pub struct InputHash(Sha256);
+
+impl InputHash {
+    pub fn new(stage: &str) -> Self {
+        Self(Sha256::new()).with(stage)
+    }
+
+    pub fn with(mut self, part: impl AsRef<[u8]>) -> Self {
+        let part = part.as_ref();
+        self.0.update((part.len() as u64).to_le_bytes());
+        self.0.update(part);
+        self
+    }
+
+    /// The hex digest.
+    pub fn finish(self) -> String {
+        format!("{:x}", self.0.finalize())
+    }
+}
+
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct Manifest {
+    /// the news the workspace belongs to
+    pub url: String,
+    pub artifacts: HashMap<String, Artifact>,
+}
+
+/// What a stage produced from its input.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Artifact {
+    /// the `InputHash` of the stage
+    pub input: String,
+    /// the file name in the workspace dir
+    pub file: Option<String>,
+    #[serde(default)]
+    pub data: serde_json::Value,
+}
+
+/// The artifacts of the stages of one news, in a dir of its own keyed by the news url,
+/// so that a rerun only redoes the stages whose input changed.
+pub struct Workspace {
+    dir: PathBuf,
+    manifest: Manifest,
+}
+
+impl Workspace {
+    /// Open the workspace of the news at `url` under `root`, created when absent.
+    pub async fn open(root: &Path, url: &str) -> io::Result<Self> {
+        let hash = InputHash::new("workspace").with(url).finish();
+        let dir = root.join(&hash[..16]);
+        fs::create_dir_all(&dir).await?;
+
+        let manifest = match fs::read(dir.join(MANIFEST_FILE)).await {
+            // a broken manifest only costs the cache
+            Ok(bytes) => serde_json::from_slice(&bytes).unwrap_or_default(),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Manifest::default(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            dir,
+            manifest: Manifest {
+                url: url.to_owned(),
+                ..manifest
+            },
+        })
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    /// The path of `name` in the workspace dir, for stages that write in place.
+    pub fn file(&self, name: &str) -> PathBuf {
+        self.dir.join(name)
+    }
+
+    /// The file and data stored as `key`,
+    /// `None` when they were made from another `input` or the file is gone.
+    pub fn cached<T: DeserializeOwned>(
+        &self,
+        key: &str,
+        input: &str,
+    ) -> Option<(Option<PathBuf>, T)> {
+        let artifact = self.manifest.artifacts.get(key)?;
+        if artifact.input != input {
+            return None;
+        }
+
+        let file = match &artifact.file {
+            Some(name) => {
+                let path = self.dir.join(name);
+                if !path.exists() {
+                    return None;
+                }
+                Some(path)
+            }
+            None => None,
+        };
+        let data = serde_json::from_value(artifact.data.clone()).ok()?;
+
+        Some((file, data))
+    }
+
+    /// Record what the stage `key` made from `input` and save the manifest.
+    /// The file is moved into the workspace dir as `<key>.<extension>`, the new path is returned.
+    pub async fn store<T: Serialize>(
+        &mut self,
+        key: &str,
+        input: &str,
+        file: Option<&Path>,
+        data: &T,
+    ) -> io::Result<Option<PathBuf>> {
+        let path = match file {
+            Some(file) => {
+                let mut path = self.dir.join(key);
+                if let Some(extension) = file.extension() {
+                    path.set_extension(extension);
+                }
+                if file != path {
+                    fs::rename(file, &path).await?;
+                }
+                Some(path)
+            }
+            None => None,
+        };
+
+        let artifact = Artifact {
+            input: input.to_owned(),
+            file: path
+                .as_ref()
+                .and_then(|p| p.file_name())
+                .map(|name| name.to_string_lossy().into_owned()),
+            data: serde_json::to_value(data)?,
+        };
+        self.manifest.artifacts.insert(key.to_owned(), artifact);
+        self.save().await?;
+
+        Ok(path)
+    }
+
+    /// Remove the workspace the file at `path` was made in, once the file is moved out of it.
+    /// Nothing is removed when `path` is not in a workspace.
+    pub async fn prune(path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(dir) if dir.join(MANIFEST_FILE).exists() => fs::remove_dir_all(dir).await,
+            _ => Ok(()),
+        }
+    }
+
+    async fn save(&self) -> io::Result<()> {
+        fs::write(
+            self.dir.join(MANIFEST_FILE),
+            serde_json::to_vec_pretty(&self.manifest)?,
+        )
+        .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    #[test]
+    fn input_hash_parts_do_not_run_together() {
+        let ab = InputHash::new("stage").with("ab").with("c").finish();
+        let a_bc = InputHash::new("stage").with("a").with("bc").finish();
+
+        assert_ne!(ab, a_bc);
+        assert_eq!(ab, InputHash::new("stage").with("ab").with("c").finish());
+    }
+
+    #[tokio::test]
+    async fn store_and_reopen() {
+        let root = TempDir::new("workspace").unwrap();
+        let url = "https://example.com/news";
+
+        let mut workspace = Workspace::open(root.path(), url).await.unwrap();
+        let file = root.path().join("voice.wav");
+        fs::write(&file, b"wav").await.unwrap();
+
+        let path = workspace
+            .store("tts-001", "input", Some(&file), &1.5)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(workspace.file("tts-001.wav"), path);
+        assert!(!file.exists());
+
+        let workspace = Workspace::open(root.path(), url).await.unwrap();
+        let (cached, duration) = workspace.cached::<f64>("tts-001", "input").unwrap();
+        assert_eq!(Some(path), cached);
+        assert_eq!(1.5, duration);
+    }
+
+    #[tokio::test]
+    async fn cached_misses() {
+        let root = TempDir::new("workspace").unwrap();
+        let mut workspace = Workspace::open(root.path(), "https://example.com/news")
+            .await
+            .unwrap();
+
+        let file = workspace.file("final.mp4");
+        fs::write(&file, b"mp4").await.unwrap();
+        workspace
+            .store("final", "input", Some(&file), &())
+            .await
+            .unwrap();
+
+        assert!(workspace.cached::<()>("final", "other input").is_none());
+        assert!(workspace.cached::<()>("absent", "input").is_none());
+
+        fs::remove_file(&file).await.unwrap();
+        assert!(workspace.cached::<()>("final", "input").is_none());
+    }
+
+    #[tokio::test]
+    async fn workspace_per_url() {
+        let root = TempDir::new("workspace").unwrap();
+
+        let a = Workspace::open(root.path(), "https://example.com/a")
+            .await
+            .unwrap();
+        let b = Workspace::open(root.path(), "https://example.com/b")
+            .await
+            .unwrap();
+        let a_again = Workspace::open(root.path(), "https://example.com/a")
+            .await
+            .unwrap();
+
+        assert_ne!(a.dir(), b.dir());
+        assert_eq!(a.dir(), a_again.dir());
+    }
+
+    #[tokio::test]
+    async fn prune() {
+        let root = TempDir::new("workspace").unwrap();
+        let mut workspace = Workspace::open(root.path(), "https://example.com/news")
+            .await
+            .unwrap();
+        let file = workspace.file("final.mp4");
+        fs::write(&file, b"mp4").await.unwrap();
+        let path = workspace
+            .store("final", "input", Some(&file), &())
+            .await
+            .unwrap()
+            .unwrap();
+
+        Workspace::prune(&path).await.unwrap();
+        assert!(!workspace.dir().exists());
+
+        // not in a workspace
+        let video = root.path().join("video.mp4");
+        fs::write(&video, b"mp4").await.unwrap();
+        Workspace::prune(&video).await.unwrap();
+        assert!(video.exists());
+    }
+}
@@ -0,0 +1,156 @@
+use std::io;
+
+use serde::Serialize;
+use thiserror::Error;
+
+use crate::{
+    config::ConfigError, director::DirectorError, news::local_news::LocalNewsError,
+    progress::Stage, secrets::SecretError, settings::SettingsError,
+};
+
+/// What went wrong, for the frontend to tell the failures apart.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum ErrorKind {
+    /// the config is missing or the pipeline cannot be built from it
+    Config,
+    /// the news source is unknown or its hot news cannot be fetched
+    Source,
+    /// the news cannot be fetched or summarized
+    Material,
+    #[serde(rename = "tts")]
+    TTS,
+    Subtitle,
+    /// the pictures cannot be downloaded or put into a slideshow
+    Video,
+    /// ffmpeg failed or cannot be launched
+    #[serde(rename = "ffmpeg")]
+    FFmpeg,
+    IO,
+    NotFound,
+    Cancelled,
+}
+
+/// The error the commands return, serialized for the frontend as `{ kind, message, stage }`.
+#[derive(Clone, Debug, Error, Serialize)]
+#[error("{message}")]
+pub struct CommandError {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// the stage of the pipeline that failed, if any
+    pub stage: Option<Stage>,
+}
+
+impl CommandError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            stage: None,
+        }
+    }
+
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::NotFound, message)
+    }
+}
+
+impl From<DirectorError> for CommandError {
+    fn from(e: DirectorError) -> Self {
+        let (kind, stage) = match e {
+            DirectorError::Source(_) | DirectorError::Crawler(_) => (ErrorKind::Source, None),
+            DirectorError::Material(_) => (ErrorKind::Material, Some(Stage::Material)),
+            DirectorError::TTS(_) | DirectorError::WavReader(_) => {
+                (ErrorKind::TTS, Some(Stage::Dubbing))
+            }
+            DirectorError::VoiceEditorTool(_) => (ErrorKind::FFmpeg, Some(Stage::Dubbing)),
+            DirectorError::Subtitle(_) => (ErrorKind::Subtitle, Some(Stage::Subtitle)),
+            DirectorError::VideoEditor(_) => (ErrorKind::Video, Some(Stage::Video)),
+            DirectorError::VideoEditorTool(_) => (ErrorKind::FFmpeg, Some(Stage::Video)),
+            DirectorError::ComposeTool(_) => (ErrorKind::FFmpeg, Some(Stage::Compose)),
+            DirectorError::IO(_) => (ErrorKind::IO, None),
+            DirectorError::Build(_) | DirectorError::Config(_) => (ErrorKind::Config, None),
+            DirectorError::Cancelled => (ErrorKind::Cancelled, None),
+        };
+
+        Self {
+            kind,
+            message: e.to_string(),
+            stage,
+        }
+    }
+}
+
+impl From<ConfigError> for CommandError {
+    fn from(e: ConfigError) -> Self {
+        Self::new(ErrorKind::Config, e.to_string())
+    }
+}
+
+impl From<SettingsError> for CommandError {
+    fn from(e: SettingsError) -> Self {
+        let kind = match e {
+            SettingsError::IO(_) => ErrorKind::IO,
+            SettingsError::Config(_) | SettingsError::Toml(_) | SettingsError::Secret(_) => {
+                ErrorKind::Config
+            }
+        };
+
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<SecretError> for CommandError {
+    fn from(e: SecretError) -> Self {
+        Self::new(ErrorKind::Config, e.to_string())
+    }
+}
+
+impl From<LocalNewsError> for CommandError {
+    fn from(e: LocalNewsError) -> Self {
+        let kind = match e {
+            LocalNewsError::Empty => ErrorKind::Material,
+            LocalNewsError::File(..) | LocalNewsError::IO(_) => ErrorKind::IO,
+        };
+
+        Self::new(kind, e.to_string())
+    }
+}
+
+impl From<io::Error> for CommandError {
+    fn from(e: io::Error) -> Self {
+        Self::new(ErrorKind::IO, e.to_string())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{director::builder::DirectorBuildError, mpeg::ComposeToolError};
+
+    use super::*;
+
+    #[test]
+    fn stage_from_director_error() {
+        let e: CommandError = DirectorError::ComposeTool(ComposeToolError::Cancelled).into();
+        assert_eq!(ErrorKind::FFmpeg, e.kind);
+        assert_eq!(Some(Stage::Compose), e.stage);
+
+        let e: CommandError = DirectorError::Build(DirectorBuildError::NoComposeTool).into();
+        assert_eq!(ErrorKind::Config, e.kind);
+        assert_eq!(None, e.stage);
+    }
+
+    #[test]
+    fn serialize() {
+        let e: CommandError = DirectorError::Config(ConfigError::Missing("TTS_URL")).into();
+
+        assert_eq!(
+            serde_json::json!({
+                "kind": "config",
+                "message": "config error: `TTS_URL` is not set in the config",
+                "stage": null,
+            }),
+            serde_json::to_value(&e).unwrap()
+        );
+    }
+}
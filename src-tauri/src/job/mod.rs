@@ -0,0 +1,103 @@
+pub mod queue;
+
+use std::{collections::HashMap, sync::Mutex};
+
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+
+use crate::progress::Stage;
+
+/// The Tauri event emitted when a job ends
+pub const JOB_EVENT: &str = "job-finished";
+
+pub type JobId = String;
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "camelCase")]
+pub enum JobStatus {
+    /// waiting for a stage permit
+    Queued,
+    Running { stage: Stage },
+    Done { path: String },
+    Failed { message: String },
+    Cancelled,
+}
+
+impl JobStatus {
+    /// Whether the job has ended, one way or another.
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Done { .. } | JobStatus::Failed { .. } | JobStatus::Cancelled
+        )
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct JobFinished {
+    pub id: JobId,
+    /// the url of the news the job was generating
+    pub url: String,
+    #[serde(flatten)]
+    pub result: JobStatus,
+}
+
+/// The running jobs and the tokens to cancel them.
+#[derive(Default)]
+pub struct JobRegistry {
+    jobs: Mutex<HashMap<JobId, CancellationToken>>,
+}
+
+impl JobRegistry {
+    /// Register a new job, returns its id and the token its pipeline watches.
+    pub fn start(&self) -> (JobId, CancellationToken) {
+        let id = nanoid::nanoid!(10);
+        let cancel = self.register(id.clone());
+
+        (id, cancel)
+    }
+
+    /// Register a job with a known id, returns the token its pipeline watches.
+    pub fn register(&self, id: JobId) -> CancellationToken {
+        let cancel = CancellationToken::new();
+
+        self.jobs.lock().unwrap().insert(id, cancel.clone());
+
+        cancel
+    }
+
+    /// Cancel a running job, returns `false` when there is no such job.
+    pub fn cancel(&self, id: &str) -> bool {
+        match self.jobs.lock().unwrap().get(id) {
+            Some(cancel) => {
+                cancel.cancel();
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Forget a job once its pipeline has returned.
+    pub fn finish(&self, id: &str) {
+        self.jobs.lock().unwrap().remove(id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cancel_running_job() {
+        let jobs = JobRegistry::default();
+
+        let (id, cancel) = jobs.start();
+        assert!(!cancel.is_cancelled());
+
+        assert!(jobs.cancel(&id));
+        assert!(cancel.is_cancelled());
+
+        jobs.finish(&id);
+        assert!(!jobs.cancel(&id));
+    }
+}
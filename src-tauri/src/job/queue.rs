@@ -0,0 +1,473 @@
+use std::{
+    fs, io,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    config::SharedConfig,
+    director::DirectorError,
+    job::{JOB_EVENT, JobFinished, JobId, JobRegistry, JobStatus},
+    library::{Library, LibraryEntry},
+    news::NewsTitle,
+    progress::{ProgressEvent, ProgressReporter, TauriReporter},
+    time::now,
+};
+
+/// The file in the app data dir the jobs are persisted to
+pub const JOBS_FILE: &str = "jobs.json";
+
+/// The Tauri event emitted with the `Job` whenever its status changes
+pub const JOB_UPDATED_EVENT: &str = "job-updated";
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Job {
+    pub id: JobId,
+    pub news_title: NewsTitle,
+    /// the config profile the video is made with, the default one when `None`
+    #[serde(default)]
+    pub profile: Option<String>,
+    #[serde(flatten)]
+    pub status: JobStatus,
+    /// unix seconds
+    pub created_at: u64,
+    /// unix seconds
+    pub updated_at: u64,
+}
+
+/// What `JobQueue::enqueue` did with a news.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum Enqueued {
+    /// a new job
+    Queued { id: JobId },
+    /// a job of the same news is queued or running already
+    Pending { id: JobId },
+    /// the news is in the library already, enqueue with `force` to render it again
+    Rendered { entry: Box<LibraryEntry> },
+}
+
+/// The jobs, saved to a JSON file on every change.
+pub struct JobStore {
+    path: PathBuf,
+    jobs: Mutex<Vec<Job>>,
+}
+
+impl JobStore {
+    /// Load the jobs saved at `path`, an absent file is an empty store.
+    /// Jobs that were still queued or running when the app quit are marked as failed.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let mut jobs: Vec<Job> = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+
+        for job in jobs.iter_mut().filter(|j| !j.status.is_finished()) {
+            job.status = JobStatus::Failed {
+                message: "interrupted, the app was closed".to_owned(),
+            };
+        }
+
+        Ok(Self {
+            path,
+            jobs: Mutex::new(jobs),
+        })
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.jobs.lock().unwrap().clone()
+    }
+
+    pub fn get(&self, id: &str) -> Option<Job> {
+        self.jobs
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|j| j.id == id)
+            .cloned()
+    }
+
+    /// The queued or running job of the news at `url`.
+    pub fn find_unfinished(&self, url: &str) -> Option<Job> {
+        Self::unfinished(&self.jobs.lock().unwrap(), url).cloned()
+    }
+
+    /// Add `job` unless its news has a queued or running job, that job is the error.
+    /// Checked under the same lock, two enqueues of a news cannot both add a job.
+    fn insert(&self, job: Job) -> Result<(), Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        if let Some(pending) = Self::unfinished(&jobs, &job.news_title.url) {
+            return Err(pending.clone());
+        }
+
+        jobs.push(job);
+        self.save(&jobs);
+        Ok(())
+    }
+
+    /// Queue the finished job `id` again, `None` when it does not exist, is still running
+    /// or another job of its news is queued or running.
+    fn requeue(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let url = jobs
+            .iter()
+            .find(|j| j.id == id && j.status.is_finished())?
+            .news_title
+            .url
+            .clone();
+        if Self::unfinished(&jobs, &url).is_some() {
+            return None;
+        }
+
+        let job = jobs.iter_mut().find(|j| j.id == id)?;
+        job.status = JobStatus::Queued;
+        job.updated_at = now();
+
+        let job = job.clone();
+        self.save(&jobs);
+        Some(job)
+    }
+
+    fn unfinished<'a>(jobs: &'a [Job], url: &str) -> Option<&'a Job> {
+        jobs.iter()
+            .find(|j| j.news_title.url == url && !j.status.is_finished())
+    }
+
+    /// Set the status of a job, `None` when it has been removed.
+    fn update(&self, id: &str, status: JobStatus) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let job = jobs.iter_mut().find(|j| j.id == id)?;
+        job.status = status;
+        job.updated_at = now();
+
+        let job = job.clone();
+        self.save(&jobs);
+        Some(job)
+    }
+
+    fn remove(&self, id: &str) -> Option<Job> {
+        let mut jobs = self.jobs.lock().unwrap();
+        let index = jobs.iter().position(|j| j.id == id)?;
+        let job = jobs.remove(index);
+        self.save(&jobs);
+        Some(job)
+    }
+
+    fn save(&self, jobs: &Vec<Job>) {
+        let res = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.path, serde_json::to_vec_pretty(jobs)?));
+
+        if let Err(e) = res {
+            // log
+            eprintln!("failed to save jobs to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// Runs many jobs at once, how many are in the LLM, TTS and ffmpeg stages
+/// at the same time is bounded by the `SharedLimits` of the app.
+/// The videos of the finished jobs are added to the `Library`.
+pub struct JobQueue {
+    app: AppHandle,
+    store: JobStore,
+    library: Arc<Library>,
+}
+
+impl JobQueue {
+    pub fn new(app: AppHandle, store: JobStore, library: Arc<Library>) -> Self {
+        Self {
+            app,
+            store,
+            library,
+        }
+    }
+
+    /// Queue one job per news, in the same order.
+    /// A news that is in the library or has a job already is skipped, unless `force`.
+    pub fn enqueue(self: &Arc<Self>, news_titles: Vec<NewsTitle>, force: bool) -> Vec<Enqueued> {
+        self.enqueue_with_profile(news_titles, None, force)
+    }
+
+    /// Like `enqueue`, the videos made with the config profile `profile`.
+    pub fn enqueue_with_profile(
+        self: &Arc<Self>,
+        news_titles: Vec<NewsTitle>,
+        profile: Option<String>,
+        force: bool,
+    ) -> Vec<Enqueued> {
+        news_titles
+            .into_iter()
+            .map(|news_title| {
+                if let Some(job) = self.store.find_unfinished(&news_title.url) {
+                    return Enqueued::Pending { id: job.id };
+                }
+                if !force && let Some(entry) = self.library.find_by_url(&news_title.url) {
+                    return Enqueued::Rendered {
+                        entry: Box::new(entry),
+                    };
+                }
+
+                let time = now();
+                let job = Job {
+                    id: nanoid::nanoid!(10),
+                    news_title,
+                    profile: profile.clone(),
+                    status: JobStatus::Queued,
+                    created_at: time,
+                    updated_at: time,
+                };
+                let id = job.id.clone();
+
+                // lost a race with another enqueue of the news
+                if let Err(pending) = self.store.insert(job.clone()) {
+                    return Enqueued::Pending { id: pending.id };
+                }
+                self.emit_updated(&job);
+                self.spawn(job);
+
+                Enqueued::Queued { id }
+            })
+            .collect()
+    }
+
+    pub fn list(&self) -> Vec<Job> {
+        self.store.list()
+    }
+
+    /// Run a finished job again, returns `false` when it does not exist, is still running
+    /// or another job of the same news is.
+    pub fn retry(self: &Arc<Self>, id: &str) -> bool {
+        let Some(job) = self.store.requeue(id) else {
+            return false;
+        };
+        self.emit_updated(&job);
+        self.spawn(job);
+
+        true
+    }
+
+    /// Cancel the job if it is running and forget it.
+    pub fn remove(&self, id: &str) -> bool {
+        self.app.state::<JobRegistry>().cancel(id);
+        self.store.remove(id).is_some()
+    }
+
+    fn spawn(self: &Arc<Self>, job: Job) {
+        let queue = self.clone();
+        let cancel = self.app.state::<JobRegistry>().register(job.id.clone());
+
+        tauri::async_runtime::spawn(async move {
+            let director = crate::new_director(
+                &queue.app.state::<SharedConfig>().get(),
+                job.profile.as_deref(),
+                queue.app.clone(),
+            );
+
+            let url = job.news_title.url.clone();
+            let progress = QueueReporter {
+                queue: queue.clone(),
+                id: job.id.clone(),
+                inner: TauriReporter::new(queue.app.clone(), job.id.clone(), url.clone()),
+            };
+
+            let res = match director {
+                Ok(director) => {
+                    director
+                        .shot_single(job.news_title, &progress, &cancel)
+                        .await
+                }
+                Err(e) => Err(e),
+            };
+            queue.app.state::<JobRegistry>().finish(&job.id);
+
+            let status = match res {
+                Ok(video) => match queue.library.add(video).await {
+                    Ok(entry) => JobStatus::Done {
+                        path: entry.path.display().to_string(),
+                    },
+                    Err(e) => JobStatus::Failed {
+                        message: format!("failed to add the video to the library: {}", e),
+                    },
+                },
+                Err(DirectorError::Cancelled) => JobStatus::Cancelled,
+                Err(e) => JobStatus::Failed {
+                    message: e.to_string(),
+                },
+            };
+
+            // it is gone when removed while running
+            if let Some(job) = queue.store.update(&job.id, status.clone()) {
+                queue.emit_updated(&job);
+            }
+
+            _ = queue.app.emit(
+                JOB_EVENT,
+                JobFinished {
+                    id: job.id,
+                    url,
+                    result: status,
+                },
+            );
+        });
+    }
+
+    fn emit_updated(&self, job: &Job) {
+        _ = self.app.emit(JOB_UPDATED_EVENT, job);
+    }
+}
+
+/// Emits the progress like `TauriReporter` and keeps the job's stage up to date.
+struct QueueReporter {
+    queue: Arc<JobQueue>,
+    id: JobId,
+    inner: TauriReporter,
+}
+
+impl ProgressReporter for QueueReporter {
+    fn report(&self, event: ProgressEvent) {
+        if let ProgressEvent::StageStarted { stage } = event
+            && let Some(job) = self
+                .queue
+                .store
+                .update(&self.id, JobStatus::Running { stage })
+        {
+            self.queue.emit_updated(&job);
+        }
+
+        self.inner.report(event);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::{news::NEWS_MODEL_VERSION, progress::Stage};
+
+    use super::*;
+
+    fn job(id: &str, status: JobStatus) -> Job {
+        Job {
+            id: id.to_owned(),
+            news_title: NewsTitle {
+                version: NEWS_MODEL_VERSION,
+                source: "pengpai".to_owned(),
+                title: "TITLE".to_owned(),
+                url: format!("https://example.com/{}", id),
+                pics: vec![],
+                videos: vec![],
+                meta: Default::default(),
+            },
+            profile: None,
+            status,
+            created_at: 1,
+            updated_at: 1,
+        }
+    }
+
+    #[test]
+    fn store_persists_jobs() {
+        let dir = TempDir::new("jobs").unwrap();
+        let path = dir.path().join(JOBS_FILE);
+
+        let store = JobStore::load(path.clone()).unwrap();
+        assert!(store.list().is_empty());
+
+        store.insert(job("a", JobStatus::Queued)).unwrap();
+        store.insert(job("b", JobStatus::Queued)).unwrap();
+        store.update(
+            "a",
+            JobStatus::Done {
+                path: "a.mp4".to_owned(),
+            },
+        );
+        store.remove("b");
+
+        let store = JobStore::load(path).unwrap();
+        let jobs = store.list();
+        assert_eq!(1, jobs.len());
+        assert_eq!(
+            JobStatus::Done {
+                path: "a.mp4".to_owned()
+            },
+            jobs[0].status
+        );
+    }
+
+    #[test]
+    fn store_fails_interrupted_jobs() {
+        let dir = TempDir::new("jobs").unwrap();
+        let path = dir.path().join(JOBS_FILE);
+
+        let store = JobStore::load(path.clone()).unwrap();
+        store.insert(job("queued", JobStatus::Queued)).unwrap();
+        store
+            .insert(job(
+                "running",
+                JobStatus::Running {
+                    stage: Stage::Dubbing,
+                },
+            ))
+            .unwrap();
+        store
+            .insert(job("cancelled", JobStatus::Cancelled))
+            .unwrap();
+
+        let store = JobStore::load(path).unwrap();
+        assert!(matches!(
+            store.get("queued").unwrap().status,
+            JobStatus::Failed { .. }
+        ));
+        assert!(matches!(
+            store.get("running").unwrap().status,
+            JobStatus::Failed { .. }
+        ));
+        assert_eq!(JobStatus::Cancelled, store.get("cancelled").unwrap().status);
+    }
+
+    #[test]
+    fn job_json_is_flat() {
+        let json = serde_json::to_value(job(
+            "a",
+            JobStatus::Running {
+                stage: Stage::Video,
+            },
+        ))
+        .unwrap();
+
+        assert_eq!("running", json["status"]);
+        assert_eq!("video", json["stage"]);
+        assert_eq!("a", json["id"]);
+        assert_eq!("TITLE", json["newsTitle"]["title"]);
+    }
+
+    #[test]
+    fn store_keeps_one_unfinished_job_per_news() {
+        let dir = TempDir::new("jobs").unwrap();
+        let store = JobStore::load(dir.path().join(JOBS_FILE)).unwrap();
+
+        store.insert(job("a", JobStatus::Queued)).unwrap();
+        let mut again = job("b", JobStatus::Queued);
+        again.news_title.url = "https://example.com/a".to_owned();
+        assert_eq!("a", store.insert(again.clone()).unwrap_err().id);
+        // not while running
+        assert!(store.requeue("a").is_none());
+
+        store.update("a", JobStatus::Cancelled);
+        store.insert(again).unwrap();
+        // the news has a job again
+        assert!(store.requeue("a").is_none());
+
+        store.update("b", JobStatus::Cancelled);
+        assert_eq!(JobStatus::Queued, store.requeue("a").unwrap().status);
+        assert_eq!(2, store.list().len());
+    }
+}
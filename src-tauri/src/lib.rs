@@ -1,66 +1,474 @@
 pub mod config;
 pub mod director;
+pub mod error;
+pub mod job;
+pub mod library;
+pub mod limits;
+pub mod llm;
 pub mod mpeg;
 pub mod news;
+pub mod profile;
+pub mod progress;
+pub mod schedule;
+pub mod secrets;
+pub mod settings;
 pub mod subtitle;
+pub mod time;
 pub mod tts;
 pub mod video;
 
+use std::{io, path::PathBuf, sync::Arc};
+
 use tauri::{AppHandle, Manager, State};
-use tokio::sync::RwLock;
+use tauri_plugin_opener::OpenerExt;
+use tokio_util::sync::CancellationToken;
+
+use crate::config::{CONFIG_FILE, GlobalConfig, SharedConfig};
+use crate::director::source::{SourceInfo, SourceName, SourceRegistry};
+use crate::director::{Director, DirectorResult, RankedNews};
+use crate::error::{CommandError, ErrorKind};
+use crate::job::queue::{Enqueued, JOBS_FILE, Job, JobQueue, JobStore};
+use crate::job::{JobId, JobRegistry};
+use crate::library::{Library, LibraryEntry};
+use crate::limits::{SharedLimits, StageLimits};
+use crate::mpeg::runner::{LimitedRunner, SidecarRunner};
+use crate::news::local_news::{LOCAL_DIR, LocalNewsInput};
+use crate::news::{Channel, NewsPage, NewsTitle};
+use crate::progress::TauriReporter;
+use crate::schedule::{QueueRun, SCHEDULE_FILE, ScheduleStatus, Scheduler};
+use crate::secrets::{SharedSecretStore, VAULT_FILE, VaultStore};
+use crate::settings::{SettingsUpdate, SettingsView};
+
+/// The director of the config profile `profile`, the default one when `None`.
+/// It waits for a permit of the `SharedLimits` of the app before each bounded stage,
+/// like the other jobs and commands.
+/// The workspaces are kept in the app data dir.
+pub(crate) fn new_director(
+    config: &GlobalConfig,
+    profile: Option<&str>,
+    app: AppHandle,
+) -> DirectorResult<Director> {
+    let data_dir = app.path().app_data_dir().map_err(io::Error::other)?;
+    let limits = app.state::<SharedLimits>().get();
+    let ffmpeg = LimitedRunner::new(SidecarRunner::new(app), limits.ffmpeg.clone());
+    let director = Director::default(config, profile, Arc::new(ffmpeg), &data_dir)?
+        .with_limits(limits)
+        .build()?;
 
-use crate::config::GlobalConfig;
-use crate::director::Director;
-use crate::director::source::SourceName;
-use crate::news::NewsTitle;
+    Ok(director)
+}
+
+/// The news sources enabled by `SOURCES` in the config, in order.
+#[tauri::command]
+async fn list_sources(config: State<'_, SharedConfig>) -> Result<Vec<SourceInfo>, CommandError> {
+    let config = config.get();
+    let settings = config.settings();
+    Ok(SourceRegistry::from_settings(settings).list(&settings.sources)?)
+}
 
+/// The hot news of `source`, the cached list unless it is older than `HOT_NEWS_TTL` or `refresh`,
+/// filtered and ranked by the `FILTER` of the config profile `profile`, the default one when absent.
 #[tauri::command]
 async fn get_hot_news_list(
     source: SourceName,
+    refresh: Option<bool>,
+    profile: Option<String>,
     app: AppHandle,
-    config: State<'_, RwLock<GlobalConfig>>,
-) -> Result<Vec<NewsTitle>, ()> {
-    let config_g = config.read().await;
-    let director = Director::default(
-        config_g.get_tts_url(),
-        config_g.get_ali_dashscope_api_key(),
-        config_g.get_deepseek_api_key(),
-        app,
-    );
-
-    let list = director.get_hot_news_list(&source).await;
+    config: State<'_, SharedConfig>,
+    library: State<'_, Arc<Library>>,
+) -> Result<Vec<NewsTitle>, CommandError> {
+    let config = config.get();
+    let director = new_director(&config, profile.as_deref(), app)?;
+
+    let list = director
+        .get_hot_news_list(&source, refresh.unwrap_or_default(), &|url| {
+            library.find_by_url(url).is_some()
+        })
+        .await?;
     Ok(list)
 }
 
+/// The hot news of all the sources of `SOURCES` in one list, like `get_hot_news_list`,
+/// the same news listed by several sources shown once when the filter dedupes.
+/// The sources that failed are listed with the news, it fails when they all fail.
+#[tauri::command]
+async fn get_ranked_news(
+    refresh: Option<bool>,
+    profile: Option<String>,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+    library: State<'_, Arc<Library>>,
+) -> Result<RankedNews, CommandError> {
+    let config = config.get();
+    let director = new_director(&config, profile.as_deref(), app)?;
+
+    let ranked = director
+        .get_ranked_news(
+            &config.settings().sources,
+            refresh.unwrap_or_default(),
+            &|url| library.find_by_url(url).is_some(),
+        )
+        .await?;
+    Ok(ranked)
+}
+
+/// The channels of `source`, empty when it has none.
+#[tauri::command]
+async fn list_channels(
+    source: SourceName,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+) -> Result<Vec<Channel>, CommandError> {
+    let config = config.get();
+    let director = new_director(&config, None, app)?;
+
+    Ok(director.channels(&source)?)
+}
+
+/// The `page` of the news of `channel`, the first one when absent.
+#[tauri::command]
+async fn get_channel_news(
+    source: SourceName,
+    channel: String,
+    page: Option<u32>,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+) -> Result<NewsPage, CommandError> {
+    let config = config.get();
+    let director = new_director(&config, None, app)?;
+
+    let page = director
+        .get_channel_news(&source, &channel, page.unwrap_or(1))
+        .await?;
+    Ok(page)
+}
+
+/// The `page` of the news of `source` found by `keyword`, the first one when absent.
+#[tauri::command]
+async fn search_news(
+    source: SourceName,
+    keyword: String,
+    page: Option<u32>,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+) -> Result<NewsPage, CommandError> {
+    let config = config.get();
+    let director = new_director(&config, None, app)?;
+
+    let page = director
+        .search_news(&source, &keyword, page.unwrap_or(1))
+        .await?;
+    Ok(page)
+}
+
+/// Generate the video with the config profile `profile`, the default one when absent, and wait for it.
+/// It can still be cancelled with the job id in the progress events.
+/// A news already in the library is not rendered again unless `force`, its video is returned.
 #[tauri::command]
 async fn gen_video(
     news_title: NewsTitle,
+    profile: Option<String>,
+    force: Option<bool>,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+    jobs: State<'_, JobRegistry>,
+    library: State<'_, Arc<Library>>,
+) -> Result<String, CommandError> {
+    if !force.unwrap_or_default()
+        && let Some(entry) = library.find_by_url(&news_title.url)
+    {
+        return Ok(entry.path.display().to_string());
+    }
+
+    let config = config.get();
+    let director = new_director(&config, profile.as_deref(), app.clone())?;
+
+    let (id, cancel) = jobs.start();
+    let progress = TauriReporter::new(app, id.clone(), news_title.url.clone());
+    let res = director.shot_single(news_title, &progress, &cancel).await;
+    jobs.finish(&id);
+
+    let entry = library.add(res?).await?;
+
+    Ok(entry.path.display().to_string())
+}
+
+/// Generate the video in the background, returns the job id at once.
+/// The result is emitted as `JOB_EVENT`.
+/// A news already in the library is not rendered again unless `force`.
+#[tauri::command]
+async fn start_gen_video(
+    news_title: NewsTitle,
+    force: Option<bool>,
+    queue: State<'_, Arc<JobQueue>>,
+) -> Result<Enqueued, CommandError> {
+    Ok(queue
+        .enqueue(vec![news_title], force.unwrap_or_default())
+        .remove(0))
+}
+
+/// Generate the video of a local file or a pasted text in the background, like `start_gen_video`.
+/// The pasted text is kept in the app data dir.
+#[tauri::command]
+async fn start_local_video(
+    input: LocalNewsInput,
+    force: Option<bool>,
     app: AppHandle,
-    config: State<'_, RwLock<GlobalConfig>>,
-) -> Result<String, ()> {
-    let config_g = config.read().await;
-    let director = Director::default(
-        config_g.get_tts_url(),
-        config_g.get_ali_dashscope_api_key(),
-        config_g.get_deepseek_api_key(),
-        app,
-    );
+    queue: State<'_, Arc<JobQueue>>,
+) -> Result<Enqueued, CommandError> {
+    let dir = app
+        .path()
+        .app_data_dir()
+        .map_err(|e| CommandError::new(ErrorKind::IO, e.to_string()))?
+        .join(LOCAL_DIR);
+    let news_title = input.into_news_title(&dir).await?;
 
-    let res = director.shot_single(news_title).await.unwrap();
+    Ok(queue
+        .enqueue(vec![news_title], force.unwrap_or_default())
+        .remove(0))
+}
 
-    Ok(res.path.canonicalize().unwrap().display().to_string())
+/// Queue a job for each news.
+#[tauri::command]
+async fn enqueue_jobs(
+    news_titles: Vec<NewsTitle>,
+    force: Option<bool>,
+    queue: State<'_, Arc<JobQueue>>,
+) -> Result<Vec<Enqueued>, CommandError> {
+    Ok(queue.enqueue(news_titles, force.unwrap_or_default()))
+}
+
+/// All the jobs, including those of previous runs.
+#[tauri::command]
+async fn list_jobs(queue: State<'_, Arc<JobQueue>>) -> Result<Vec<Job>, CommandError> {
+    Ok(queue.list())
+}
+
+/// Run a failed, cancelled or done job again.
+#[tauri::command]
+async fn retry_job(id: JobId, queue: State<'_, Arc<JobQueue>>) -> Result<bool, CommandError> {
+    Ok(queue.retry(&id))
+}
+
+/// Cancel the job if needed and drop it from the list.
+#[tauri::command]
+async fn remove_job(id: JobId, queue: State<'_, Arc<JobQueue>>) -> Result<bool, CommandError> {
+    Ok(queue.remove(&id))
+}
+
+/// The rendered videos, the newest first.
+#[tauri::command]
+async fn list_library(library: State<'_, Arc<Library>>) -> Result<Vec<LibraryEntry>, CommandError> {
+    Ok(library.list())
+}
+
+/// The rendered videos whose title, url or summary contains `query`.
+#[tauri::command]
+async fn search_library(
+    query: String,
+    library: State<'_, Arc<Library>>,
+) -> Result<Vec<LibraryEntry>, CommandError> {
+    Ok(library.search(&query))
+}
+
+/// Open the video with the default player.
+#[tauri::command]
+async fn open_video(
+    id: String,
+    app: AppHandle,
+    library: State<'_, Arc<Library>>,
+) -> Result<(), CommandError> {
+    let entry = get_entry(&library, &id)?;
+
+    app.opener()
+        .open_path(entry.path.display().to_string(), None::<&str>)
+        .map_err(|e| CommandError::new(ErrorKind::IO, e.to_string()))
+}
+
+/// Render the news of the video again, the new video replaces it when done.
+#[tauri::command]
+async fn rerender_video(
+    id: String,
+    library: State<'_, Arc<Library>>,
+    queue: State<'_, Arc<JobQueue>>,
+) -> Result<Enqueued, CommandError> {
+    let entry = get_entry(&library, &id)?;
+
+    Ok(queue.enqueue(vec![entry.news_title], true).remove(0))
+}
+
+/// Delete the video and its library entry.
+#[tauri::command]
+async fn delete_video(id: String, library: State<'_, Arc<Library>>) -> Result<bool, CommandError> {
+    Ok(library.delete(&id).await?)
+}
+
+fn get_entry(library: &Library, id: &str) -> Result<LibraryEntry, CommandError> {
+    library
+        .get(id)
+        .ok_or_else(|| CommandError::not_found(format!("no video {} in the library", id)))
+}
+
+/// The settings with the secrets masked.
+#[tauri::command]
+async fn get_settings(
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+) -> Result<SettingsView, CommandError> {
+    let config = config.get();
+    Ok(SettingsView::new(&config, &config_file(&app)?))
+}
+
+/// Save the changes to the config file and apply them,
+/// the jobs started from now on use the new settings.
+#[tauri::command]
+async fn update_settings(
+    update: SettingsUpdate,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+    limits: State<'_, SharedLimits>,
+    library: State<'_, Arc<Library>>,
+) -> Result<SettingsView, CommandError> {
+    let file = config_file(&app)?;
+    let config = config.change(|current| settings::save(&file, current.secrets(), update))?;
+    limits.set(StageLimits::of(&config));
+    library.set_videos_dir(config.get_output_dir());
+
+    Ok(SettingsView::new(&config, &file))
+}
+
+/// Load the config again, after the config file was edited by hand.
+#[tauri::command]
+async fn reload_settings(
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+    limits: State<'_, SharedLimits>,
+    library: State<'_, Arc<Library>>,
+) -> Result<SettingsView, CommandError> {
+    let file = config_file(&app)?;
+    let config = config.change(|current| GlobalConfig::load(&file, current.secrets().cloned()))?;
+    limits.set(StageLimits::of(&config));
+    library.set_videos_dir(config.get_output_dir());
+
+    Ok(SettingsView::new(&config, &file))
+}
+
+/// Open the encrypted vault with `passphrase` and keep the API keys in it from now on,
+/// for when there is no keyring. The keys in the config file are moved into it.
+#[tauri::command]
+async fn unlock_vault(
+    passphrase: String,
+    app: AppHandle,
+    config: State<'_, SharedConfig>,
+    limits: State<'_, SharedLimits>,
+    library: State<'_, Arc<Library>>,
+) -> Result<SettingsView, CommandError> {
+    let file = config_file(&app)?;
+    let vault = file.with_file_name(VAULT_FILE);
+    let store: SharedSecretStore = Arc::new(VaultStore::open(vault, &passphrase)?);
+    let config = config.change(|_| -> Result<GlobalConfig, CommandError> {
+        secrets::migrate(&file, &*store)?;
+        Ok(GlobalConfig::load(&file, Some(store))?)
+    })?;
+    limits.set(StageLimits::of(&config));
+    library.set_videos_dir(config.get_output_dir());
+
+    Ok(SettingsView::new(&config, &file))
+}
+
+/// `config.toml` in the app config dir.
+fn config_file(app: &AppHandle) -> Result<PathBuf, CommandError> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(CONFIG_FILE))
+        .map_err(|e| CommandError::new(ErrorKind::IO, e.to_string()))
+}
+
+/// What the scheduler has done and when it runs next.
+#[tauri::command]
+async fn get_schedule_status(
+    config: State<'_, SharedConfig>,
+    scheduler: State<'_, Arc<Scheduler>>,
+) -> Result<ScheduleStatus, CommandError> {
+    let config = config.get();
+    Ok(scheduler.status(&config.settings().schedule))
+}
+
+/// Cancel a running job, returns `false` when it has already ended.
+#[tauri::command]
+async fn cancel_job(id: JobId, jobs: State<'_, JobRegistry>) -> Result<bool, CommandError> {
+    Ok(jobs.cancel(&id))
 }
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
 pub fn run() {
     tauri::Builder::default()
         .setup(|app| {
-            app.manage(RwLock::new(crate::config::GlobalConfig::new()));
+            let file = config_file(app.handle())?;
+            let secrets = match file.parent() {
+                Some(dir) => secrets::open_store(dir)?,
+                None => None,
+            };
+            if let Some(store) = &secrets {
+                let moved = secrets::migrate(&file, &**store)?;
+                if !moved.is_empty() {
+                    eprintln!("moved {} to the {}", moved.join(", "), store.name());
+                }
+            }
+            let config = GlobalConfig::load(&file, secrets)?;
+            let limits = SharedLimits::new(StageLimits::of(&config));
+            let data_dir = app.path().app_data_dir()?;
+            let store = JobStore::load(data_dir.join(JOBS_FILE))?;
+            let scheduler = Arc::new(Scheduler::load(data_dir.join(SCHEDULE_FILE))?);
+            let library = Arc::new(Library::load(data_dir)?);
+            library.set_videos_dir(config.get_output_dir());
+
+            app.manage(SharedConfig::new(config));
+            app.manage(limits);
+            app.manage(JobRegistry::default());
+            app.manage(Arc::new(JobQueue::new(
+                app.handle().clone(),
+                store,
+                library.clone(),
+            )));
+            app.manage(library);
+            app.manage(scheduler.clone());
+
+            // runs as long as the app, the settings are read again on every tick
+            let target = QueueRun::new(app.handle().clone());
+            tauri::async_runtime::spawn(async move {
+                scheduler.run(&target, &CancellationToken::new()).await;
+            });
             Ok(())
         })
         .plugin(tauri_plugin_shell::init())
         .plugin(tauri_plugin_opener::init())
-        .invoke_handler(tauri::generate_handler![get_hot_news_list, gen_video])
+        .invoke_handler(tauri::generate_handler![
+            list_sources,
+            get_hot_news_list,
+            get_ranked_news,
+            list_channels,
+            get_channel_news,
+            search_news,
+            gen_video,
+            start_gen_video,
+            start_local_video,
+            cancel_job,
+            enqueue_jobs,
+            list_jobs,
+            retry_job,
+            remove_job,
+            list_library,
+            search_library,
+            open_video,
+            rerender_video,
+            delete_video,
+            get_settings,
+            update_settings,
+            reload_settings,
+            unlock_vault,
+            get_schedule_status
+        ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
 }
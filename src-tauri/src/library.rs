@@ -0,0 +1,344 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    director::{NewsShortVideo, workspace::Workspace},
+    news::{NewsPic, NewsTitle, Provenance},
+    time::now,
+};
+
+/// The index of the library in its dir
+pub const LIBRARY_FILE: &str = "library.json";
+/// The dir the videos are moved to by default
+const VIDEOS_DIR: &str = "videos";
+
+/// A generated video and what it was made of.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LibraryEntry {
+    pub id: String,
+    pub title: String,
+    pub news_title: NewsTitle,
+    pub summary: Vec<String>,
+    pub pics: Vec<NewsPic>,
+    /// what made the summary, `None` for the videos made before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+    /// `None` when there is no dubbing
+    pub voice: Option<String>,
+    /// seconds of each summary sentence
+    pub durations: Vec<f64>,
+    /// seconds of the whole video
+    pub duration: f64,
+    pub path: PathBuf,
+    /// unix seconds
+    pub created_at: u64,
+}
+
+impl LibraryEntry {
+    /// Whether the titles, the url or the summary contain `query`, ignoring case.
+    fn matches(&self, query: &str) -> bool {
+        let query = query.to_lowercase();
+
+        [&self.title, &self.news_title.title, &self.news_title.url]
+            .into_iter()
+            .chain(self.summary.iter())
+            .any(|text| text.to_lowercase().contains(&query))
+    }
+}
+
+/// The generated videos, one per news url.
+/// The videos are moved into the library dir and the index is saved on every change.
+pub struct Library {
+    dir: PathBuf,
+    /// where new videos are moved to, the existing ones stay where they are
+    videos_dir: Mutex<PathBuf>,
+    entries: Mutex<Vec<LibraryEntry>>,
+}
+
+impl Library {
+    /// Load the library kept in `dir`, an absent index is an empty library.
+    pub fn load(dir: PathBuf) -> io::Result<Self> {
+        let entries = match std::fs::read_to_string(dir.join(LIBRARY_FILE)) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => vec![],
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            videos_dir: Mutex::new(dir.join(VIDEOS_DIR)),
+            dir,
+            entries: Mutex::new(entries),
+        })
+    }
+
+    /// Move the next videos to `dir`, `None` is the `videos` dir of the library.
+    pub fn set_videos_dir(&self, dir: Option<PathBuf>) {
+        *self.videos_dir.lock().unwrap() = dir.unwrap_or_else(|| self.dir.join(VIDEOS_DIR));
+    }
+
+    /// All the entries, the newest first.
+    pub fn list(&self) -> Vec<LibraryEntry> {
+        self.entries.lock().unwrap().iter().rev().cloned().collect()
+    }
+
+    /// The entries matching `query`, the newest first.
+    pub fn search(&self, query: &str) -> Vec<LibraryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .rev()
+            .filter(|e| e.matches(query))
+            .cloned()
+            .collect()
+    }
+
+    pub fn get(&self, id: &str) -> Option<LibraryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.id == id)
+            .cloned()
+    }
+
+    /// The entry rendered from the news at `url`.
+    pub fn find_by_url(&self, url: &str) -> Option<LibraryEntry> {
+        self.entries
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|e| e.news_title.url == url)
+            .cloned()
+    }
+
+    /// Move the video into the library,
+    /// replacing the entry (and removing the video) of a previous render of the same news.
+    pub async fn add(&self, video: NewsShortVideo) -> io::Result<LibraryEntry> {
+        let id = nanoid::nanoid!(10);
+        let videos_dir = self.videos_dir.lock().unwrap().clone();
+        fs::create_dir_all(&videos_dir).await?;
+
+        let path = videos_dir.join(format!("{}.mp4", id));
+        move_file(&video.path, &path).await?;
+        // the artifacts are of no use once the video is in the library
+        _ = Workspace::prune(&video.path).await;
+        if let Some(dir) = video.path.parent() {
+            // only removed when empty
+            _ = fs::remove_dir(dir).await;
+        }
+
+        let entry = LibraryEntry {
+            id,
+            title: video.title,
+            summary: video.material.summary,
+            pics: video.material.pics,
+            provenance: video.material.provenance,
+            voice: video.voice,
+            durations: video.durations.iter().map(|d| d.as_secs_f64()).collect(),
+            duration: video.duration.as_secs_f64(),
+            path,
+            created_at: now(),
+            news_title: video.news_title,
+        };
+
+        let replaced = {
+            let mut entries = self.entries.lock().unwrap();
+            let replaced = entries
+                .iter()
+                .position(|e| e.news_title.url == entry.news_title.url)
+                .map(|index| entries.remove(index));
+            entries.push(entry.clone());
+            self.save(&entries);
+            replaced
+        };
+
+        if let Some(replaced) = replaced {
+            _ = fs::remove_file(&replaced.path).await;
+        }
+
+        Ok(entry)
+    }
+
+    /// Remove the entry and its video, returns `false` when there is no such entry.
+    pub async fn delete(&self, id: &str) -> io::Result<bool> {
+        let entry = {
+            let mut entries = self.entries.lock().unwrap();
+            let Some(index) = entries.iter().position(|e| e.id == id) else {
+                return Ok(false);
+            };
+            let entry = entries.remove(index);
+            self.save(&entries);
+            entry
+        };
+
+        match fs::remove_file(&entry.path).await {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(true),
+        }
+    }
+
+    fn save(&self, entries: &Vec<LibraryEntry>) {
+        let path = self.dir.join(LIBRARY_FILE);
+        let res = std::fs::create_dir_all(&self.dir)
+            .and_then(|_| std::fs::write(&path, serde_json::to_vec_pretty(entries)?));
+
+        if let Err(e) = res {
+            // log
+            eprintln!("failed to save library to {}: {}", path.display(), e);
+        }
+    }
+}
+
+/// Rename, or copy when `from` is on another file system.
+async fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if fs::rename(from, to).await.is_ok() {
+        return Ok(());
+    }
+
+    fs::copy(from, to).await?;
+    fs::remove_file(from).await
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use tempdir::TempDir;
+
+    use crate::news::{NEWS_MODEL_VERSION, NewsMaterial};
+
+    use super::*;
+
+    async fn video(dir: &Path, url: &str, title: &str) -> NewsShortVideo {
+        // each video in a dir of its own, like the workspace of a news
+        let dir = dir.join(nanoid::nanoid!(10));
+        fs::create_dir_all(&dir).await.unwrap();
+        let path = dir.join("final.mp4");
+        fs::write(&path, b"video").await.unwrap();
+
+        NewsShortVideo {
+            title: title.to_owned(),
+            path,
+            news_title: NewsTitle {
+                version: NEWS_MODEL_VERSION,
+                source: "pengpai".to_owned(),
+                title: title.to_owned(),
+                url: url.to_owned(),
+                pics: vec![],
+                videos: vec![],
+                meta: Default::default(),
+            },
+            material: NewsMaterial {
+                version: NEWS_MODEL_VERSION,
+                title: title.to_owned(),
+                summary: vec!["第一句".to_owned(), "第二句".to_owned()],
+                pics: vec!["https://example.com/a.jpg".into()],
+                videos: vec![],
+                article: vec![],
+                provenance: None,
+            },
+            voice: Some("Serena".to_owned()),
+            durations: vec![Duration::from_secs(1), Duration::from_millis(1500)],
+            duration: Duration::from_millis(2500),
+        }
+    }
+
+    #[tokio::test]
+    async fn add_moves_video_and_persists() {
+        let temp = TempDir::new("output").unwrap();
+        let dir = TempDir::new("library").unwrap();
+
+        let library = Library::load(dir.path().to_path_buf()).unwrap();
+        let video = video(temp.path(), "https://example.com/1", "Title").await;
+        let source = video.path.clone();
+
+        let entry = library.add(video).await.unwrap();
+        assert!(!source.exists());
+        assert!(entry.path.starts_with(dir.path()));
+        assert!(entry.path.exists());
+        assert_eq!(vec![1.0, 1.5], entry.durations);
+        assert_eq!(Some("Serena".to_owned()), entry.voice);
+
+        let library = Library::load(dir.path().to_path_buf()).unwrap();
+        assert_eq!(entry.id, library.list()[0].id);
+        assert!(library.find_by_url("https://example.com/1").is_some());
+    }
+
+    #[tokio::test]
+    async fn add_replaces_same_url() {
+        let temp = TempDir::new("output").unwrap();
+        let dir = TempDir::new("library").unwrap();
+        let library = Library::load(dir.path().to_path_buf()).unwrap();
+
+        let first = library
+            .add(video(temp.path(), "https://example.com/1", "Old").await)
+            .await
+            .unwrap();
+        let second = library
+            .add(video(temp.path(), "https://example.com/1", "New").await)
+            .await
+            .unwrap();
+
+        let entries = library.list();
+        assert_eq!(1, entries.len());
+        assert_eq!(second.id, entries[0].id);
+        assert!(!first.path.exists());
+    }
+
+    #[tokio::test]
+    async fn search_and_delete() {
+        let temp = TempDir::new("output").unwrap();
+        let dir = TempDir::new("library").unwrap();
+        let library = Library::load(dir.path().to_path_buf()).unwrap();
+
+        let rocket = library
+            .add(video(temp.path(), "https://example.com/1", "Rocket Launch").await)
+            .await
+            .unwrap();
+        library
+            .add(video(temp.path(), "https://example.com/2", "Weather").await)
+            .await
+            .unwrap();
+
+        let found = library.search("rocket");
+        assert_eq!(1, found.len());
+        assert_eq!(rocket.id, found[0].id);
+        assert_eq!(2, library.search("第二句").len());
+
+        assert!(library.delete(&rocket.id).await.unwrap());
+        assert!(!rocket.path.exists());
+        assert!(!library.delete(&rocket.id).await.unwrap());
+        assert_eq!(1, library.list().len());
+    }
+
+    #[tokio::test]
+    async fn set_videos_dir() {
+        let temp = TempDir::new("output").unwrap();
+        let dir = TempDir::new("library").unwrap();
+        let out = TempDir::new("videos").unwrap();
+        let library = Library::load(dir.path().to_path_buf()).unwrap();
+
+        library.set_videos_dir(Some(out.path().to_path_buf()));
+        let moved = library
+            .add(video(temp.path(), "https://example.com/1", "Moved").await)
+            .await
+            .unwrap();
+        assert!(moved.path.starts_with(out.path()));
+
+        library.set_videos_dir(None);
+        let default = library
+            .add(video(temp.path(), "https://example.com/2", "Default").await)
+            .await
+            .unwrap();
+        assert!(default.path.starts_with(dir.path().join(VIDEOS_DIR)));
+        assert!(moved.path.exists());
+    }
+}
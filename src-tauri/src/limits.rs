@@ -0,0 +1,103 @@
+use std::sync::{Arc, RwLock};
+
+use tokio::sync::{Semaphore, SemaphorePermit};
+use tokio_util::sync::CancellationToken;
+
+use crate::config::GlobalConfig;
+
+/// How many jobs may be in each expensive stage at the same time,
+/// shared by every `Director` that runs jobs of the same queue.
+#[derive(Clone)]
+pub struct StageLimits {
+    /// the LLM summarization in the material stage
+    pub llm: Arc<Semaphore>,
+    /// the text to speech requests
+    pub tts: Arc<Semaphore>,
+    /// the ffmpeg processes, see `LimitedRunner`
+    pub ffmpeg: Arc<Semaphore>,
+    /// the permits of `llm`, `tts` and `ffmpeg`
+    sizes: [usize; 3],
+}
+
+impl StageLimits {
+    pub fn new(llm: usize, tts: usize, ffmpeg: usize) -> Self {
+        let sizes = [llm.max(1), tts.max(1), ffmpeg.max(1)];
+        Self {
+            llm: Arc::new(Semaphore::new(sizes[0])),
+            tts: Arc::new(Semaphore::new(sizes[1])),
+            ffmpeg: Arc::new(Semaphore::new(sizes[2])),
+            sizes,
+        }
+    }
+
+    /// The limits set by `LLM_CONCURRENCY`, `TTS_CONCURRENCY` and `FFMPEG_CONCURRENCY`.
+    pub fn of(config: &GlobalConfig) -> Self {
+        Self::new(
+            config.get_llm_concurrency(),
+            config.get_tts_concurrency(),
+            config.get_ffmpeg_concurrency(),
+        )
+    }
+}
+
+/// The limits of the app, shared by the queued jobs and the commands.
+/// They are replaced when the concurrency settings change, a job keeps the limits it started with,
+/// so until it ends it is not counted against the new ones.
+pub struct SharedLimits {
+    current: RwLock<StageLimits>,
+}
+
+impl SharedLimits {
+    pub fn new(limits: StageLimits) -> Self {
+        Self {
+            current: RwLock::new(limits),
+        }
+    }
+
+    /// The limits as they are now.
+    pub fn get(&self) -> StageLimits {
+        self.current.read().unwrap().clone()
+    }
+
+    /// Replace the limits, unless `limits` allow as many jobs in each stage.
+    pub fn set(&self, limits: StageLimits) {
+        let mut current = self.current.write().unwrap();
+        if current.sizes != limits.sizes {
+            *current = limits;
+        }
+    }
+}
+
+/// Wait for a permit, `None` when cancelled first.
+pub async fn acquire<'a>(
+    semaphore: &'a Semaphore,
+    cancel: &CancellationToken,
+) -> Option<SemaphorePermit<'a>> {
+    cancel
+        .run_until_cancelled(semaphore.acquire())
+        .await
+        // the semaphores are never closed
+        .map(|permit| permit.expect("stage semaphore closed"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shared_limits_replaced_on_change() {
+        let shared = SharedLimits::new(StageLimits::new(2, 2, 1));
+        let before = shared.get();
+
+        // the same sizes, the jobs running still count
+        shared.set(StageLimits::new(2, 2, 1));
+        assert!(Arc::ptr_eq(&before.llm, &shared.get().llm));
+
+        shared.set(StageLimits::new(4, 0, 1));
+        let after = shared.get();
+        assert!(!Arc::ptr_eq(&before.llm, &after.llm));
+        assert_eq!(4, after.llm.available_permits());
+        assert_eq!(1, after.tts.available_permits());
+        assert_eq!(2, before.llm.available_permits());
+    }
+}
@@ -0,0 +1,54 @@
+use std::{collections::VecDeque, sync::Mutex};
+
+use crate::llm::{LlmClient, LlmError, LlmMessage};
+
+/// A chat model answering from a list, for the tests of what is built on `LlmClient`.
+pub struct MockLlm {
+    model: String,
+    answers: Mutex<VecDeque<Result<String, LlmError>>>,
+    requests: Mutex<Vec<Vec<LlmMessage>>>,
+}
+
+impl MockLlm {
+    pub fn new(model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            answers: Mutex::new(VecDeque::new()),
+            requests: Mutex::new(vec![]),
+        }
+    }
+
+    /// Answer the next chat with `answer`, after the answers added before.
+    pub fn with_answer(self, answer: impl Into<String>) -> Self {
+        self.answers.lock().unwrap().push_back(Ok(answer.into()));
+        self
+    }
+
+    /// Fail the next chat with `error`, after the answers added before.
+    pub fn with_error(self, error: LlmError) -> Self {
+        self.answers.lock().unwrap().push_back(Err(error));
+        self
+    }
+
+    /// The messages of each chat so far, in order.
+    pub fn requests(&self) -> Vec<Vec<LlmMessage>> {
+        self.requests.lock().unwrap().clone()
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmClient for MockLlm {
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    async fn chat(&self, messages: &[LlmMessage]) -> Result<String, LlmError> {
+        self.requests.lock().unwrap().push(messages.to_vec());
+        // out of answers, like an endpoint answering nothing
+        self.answers
+            .lock()
+            .unwrap()
+            .pop_front()
+            .unwrap_or(Err(LlmError::Empty))
+    }
+}
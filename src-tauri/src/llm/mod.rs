@@ -0,0 +1,155 @@
+pub mod mock;
+pub mod openai_compatible;
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// Who a chat message is from.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LlmRole {
+    System,
+    User,
+    Assistant,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LlmMessage {
+    pub role: LlmRole,
+    pub content: String,
+}
+
+impl LlmMessage {
+    pub fn system(content: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::System,
+            content: content.into(),
+        }
+    }
+
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: LlmRole::User,
+            content: content.into(),
+        }
+    }
+}
+
+#[derive(Error, Debug)]
+pub enum LlmError {
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("the request timed out")]
+    Timeout,
+    #[error("the endpoint answered {status}: {message}")]
+    Status { status: u16, message: String },
+    #[error("unexpected response: {0}")]
+    Response(String),
+    #[error("empty answer")]
+    Empty,
+}
+
+/// A chat model, whatever serves it.
+#[async_trait::async_trait]
+pub trait LlmClient {
+    /// The name of the model, recorded in the provenance of the summaries.
+    fn model(&self) -> String;
+
+    /// The settings the answers depend on, part of the cache key of the material stage.
+    fn cache_key(&self) -> String {
+        self.model()
+    }
+
+    /// The text of the answer to `messages`.
+    async fn chat(&self, messages: &[LlmMessage]) -> Result<String, LlmError>;
+}
+
+/// Where the chat models are served, each with the base url of its OpenAI compatible API.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum LlmProvider {
+    #[default]
+    Deepseek,
+    /// the compatible mode of Ali DashScope, for the Qwen models
+    Dashscope,
+    /// a local Ollama server
+    Ollama,
+    /// a local llama.cpp server
+    LlamaCpp,
+}
+
+impl LlmProvider {
+    pub fn base_url(&self) -> &'static str {
+        match self {
+            Self::Deepseek => "https://api.deepseek.com",
+            Self::Dashscope => "https://dashscope.aliyuncs.com/compatible-mode/v1",
+            Self::Ollama => "http://localhost:11434/v1",
+            Self::LlamaCpp => "http://localhost:8080/v1",
+        }
+    }
+
+    /// Whether the endpoint is local and needs no API key.
+    pub fn is_local(&self) -> bool {
+        matches!(self, Self::Ollama | Self::LlamaCpp)
+    }
+}
+
+/// The endpoint of the chat model, in the config as `[LLM]`, the model is `LLM_MODEL`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", default)]
+pub struct LlmSettings {
+    pub provider: LlmProvider,
+    /// any OpenAI compatible endpoint, the one of the provider when unset
+    pub base_url: Option<String>,
+    /// from 0 to 2, the default of the endpoint when unset
+    pub temperature: Option<f32>,
+    /// how long a request may take, in seconds
+    pub timeout: u64,
+    /// the most tokens of an answer, the default of the endpoint when unset
+    pub max_tokens: Option<u32>,
+}
+
+impl Default for LlmSettings {
+    fn default() -> Self {
+        Self {
+            provider: LlmProvider::default(),
+            base_url: None,
+            temperature: None,
+            timeout: 120,
+            max_tokens: None,
+        }
+    }
+}
+
+impl LlmSettings {
+    /// The base url of the endpoint.
+    pub fn base_url(&self) -> &str {
+        self.base_url
+            .as_deref()
+            .unwrap_or_else(|| self.provider.base_url())
+    }
+
+    /// The keys that are set but wrong with why, `BASE_URL` for `base_url`.
+    pub fn invalid(&self) -> Vec<(&'static str, String)> {
+        let mut errors = vec![];
+
+        if let Some(url) = &self.base_url
+            && reqwest::Url::parse(url).is_err()
+        {
+            errors.push(("BASE_URL", "is not a url".to_owned()));
+        }
+        if let Some(temperature) = self.temperature
+            && !(0.0..=2.0).contains(&temperature)
+        {
+            errors.push(("TEMPERATURE", "must be from 0 to 2".to_owned()));
+        }
+        if self.timeout == 0 {
+            errors.push(("TIMEOUT", "must be at least 1".to_owned()));
+        }
+        if self.max_tokens == Some(0) {
+            errors.push(("MAX_TOKENS", "must be at least 1".to_owned()));
+        }
+
+        errors
+    }
+}
@@ -0,0 +1,236 @@
+use std::time::Duration;
+
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::llm::{LlmClient, LlmError, LlmMessage, LlmSettings};
+
+const CHAT_PATH: &str = "/chat/completions";
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// A chat model behind an OpenAI compatible `/chat/completions` endpoint,
+/// DeepSeek, the compatible mode of DashScope, Ollama or llama.cpp.
+pub struct OpenAiCompatible {
+    http: Client,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    temperature: Option<f32>,
+    max_tokens: Option<u32>,
+    timeout: Duration,
+}
+
+impl OpenAiCompatible {
+    /// base_url: the url the `/chat/completions` path is appended to.
+    /// api_key: sent as a bearer token, the local servers need none.
+    pub fn new(
+        base_url: impl Into<String>,
+        api_key: Option<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self {
+            http: Client::new(),
+            base_url: base_url.into().trim_end_matches('/').to_owned(),
+            api_key,
+            model: model.into(),
+            temperature: None,
+            max_tokens: None,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// The client of the endpoint of `settings`.
+    pub fn from_settings(
+        settings: &LlmSettings,
+        api_key: Option<String>,
+        model: impl Into<String>,
+    ) -> Self {
+        Self::new(settings.base_url(), api_key, model)
+            .with_temperature(settings.temperature)
+            .with_max_tokens(settings.max_tokens)
+            .with_timeout(Duration::from_secs(settings.timeout))
+    }
+
+    /// The sampling temperature, the default of the endpoint when `None`.
+    pub fn with_temperature(self, temperature: Option<f32>) -> Self {
+        Self {
+            temperature,
+            ..self
+        }
+    }
+
+    /// The most tokens of an answer, the default of the endpoint when `None`.
+    pub fn with_max_tokens(self, max_tokens: Option<u32>) -> Self {
+        Self { max_tokens, ..self }
+    }
+
+    /// How long a request may take, 2 minutes by default.
+    pub fn with_timeout(self, timeout: Duration) -> Self {
+        Self { timeout, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl LlmClient for OpenAiCompatible {
+    fn model(&self) -> String {
+        self.model.clone()
+    }
+
+    fn cache_key(&self) -> String {
+        // the same model served elsewhere may answer differently
+        format!(
+            "{}\n{}\n{:?}\n{:?}",
+            self.base_url, self.model, self.temperature, self.max_tokens
+        )
+    }
+
+    async fn chat(&self, messages: &[LlmMessage]) -> Result<String, LlmError> {
+        let body = ChatRequest {
+            model: &self.model,
+            messages,
+            temperature: self.temperature,
+            max_tokens: self.max_tokens,
+        };
+
+        let mut request = self
+            .http
+            .post(format!("{}{}", self.base_url, CHAT_PATH))
+            .timeout(self.timeout)
+            .json(&body);
+        if let Some(key) = &self.api_key {
+            request = request.bearer_auth(key);
+        }
+
+        let response = request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                LlmError::Timeout
+            } else {
+                LlmError::Network(e.to_string())
+            }
+        })?;
+        let status = response.status();
+        if !status.is_success() {
+            let message = response.text().await.unwrap_or_default();
+            return Err(LlmError::Status {
+                status: status.as_u16(),
+                message,
+            });
+        }
+
+        let json: ChatResponse = response
+            .json()
+            .await
+            .map_err(|e| LlmError::Response(e.to_string()))?;
+
+        json.choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .filter(|content| !content.trim().is_empty())
+            .ok_or(LlmError::Empty)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: &'a [LlmMessage],
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    max_tokens: Option<u32>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ChoiceMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    content: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn chat_completion() {
+        let mut server = mockito::Server::new_async().await;
+        let _chat = server
+            .mock("POST", CHAT_PATH)
+            .match_header("authorization", "Bearer sk-test")
+            .match_body(Matcher::Json(json!({
+                "model": "qwen-plus",
+                "messages": [
+                    {"role": "system", "content": "be brief"},
+                    {"role": "user", "content": "hi"},
+                ],
+                "temperature": 0.5,
+                "max_tokens": 100,
+            })))
+            .with_status(200)
+            .with_body(r#"{"choices": [{"message": {"role": "assistant", "content": "hello"}}]}"#)
+            .create_async()
+            .await;
+
+        let llm = OpenAiCompatible::new(
+            format!("{}/", server.url()),
+            Some("sk-test".to_owned()),
+            "qwen-plus",
+        )
+        .with_temperature(Some(0.5))
+        .with_max_tokens(Some(100));
+        let answer = llm
+            .chat(&[LlmMessage::system("be brief"), LlmMessage::user("hi")])
+            .await
+            .unwrap();
+
+        assert_eq!("hello", answer);
+    }
+
+    #[tokio::test]
+    async fn errors() {
+        let mut server = mockito::Server::new_async().await;
+        let _chat = server
+            .mock("POST", CHAT_PATH)
+            .match_header("authorization", Matcher::Missing)
+            .with_status(401)
+            .with_body("no key")
+            .create_async()
+            .await;
+
+        let llm = OpenAiCompatible::new(server.url(), None, "llama3");
+        let result = llm.chat(&[LlmMessage::user("hi")]).await;
+
+        assert!(matches!(
+            result,
+            Err(LlmError::Status { status: 401, message }) if message == "no key"
+        ));
+    }
+
+    #[test]
+    fn settings() {
+        let settings = LlmSettings {
+            provider: crate::llm::LlmProvider::Ollama,
+            temperature: Some(0.2),
+            timeout: 30,
+            ..Default::default()
+        };
+
+        let llm = OpenAiCompatible::from_settings(&settings, None, "qwen2.5");
+        assert_eq!("http://localhost:11434/v1", llm.base_url);
+        assert_eq!(Some(0.2), llm.temperature);
+        assert_eq!(Duration::from_secs(30), llm.timeout);
+        assert_eq!("qwen2.5", llm.model());
+    }
+}
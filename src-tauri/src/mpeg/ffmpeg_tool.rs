@@ -1,71 +1,100 @@
-use std::path::PathBuf;
+use std::{ffi::OsString, path::PathBuf, sync::Arc};
 
-use tauri::AppHandle;
-use tauri_plugin_shell::{
-    ShellExt,
-    process::{CommandEvent, TerminatedPayload},
-};
+use tokio_util::sync::CancellationToken;
 
-use crate::mpeg::{
-    ComposeTool, ComposeToolError, VideoEditTool, VideoEditToolError, VoiceEditTool,
+use crate::{
+    mpeg::{
+        ComposeTool, ComposeToolError, VideoEditTool, VideoEditToolError, VoiceEditTool,
+        VoiceEditToolError,
+        runner::{FFmpegRunner, FFmpegRunnerError},
+    },
+    profile::Resolution,
+    progress::ProgressReporter,
 };
 
-pub struct FFmpeg4Video(pub AppHandle);
+/// The runner shared by all the ffmpeg tools.
+pub type SharedRunner = Arc<dyn FFmpegRunner + Sync + Send + 'static>;
+
+/// Run ffmpeg, removing the partial `output` when it fails or is cancelled.
+async fn run_to(
+    runner: &(dyn FFmpegRunner + Sync + Send),
+    args: Vec<OsString>,
+    output: &PathBuf,
+    progress: &dyn ProgressReporter,
+    cancel: &CancellationToken,
+) -> Result<(), FFmpegRunnerError> {
+    let res = runner.run(args, progress, cancel).await;
+    if res.is_err() {
+        _ = tokio::fs::remove_file(output).await;
+    }
+
+    res
+}
+
+pub struct FFmpeg4Video {
+    runner: SharedRunner,
+    resolution: Resolution,
+}
+
+impl FFmpeg4Video {
+    pub fn new(runner: SharedRunner) -> Self {
+        Self {
+            runner,
+            resolution: Resolution::default(),
+        }
+    }
+
+    pub fn with_resolution(self, resolution: Resolution) -> Self {
+        Self { resolution, ..self }
+    }
+}
 
 #[async_trait::async_trait]
 impl VideoEditTool for FFmpeg4Video {
+    fn cache_key(&self) -> String {
+        self.resolution.to_string()
+    }
+
     async fn compose_images(
         &self,
         file_list_path: &PathBuf,
         output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
     ) -> Result<(), VideoEditToolError> {
-        let ffmpeg = self.0.shell().sidecar("ffmpeg").map_err(|_| VideoEditToolError::Init("launching ffmpeg failed".to_owned()))?
-        .arg("-y")
-            .arg("-f")
-            .arg("concat")
-            .arg("-i")
-            .arg(&file_list_path)
-            .arg("-vf")
-            .arg("scale=720:1280:force_original_aspect_ratio=decrease,pad=720:1280:(ow-iw)/2:(oh-ih)/2:color=black")
-            .arg("-c:v")
-            .arg("libx264")
-            .arg("-r")
-            .arg("30")
-            .arg("-pix_fmt")
-            .arg("yuv420p")
-            .arg(output);
-
-        let (mut rx, mut _child) = ffmpeg.spawn().expect("Failed to spawn sidecar");
-        let join = tauri::async_runtime::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(line) => {
-                        // log
-                        print!("{}", String::from_utf8(line).unwrap());
-                    }
-                    CommandEvent::Stderr(line) => {
-                        // log
-                        print!("{}", String::from_utf8(line).unwrap());
-                    }
-                    CommandEvent::Error(line) => {
-                        // log
-                        print!("{}", line);
-                    }
-                    CommandEvent::Terminated(TerminatedPayload { code: Some(v), .. }) if v != 0 => {
-                        // log
-                        print!("{}", v);
-                    }
-                    _ => {}
-                }
-            }
-        });
-        join.await.expect("ffmpeg handle video error");
+        let Resolution { width, height } = self.resolution;
+        let args: Vec<OsString> = vec![
+            "-y".into(),
+            "-f".into(),
+            "concat".into(),
+            "-i".into(),
+            file_list_path.into(),
+            "-vf".into(),
+            format!("scale={width}:{height}:force_original_aspect_ratio=decrease,pad={width}:{height}:(ow-iw)/2:(oh-ih)/2:color=black").into(),
+            "-c:v".into(),
+            "libx264".into(),
+            "-r".into(),
+            "30".into(),
+            "-pix_fmt".into(),
+            "yuv420p".into(),
+            output.into(),
+        ];
+
+        run_to(&*self.runner, args, output, progress, cancel).await?;
 
         Ok(())
     }
 }
 
-pub struct FFmpeg4Voice(pub AppHandle);
+pub struct FFmpeg4Voice {
+    runner: SharedRunner,
+}
+
+impl FFmpeg4Voice {
+    pub fn new(runner: SharedRunner) -> Self {
+        Self { runner }
+    }
+}
 
 #[async_trait::async_trait]
 impl VoiceEditTool for FFmpeg4Voice {
@@ -73,106 +102,329 @@ impl VoiceEditTool for FFmpeg4Voice {
         &self,
         input: &PathBuf,
         output: &PathBuf,
-    ) -> Result<(), VideoEditToolError> {
-        let ffmpeg = self
-            .0
-            .shell()
-            .sidecar("ffmpeg")
-            .expect("Failed to get sidecar: ffmpeg")
-            .args([
-                "-i",
-                input.to_str().unwrap(),
-                "-af",
-                "asetrate=30000, aresample=22050, atempo=1",
-                output.to_str().unwrap(),
-            ]);
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), VoiceEditToolError> {
+        let args: Vec<OsString> = vec![
+            "-i".into(),
+            input.into(),
+            "-af".into(),
+            "asetrate=30000, aresample=22050, atempo=1".into(),
+            output.into(),
+        ];
+
+        run_to(&*self.runner, args, output, progress, cancel).await?;
+
+        Ok(())
+    }
+}
+
+pub struct FFmpeg4Compose {
+    runner: SharedRunner,
+    /// the ASS style of the subtitles
+    subtitle_style: Option<String>,
+    /// the text drawn in the top right corner
+    watermark: Option<String>,
+}
 
-        let (mut rx, mut _child) = ffmpeg.spawn().expect("Failed to spawn sidecar");
-        let join = tauri::async_runtime::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(_line) => {
-                        // log
-                    }
-                    CommandEvent::Stderr(_line) => {
-                        // log
-                    }
-                    CommandEvent::Error(_line) => {
-                        // log
-                    }
-                    CommandEvent::Terminated(TerminatedPayload { code: Some(v), .. }) if v != 0 => {
-                        // log
-                    }
-                    _ => {}
-                }
+impl FFmpeg4Compose {
+    pub fn new(runner: SharedRunner) -> Self {
+        Self {
+            runner,
+            subtitle_style: None,
+            watermark: None,
+        }
+    }
+
+    pub fn with_subtitle_style(self, style: Option<String>) -> Self {
+        Self {
+            subtitle_style: style,
+            ..self
+        }
+    }
+
+    pub fn with_watermark(self, text: Option<String>) -> Self {
+        Self {
+            watermark: text,
+            ..self
+        }
+    }
+
+    /// The `-vf` filters burning the subtitles and drawing the watermark.
+    fn filters(&self, subtitle_input: Option<&PathBuf>) -> Vec<String> {
+        let mut filters = vec![];
+
+        if let Some(subtitle_input) = subtitle_input {
+            let s = subtitle_input.display().to_string().replace('\\', "/");
+            match &self.subtitle_style {
+                Some(style) => filters.push(format!(
+                    "subtitles='{}':force_style='{}'",
+                    s,
+                    escape_filter_value(style)
+                )),
+                None => filters.push(format!("subtitles='{}'", s)),
             }
-        });
+        }
 
-        join.await.expect("ffmpeg handle voice error");
+        if let Some(text) = &self.watermark {
+            filters.push(format!(
+                "drawtext=text='{}':fontsize=h/40:fontcolor=white@0.6:x=w-tw-h/60:y=h/60",
+                escape_filter_value(&text.replace('\\', "\\\\").replace('%', "\\%"))
+            ));
+        }
 
-        Ok(())
+        filters
     }
 }
 
-pub struct FFmpeg4Compose(pub AppHandle);
+/// Escape a value put between single quotes in a filter graph.
+fn escape_filter_value(value: &str) -> String {
+    value.replace('\'', "'\\''")
+}
 
 #[async_trait::async_trait]
 impl ComposeTool for FFmpeg4Compose {
+    fn cache_key(&self) -> String {
+        format!(
+            "{}\n{}",
+            self.subtitle_style.as_deref().unwrap_or_default(),
+            self.watermark.as_deref().unwrap_or_default()
+        )
+    }
+
     async fn compose_all(
         &self,
         video_input: &PathBuf,
-        voice_input: &PathBuf,
-        subtitle_input: &PathBuf,
+        voice_input: Option<&PathBuf>,
+        subtitle_input: Option<&PathBuf>,
         output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
     ) -> Result<(), ComposeToolError> {
-        let s = subtitle_input.display().to_string().replace('\\', "/");
-        let ffmpeg = self
-            .0
-            .shell()
-            .sidecar("ffmpeg")
-            .expect("Failed to get sidecar: ffmpeg")
-            .arg("-i")
-            .arg(video_input)
-            .arg("-i")
-            .arg(voice_input)
-            .arg("-vf")
-            .arg(&format!("subtitles='{}'", s))
-            .arg("-c:v")
-            .arg("libx264")
-            .arg("-c:a")
-            .arg("aac")
-            .arg("-b:a")
-            .arg("192k")
-            .arg("-shortest")
-            .arg("-y")
-            .arg(output);
-
-        let (mut rx, mut _child) = ffmpeg.spawn().expect("Failed to spawn sidecar");
-        let join = tauri::async_runtime::spawn(async move {
-            while let Some(event) = rx.recv().await {
-                match event {
-                    CommandEvent::Stdout(line) => {
-                        // log
-                        print!("{}", String::from_utf8(line).unwrap());
-                    }
-                    CommandEvent::Stderr(line) => {
-                        // log
-                        print!("{}", String::from_utf8(line).unwrap());
-                    }
-                    CommandEvent::Error(line) => {
-                        // log
-                        print!("{}", line);
-                    }
-                    CommandEvent::Terminated(TerminatedPayload { code: Some(v), .. }) if v != 0 => {
-                        // log
-                        print!("{}", v);
-                    }
-                    _ => {}
-                }
-            }
-        });
-        join.await.expect("ffmpeg handle compose error");
+        let mut args: Vec<OsString> = vec!["-i".into(), video_input.into()];
+
+        if let Some(voice_input) = voice_input {
+            args.extend(["-i".into(), voice_input.into()]);
+        }
+
+        let filters = self.filters(subtitle_input);
+        if !filters.is_empty() {
+            args.extend(["-vf".into(), filters.join(",").into()]);
+        }
+
+        args.extend(["-c:v".into(), "libx264".into()]);
+
+        if voice_input.is_some() {
+            args.extend([
+                "-c:a".into(),
+                "aac".into(),
+                "-b:a".into(),
+                "192k".into(),
+                "-shortest".into(),
+            ]);
+        } else {
+            args.push("-an".into());
+        }
+
+        args.extend(["-y".into(), output.into()]);
+
+        run_to(&*self.runner, args, output, progress, cancel).await?;
 
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use crate::{mpeg::runner::FFmpegFailure, progress::NoopReporter};
+
+    use super::*;
+
+    /// records the command lines instead of running ffmpeg
+    #[derive(Default)]
+    struct FakeRunner {
+        calls: Mutex<Vec<Vec<OsString>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl FFmpegRunner for FakeRunner {
+        async fn run(
+            &self,
+            args: Vec<OsString>,
+            _: &dyn ProgressReporter,
+            _: &CancellationToken,
+        ) -> Result<(), FFmpegRunnerError> {
+            self.calls.lock().unwrap().push(args);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn tools_share_one_runner() {
+        let runner = Arc::new(FakeRunner::default());
+
+        let video = FFmpeg4Video::new(runner.clone());
+        let voice = FFmpeg4Voice::new(runner.clone());
+        let compose = FFmpeg4Compose::new(runner.clone());
+
+        video
+            .compose_images(
+                &PathBuf::from("list.txt"),
+                &PathBuf::from("video.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        voice
+            .cartoned_voice(
+                &PathBuf::from("in.wav"),
+                &PathBuf::from("out.wav"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        compose
+            .compose_all(
+                &PathBuf::from("video.mp4"),
+                Some(&PathBuf::from("voice.wav")),
+                Some(&PathBuf::from("sub.srt")),
+                &PathBuf::from("final.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let calls = runner.calls.lock().unwrap();
+        assert_eq!(3, calls.len());
+        assert_eq!(Some(&OsString::from("video.mp4")), calls[0].last());
+        assert_eq!(Some(&OsString::from("out.wav")), calls[1].last());
+        assert!(calls[2].contains(&OsString::from("subtitles='sub.srt'")));
+        assert_eq!(Some(&OsString::from("final.mp4")), calls[2].last());
+    }
+
+    #[tokio::test]
+    async fn compose_without_dubbing_and_subtitle() {
+        let runner = Arc::new(FakeRunner::default());
+        let compose = FFmpeg4Compose::new(runner.clone());
+
+        compose
+            .compose_all(
+                &PathBuf::from("video.mp4"),
+                None,
+                None,
+                &PathBuf::from("final.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let calls = runner.calls.lock().unwrap();
+        let args: Vec<&str> = calls[0].iter().map(|a| a.to_str().unwrap()).collect();
+        assert_eq!(
+            vec![
+                "-i",
+                "video.mp4",
+                "-c:v",
+                "libx264",
+                "-an",
+                "-y",
+                "final.mp4"
+            ],
+            args
+        );
+    }
+
+    #[tokio::test]
+    async fn profile_filters() {
+        let runner = Arc::new(FakeRunner::default());
+        let video = FFmpeg4Video::new(runner.clone()).with_resolution(Resolution {
+            width: 1080,
+            height: 1920,
+        });
+        let compose = FFmpeg4Compose::new(runner.clone())
+            .with_subtitle_style(Some("FontSize=24,Outline=1".to_owned()))
+            .with_watermark(Some("Tom's 100%".to_owned()));
+
+        video
+            .compose_images(
+                &PathBuf::from("list.txt"),
+                &PathBuf::from("video.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+        compose
+            .compose_all(
+                &PathBuf::from("video.mp4"),
+                None,
+                Some(&PathBuf::from("sub.srt")),
+                &PathBuf::from("final.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        let calls = runner.calls.lock().unwrap();
+        assert!(
+            calls[0][6]
+                .to_str()
+                .unwrap()
+                .starts_with("scale=1080:1920:")
+        );
+        assert_eq!(
+            OsString::from(
+                "subtitles='sub.srt':force_style='FontSize=24,Outline=1',\
+                 drawtext=text='Tom'\\''s 100\\%':fontsize=h/40:fontcolor=white@0.6:x=w-tw-h/60:y=h/60"
+            ),
+            calls[1][3]
+        );
+    }
+
+    #[tokio::test]
+    async fn tool_error_carries_ffmpeg_failure() {
+        struct FailingRunner;
+
+        #[async_trait::async_trait]
+        impl FFmpegRunner for FailingRunner {
+            async fn run(
+                &self,
+                _: Vec<OsString>,
+                _: &dyn ProgressReporter,
+                _: &CancellationToken,
+            ) -> Result<(), FFmpegRunnerError> {
+                Err(FFmpegRunnerError::Exit(FFmpegFailure {
+                    code: Some(1),
+                    signal: None,
+                    command: "ffmpeg -i video.mp4".to_owned(),
+                    stderr: vec!["Conversion failed!".to_owned()],
+                }))
+            }
+        }
+
+        let compose = FFmpeg4Compose::new(Arc::new(FailingRunner));
+        let res = compose
+            .compose_all(
+                &PathBuf::from("video.mp4"),
+                Some(&PathBuf::from("voice.wav")),
+                Some(&PathBuf::from("sub.srt")),
+                &PathBuf::from("final.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        let Err(ComposeToolError::Exit(failure)) = res else {
+            panic!("expected an exit error");
+        };
+        assert_eq!(Some(1), failure.code);
+        assert_eq!(vec!["Conversion failed!"], failure.stderr);
+    }
+}
@@ -1,40 +1,125 @@
 pub mod ffmpeg_tool;
+pub mod runner;
 
 use std::path::PathBuf;
 
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    mpeg::runner::{FFmpegFailure, FFmpegRunnerError},
+    progress::ProgressReporter,
+};
 
 #[async_trait::async_trait]
 pub trait VideoEditTool {
-    async fn compose_images(&self, file_list_path: &PathBuf, output: &PathBuf) -> Result<(), VideoEditToolError>;
+    /// The settings the output depends on, part of the cache key of the stage.
+    fn cache_key(&self) -> String {
+        String::new()
+    }
+
+    async fn compose_images(
+        &self,
+        file_list_path: &PathBuf,
+        output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), VideoEditToolError>;
 }
 
 #[derive(Error, Debug)]
 pub enum VideoEditToolError {
     #[error("init tool failed: {0}")]
-    Init(String)
+    Init(String),
+    #[error("launching tool failed: {0}")]
+    Spawn(String),
+    #[error("composing images failed, {0}")]
+    Exit(FFmpegFailure),
+    #[error("cancelled")]
+    Cancelled,
 }
 
+impl From<FFmpegRunnerError> for VideoEditToolError {
+    fn from(e: FFmpegRunnerError) -> Self {
+        match e {
+            FFmpegRunnerError::Spawn(msg) => Self::Spawn(msg),
+            FFmpegRunnerError::Exit(failure) => Self::Exit(failure),
+            FFmpegRunnerError::Cancelled => Self::Cancelled,
+        }
+    }
+}
 
 #[async_trait::async_trait]
 pub trait VoiceEditTool {
-    async fn cartoned_voice(&self, input: &PathBuf, output: &PathBuf) -> Result<(), VideoEditToolError>;
+    async fn cartoned_voice(
+        &self,
+        input: &PathBuf,
+        output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), VoiceEditToolError>;
 }
 
 #[derive(Error, Debug)]
 pub enum VoiceEditToolError {
     #[error("Failed to handle voice: {0}")]
-    Voice(String)
+    Voice(String),
+    #[error("launching tool failed: {0}")]
+    Spawn(String),
+    #[error("Failed to handle voice, {0}")]
+    Exit(FFmpegFailure),
+    #[error("cancelled")]
+    Cancelled,
 }
 
+impl From<FFmpegRunnerError> for VoiceEditToolError {
+    fn from(e: FFmpegRunnerError) -> Self {
+        match e {
+            FFmpegRunnerError::Spawn(msg) => Self::Spawn(msg),
+            FFmpegRunnerError::Exit(failure) => Self::Exit(failure),
+            FFmpegRunnerError::Cancelled => Self::Cancelled,
+        }
+    }
+}
 
 #[async_trait::async_trait]
 pub trait ComposeTool {
-    async fn compose_all(&self, video_input: &PathBuf, voice_input: &PathBuf, subtitle_input: &PathBuf, output: &PathBuf) -> Result<(), ComposeToolError>;
+    /// The settings the output depends on, part of the cache key of the stage.
+    fn cache_key(&self) -> String {
+        String::new()
+    }
+
+    /// Put the video, the dubbing and the subtitles together,
+    /// without a dubbing the output is silent, without subtitles nothing is burnt in.
+    async fn compose_all(
+        &self,
+        video_input: &PathBuf,
+        voice_input: Option<&PathBuf>,
+        subtitle_input: Option<&PathBuf>,
+        output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), ComposeToolError>;
 }
 
 #[derive(Error, Debug)]
 pub enum ComposeToolError {
     #[error("Failed to to compose video, voice, subtitle: {0}")]
-    Fail(String)
-}
\ No newline at end of file
+    Fail(String),
+    #[error("launching tool failed: {0}")]
+    Spawn(String),
+    #[error("Failed to to compose video, voice, subtitle, {0}")]
+    Exit(FFmpegFailure),
+    #[error("cancelled")]
+    Cancelled,
+}
+
+impl From<FFmpegRunnerError> for ComposeToolError {
+    fn from(e: FFmpegRunnerError) -> Self {
+        match e {
+            FFmpegRunnerError::Spawn(msg) => Self::Spawn(msg),
+            FFmpegRunnerError::Exit(failure) => Self::Exit(failure),
+            FFmpegRunnerError::Cancelled => Self::Cancelled,
+        }
+    }
+}
@@ -0,0 +1,475 @@
+use std::{
+    collections::VecDeque, ffi::OsString, fmt, path::PathBuf, process::Stdio, sync::Arc,
+};
+
+use tauri::AppHandle;
+use tauri_plugin_shell::{
+    ShellExt,
+    process::{CommandEvent, TerminatedPayload},
+};
+use thiserror::Error;
+use tokio::{io::AsyncReadExt, process::Command, sync::Semaphore};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    limits::acquire,
+    progress::{ProgressEvent, ProgressReporter},
+};
+
+const DEFAULT_FFMPEG_BIN: &str = "ffmpeg";
+
+/// How many stderr lines are kept for the error when ffmpeg fails
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Runs one ffmpeg command line, how ffmpeg is launched is up to the implementation.
+#[async_trait::async_trait]
+pub trait FFmpegRunner {
+    /// Run ffmpeg with `args` and wait for it to exit.
+    /// Anything but a zero exit code is an error.
+    /// The `frame=`/`time=` lines ffmpeg prints are reported to `progress`.
+    /// ffmpeg is killed when `cancel` is cancelled.
+    async fn run(
+        &self,
+        args: Vec<OsString>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), FFmpegRunnerError>;
+}
+
+#[derive(Error, Debug)]
+pub enum FFmpegRunnerError {
+    #[error("launching ffmpeg failed: {0}")]
+    Spawn(String),
+    #[error("ffmpeg failed: {0}")]
+    Exit(FFmpegFailure),
+    #[error("ffmpeg was cancelled")]
+    Cancelled,
+}
+
+/// What is known about an ffmpeg run that did not exit with 0.
+#[derive(Debug, Clone)]
+pub struct FFmpegFailure {
+    /// the exit code, `None` when ffmpeg was terminated by a signal
+    pub code: Option<i32>,
+    /// the signal that terminated ffmpeg, if any
+    pub signal: Option<i32>,
+    /// the command line that was run
+    pub command: String,
+    /// the last lines ffmpeg wrote to stderr
+    pub stderr: Vec<String>,
+}
+
+impl fmt::Display for FFmpegFailure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.code, self.signal) {
+            (Some(code), _) => write!(f, "exit code {}", code)?,
+            (None, Some(signal)) => write!(f, "terminated by signal {}", signal)?,
+            (None, None) => write!(f, "terminated")?,
+        }
+        write!(f, ", command: {}", self.command)?;
+        for line in &self.stderr {
+            write!(f, "\n{}", line)?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Keeps the last lines of ffmpeg's stderr.
+/// ffmpeg ends its progress lines with `\r`, so both `\r` and `\n` end a line.
+#[derive(Default)]
+struct StderrTail {
+    lines: VecDeque<String>,
+    partial: Vec<u8>,
+}
+
+impl StderrTail {
+    fn push(&mut self, bytes: &[u8], progress: &dyn ProgressReporter) {
+        for &b in bytes {
+            if b == b'\n' || b == b'\r' {
+                if let Some(event) = self.end_line() {
+                    progress.report(event);
+                }
+            } else {
+                self.partial.push(b);
+            }
+        }
+    }
+
+    /// Finish the current line, returns the progress if it is a progress line.
+    fn end_line(&mut self) -> Option<ProgressEvent> {
+        if self.partial.is_empty() {
+            return None;
+        }
+
+        let line = String::from_utf8_lossy(&self.partial).trim_end().to_owned();
+        self.partial.clear();
+
+        let event = parse_progress(&line);
+
+        if self.lines.len() == STDERR_TAIL_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line);
+
+        event
+    }
+
+    fn into_lines(mut self) -> Vec<String> {
+        self.end_line();
+        self.lines.into()
+    }
+}
+
+/// Parse a progress line such as
+/// `frame=  150 fps= 30 q=28.0 size=  256kB time=00:00:05.00 bitrate= 419.4kbits/s speed=1.2x`
+fn parse_progress(line: &str) -> Option<ProgressEvent> {
+    if !line.contains("time=") {
+        return None;
+    }
+
+    let mut frame = None;
+    let mut time = None;
+
+    // ffmpeg pads the values, `frame=  150` is split into `frame=` and `150`
+    let mut fields = line.split_whitespace();
+    while let Some(field) = fields.next() {
+        let Some((key, value)) = field.split_once('=') else {
+            continue;
+        };
+        let value = if value.is_empty() {
+            fields.next().unwrap_or_default()
+        } else {
+            value
+        };
+
+        match key {
+            "frame" => frame = value.parse().ok(),
+            "time" => time = parse_time(value),
+            _ => {}
+        }
+    }
+
+    if frame.is_none() && time.is_none() {
+        return None;
+    }
+
+    Some(ProgressEvent::FFmpeg { frame, time })
+}
+
+/// `00:01:02.50` to seconds
+fn parse_time(time: &str) -> Option<f64> {
+    let mut seconds = 0f64;
+    for part in time.trim_start_matches('-').split(':') {
+        seconds = seconds * 60.0 + part.parse::<f64>().ok()?;
+    }
+
+    Some(seconds)
+}
+
+/// The command line as it would be typed into a shell, for error messages.
+fn command_line(bin: &str, args: &[OsString]) -> String {
+    let mut line = bin.to_owned();
+    for arg in args {
+        let arg = arg.to_string_lossy();
+        line.push(' ');
+        if arg.is_empty() || arg.contains(char::is_whitespace) {
+            line.push_str(&format!("\"{}\"", arg));
+        } else {
+            line.push_str(&arg);
+        }
+    }
+
+    line
+}
+
+/// Runs the ffmpeg sidecar bundled with the Tauri app, see `bundle.externalBin`.
+pub struct SidecarRunner {
+    app: AppHandle,
+}
+
+impl SidecarRunner {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl FFmpegRunner for SidecarRunner {
+    async fn run(
+        &self,
+        args: Vec<OsString>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), FFmpegRunnerError> {
+        let command = command_line(DEFAULT_FFMPEG_BIN, &args);
+        let ffmpeg = self
+            .app
+            .shell()
+            .sidecar(DEFAULT_FFMPEG_BIN)
+            .map_err(|e| FFmpegRunnerError::Spawn(format!("{}: {}", command, e)))?
+            .args(args);
+
+        let (mut rx, child) = ffmpeg
+            .spawn()
+            .map_err(|e| FFmpegRunnerError::Spawn(format!("{}: {}", command, e)))?;
+
+        let mut stderr = StderrTail::default();
+        let mut terminated = None;
+        let wait = async {
+            while let Some(event) = rx.recv().await {
+                match event {
+                    CommandEvent::Stderr(line) => stderr.push(&line, progress),
+                    CommandEvent::Error(line) => stderr.push(line.as_bytes(), progress),
+                    CommandEvent::Terminated(payload) => terminated = Some(payload),
+                    _ => {}
+                }
+            }
+        };
+
+        if cancel.run_until_cancelled(wait).await.is_none() {
+            _ = child.kill();
+            return Err(FFmpegRunnerError::Cancelled);
+        }
+
+        match terminated {
+            Some(TerminatedPayload { code: Some(0), .. }) => Ok(()),
+            Some(TerminatedPayload { code, signal }) => {
+                Err(FFmpegRunnerError::Exit(FFmpegFailure {
+                    code,
+                    signal,
+                    command,
+                    stderr: stderr.into_lines(),
+                }))
+            }
+            None => Err(FFmpegRunnerError::Exit(FFmpegFailure {
+                code: None,
+                signal: None,
+                command,
+                stderr: stderr.into_lines(),
+            })),
+        }
+    }
+}
+
+/// Runs an ffmpeg binary as a child process, for servers, the CLI and tests.
+pub struct ProcessRunner {
+    bin: PathBuf,
+}
+
+impl ProcessRunner {
+    /// `bin` is the path of the ffmpeg binary, or a name looked up in `PATH`.
+    pub fn new(bin: impl Into<PathBuf>) -> Self {
+        Self { bin: bin.into() }
+    }
+}
+
+impl Default for ProcessRunner {
+    fn default() -> Self {
+        Self::new(DEFAULT_FFMPEG_BIN)
+    }
+}
+
+#[async_trait::async_trait]
+impl FFmpegRunner for ProcessRunner {
+    async fn run(
+        &self,
+        args: Vec<OsString>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), FFmpegRunnerError> {
+        let command = command_line(&self.bin.to_string_lossy(), &args);
+
+        let mut child = Command::new(&self.bin)
+            .args(args)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .map_err(|e| FFmpegRunnerError::Spawn(format!("{}: {}", command, e)))?;
+
+        let mut stderr = StderrTail::default();
+        let mut pipe = child.stderr.take();
+        let wait = async {
+            if let Some(pipe) = pipe.as_mut() {
+                let mut buf = [0u8; 4096];
+                while let Ok(n) = pipe.read(&mut buf).await {
+                    if n == 0 {
+                        break;
+                    }
+                    stderr.push(&buf[..n], progress);
+                }
+            }
+
+            child.wait().await
+        };
+
+        let Some(status) = cancel.run_until_cancelled(wait).await else {
+            _ = child.kill().await;
+            return Err(FFmpegRunnerError::Cancelled);
+        };
+
+        let status = status.map_err(|e| FFmpegRunnerError::Spawn(format!("{}: {}", command, e)))?;
+        if status.success() {
+            return Ok(());
+        }
+
+        #[cfg(unix)]
+        let signal = std::os::unix::process::ExitStatusExt::signal(&status);
+        #[cfg(not(unix))]
+        let signal = None;
+
+        Err(FFmpegRunnerError::Exit(FFmpegFailure {
+            code: status.code(),
+            signal,
+            command,
+            stderr: stderr.into_lines(),
+        }))
+    }
+}
+
+/// Wraps another runner, at most as many ffmpeg run at once as `permits` allows.
+pub struct LimitedRunner<R> {
+    inner: R,
+    permits: Arc<Semaphore>,
+}
+
+impl<R> LimitedRunner<R> {
+    pub fn new(inner: R, permits: Arc<Semaphore>) -> Self {
+        Self { inner, permits }
+    }
+}
+
+#[async_trait::async_trait]
+impl<R> FFmpegRunner for LimitedRunner<R>
+where
+    R: FFmpegRunner + Sync + Send,
+{
+    async fn run(
+        &self,
+        args: Vec<OsString>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<(), FFmpegRunnerError> {
+        let Some(_permit) = acquire(&self.permits, cancel).await else {
+            return Err(FFmpegRunnerError::Cancelled);
+        };
+
+        self.inner.run(args, progress, cancel).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::progress::NoopReporter;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn process_runner_missing_binary() {
+        let runner = ProcessRunner::new("./this-ffmpeg-does-not-exist");
+
+        let res = runner
+            .run(
+                vec!["-version".into()],
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        assert!(matches!(res, Err(FFmpegRunnerError::Spawn(_))));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn process_runner_non_zero_exit() {
+        let runner = ProcessRunner::new("sh");
+
+        let res = runner
+            .run(
+                vec!["-c".into(), "echo first >&2; echo last >&2; exit 3".into()],
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        let Err(FFmpegRunnerError::Exit(failure)) = res else {
+            panic!("expected an exit error");
+        };
+        assert_eq!(Some(3), failure.code);
+        assert_eq!(None, failure.signal);
+        assert_eq!(
+            r#"sh -c "echo first >&2; echo last >&2; exit 3""#,
+            failure.command
+        );
+        assert_eq!(vec!["first", "last"], failure.stderr);
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn process_runner_cancel_kills_child() {
+        let runner = ProcessRunner::new("sh");
+        let cancel = CancellationToken::new();
+
+        let canceller = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+            canceller.cancel();
+        });
+
+        let started = std::time::Instant::now();
+        let res = runner
+            .run(vec!["-c".into(), "sleep 10".into()], &NoopReporter, &cancel)
+            .await;
+
+        assert!(matches!(res, Err(FFmpegRunnerError::Cancelled)));
+        assert!(started.elapsed() < std::time::Duration::from_secs(5));
+    }
+
+    #[test]
+    fn stderr_tail_keeps_last_lines() {
+        let mut tail = StderrTail::default();
+
+        for i in 0..STDERR_TAIL_LINES {
+            tail.push(format!("line {}\n", i).as_bytes(), &NoopReporter);
+        }
+        tail.push(
+            b"frame=  1 time=00:00:01\rframe=  2 time=00:00:02\r",
+            &NoopReporter,
+        );
+        tail.push(b"Conversion failed!", &NoopReporter);
+
+        let lines = tail.into_lines();
+        assert_eq!(STDERR_TAIL_LINES, lines.len());
+        assert_eq!("line 3", lines[0]);
+        assert_eq!("frame=  2 time=00:00:02", lines[STDERR_TAIL_LINES - 2]);
+        assert_eq!("Conversion failed!", lines[STDERR_TAIL_LINES - 1]);
+    }
+
+    #[test]
+    fn parse_progress_line() {
+        let event = parse_progress(
+            "frame=  150 fps= 30 q=28.0 size=     256kB time=00:01:05.50 bitrate= 419.4kbits/s speed=1.2x",
+        );
+        assert_eq!(
+            Some(ProgressEvent::FFmpeg {
+                frame: Some(150),
+                time: Some(65.5),
+            }),
+            event
+        );
+
+        let event = parse_progress("size=     123kB time=00:00:03.50 bitrate= 288.0kbits/s");
+        assert_eq!(
+            Some(ProgressEvent::FFmpeg {
+                frame: None,
+                time: Some(3.5),
+            }),
+            event
+        );
+
+        assert_eq!(None, parse_progress("Input #0, wav, from 'in.wav':"));
+        assert_eq!(None, parse_progress("size=N/A time=N/A bitrate=N/A"));
+    }
+}
@@ -0,0 +1,683 @@
+use std::collections::HashMap;
+
+use reqwest::Url;
+use scraper::{ElementRef, Html, Selector};
+
+/// never part of the content
+const UNLIKELY_TAGS: [&str; 10] = [
+    "script", "style", "noscript", "nav", "header", "footer", "aside", "form", "iframe", "svg",
+];
+/// words of the class or id of the elements around the content
+const POSITIVE_WORDS: [&str; 10] = [
+    "article", "body", "content", "entry", "main", "post", "text", "story", "news", "detail",
+];
+/// words of the class or id of the navigation, ads, comments...
+const NEGATIVE_WORDS: [&str; 22] = [
+    "comment",
+    "comments",
+    "footer",
+    "nav",
+    "navbar",
+    "sidebar",
+    "ad",
+    "ads",
+    "advert",
+    "advertisement",
+    "share",
+    "social",
+    "related",
+    "recommend",
+    "menu",
+    "header",
+    "promo",
+    "sponsor",
+    "breadcrumb",
+    "popup",
+    "banner",
+    "copyright",
+];
+/// words of the class, id, alt or file name of the images not part of the content
+const ICON_WORDS: [&str; 14] = [
+    "icon",
+    "icons",
+    "avatar",
+    "logo",
+    "emoji",
+    "emoticon",
+    "qrcode",
+    "spinner",
+    "loading",
+    "placeholder",
+    "sprite",
+    "badge",
+    "pixel",
+    "blank",
+];
+/// words of the class of the credit line of a figure
+const CREDIT_WORDS: [&str; 4] = ["credit", "credits", "copyright", "source"];
+/// what comes before the credit at the end of a caption
+const CREDIT_PREFIXES: [&str; 6] = [
+    "图片来源：",
+    "图源：",
+    "摄影：",
+    "图/",
+    "Photo: ",
+    "Credit: ",
+];
+/// the attributes of the lazy loaded images, before their placeholder `src`
+const LAZY_SRC_ATTRS: [&str; 4] = ["data-src", "data-original", "data-lazy-src", "data-url"];
+/// the smaller images are icons, buttons, separators...
+const MIN_IMAGE_SIDE: u32 = 200;
+/// the longer side of a content image is at most this many times the shorter one
+const MAX_IMAGE_ASPECT: f64 = 3.0;
+/// shorter paragraphs are not scored
+const MIN_PARAGRAPH_CHARS: usize = 25;
+
+/// The main content of a news page, without the navigation, ads and scripts.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct Article {
+    pub title: Option<String>,
+    pub paragraphs: Vec<String>,
+    /// the images in the content, in order
+    pub images: Vec<ArticleImage>,
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ArticleImage {
+    /// absolute when the page url is known
+    pub url: String,
+    /// the `figcaption` of the image, else its `alt`
+    pub caption: Option<String>,
+    /// the credit line of the figure, or the end of the caption after `图源：`, `Photo: `...
+    pub credit: Option<String>,
+    /// from the `srcset`, the attributes of the image or a probe of the file
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+}
+
+impl ArticleImage {
+    /// Not too small nor too narrow to be in a video, an unknown size is fine.
+    pub fn has_content_size(&self) -> bool {
+        let sides = [self.width, self.height];
+        if sides
+            .into_iter()
+            .flatten()
+            .any(|side| side < MIN_IMAGE_SIDE)
+        {
+            return false;
+        }
+        match (self.width, self.height) {
+            (Some(width), Some(height)) => {
+                let (long, short) = (width.max(height), width.min(height));
+                f64::from(long) <= f64::from(short) * MAX_IMAGE_ASPECT
+            }
+            _ => true,
+        }
+    }
+}
+
+impl Article {
+    /// Find the main content of `html` by scoring the elements holding the paragraphs,
+    /// the relative image urls are resolved against `base`.
+    /// `None` when no element looks like an article.
+    pub fn extract(html: &str, base: Option<&Url>) -> Option<Self> {
+        let document = Html::parse_document(html);
+
+        let mut scores = HashMap::new();
+        for paragraph in document.select(&selector("p, pre, td")) {
+            if is_inside_unlikely(paragraph) {
+                continue;
+            }
+            let text = normalize(paragraph.text());
+            let chars = text.chars().count();
+            if chars < MIN_PARAGRAPH_CHARS {
+                continue;
+            }
+
+            let commas = text.matches([',', '，', '、']).count();
+            let score = 1.0 + commas as f64 + (chars / 100).min(3) as f64;
+
+            let mut ancestors = paragraph.ancestors().filter_map(ElementRef::wrap);
+            for (ancestor, share) in ancestors.by_ref().take(2).zip([1.0, 0.5]) {
+                *scores
+                    .entry(ancestor.id())
+                    .or_insert_with(|| initial_score(ancestor)) += score * share;
+            }
+        }
+
+        let top = scores
+            .iter()
+            .filter_map(|(id, score)| {
+                let element = ElementRef::wrap(document.tree.get(*id)?)?;
+                Some((element, score * (1.0 - link_density(element))))
+            })
+            .max_by(|(_, a), (_, b)| a.total_cmp(b))?;
+
+        let blocks = content_blocks(top, |e| scores.get(&e.id()).copied());
+
+        Some(Self {
+            title: title(&document),
+            paragraphs: blocks.iter().flat_map(|b| paragraphs(*b)).collect(),
+            images: images(&blocks, base),
+        })
+    }
+
+    /// The article of a Markdown or plain text document, each line is a paragraph.
+    /// The first `#` heading is the title, the `![alt](src)` images are resolved against `base`.
+    /// `None` when there is no text.
+    pub fn from_text(text: &str, base: Option<&Url>) -> Option<Self> {
+        let mut article = Self::default();
+
+        for line in text.lines().map(str::trim) {
+            if line.starts_with("```") || line.chars().all(|c| matches!(c, '-' | '*' | '=')) {
+                continue;
+            }
+
+            let heading = line.trim_start_matches('#');
+            let is_heading = heading.len() < line.len();
+            let line = ["> ", "- ", "* ", "+ "]
+                .iter()
+                .find_map(|marker| heading.trim_start().strip_prefix(marker))
+                .unwrap_or(heading);
+            let (line, images) = inline_markdown(line.trim());
+
+            for (alt, src) in images {
+                let Some(url) = resolve(&src, base) else {
+                    continue;
+                };
+                if !article.images.iter().any(|i| i.url == url) {
+                    article.images.push(ArticleImage {
+                        url,
+                        caption: Some(alt).filter(|alt| !alt.is_empty()),
+                        credit: None,
+                        width: None,
+                        height: None,
+                    });
+                }
+            }
+
+            if line.is_empty() {
+                continue;
+            }
+            if is_heading && article.title.is_none() {
+                article.title = Some(line);
+            } else {
+                article.paragraphs.push(line);
+            }
+        }
+
+        (!article.paragraphs.is_empty()).then_some(article)
+    }
+}
+
+/// The text of a Markdown line without the emphasis and the links, and its images as `(alt, src)`.
+fn inline_markdown(line: &str) -> (String, Vec<(String, String)>) {
+    let mut text = String::new();
+    let mut images = vec![];
+    let mut rest = line;
+
+    while let Some(start) = rest.find('[') {
+        let link = rest[start + 1..]
+            .split_once("](")
+            .and_then(|(label, after)| {
+                let (target, after) = after.split_once(')')?;
+                (!label.contains(['[', ']'])).then_some((label, target, after))
+            });
+        let Some((label, target, after)) = link else {
+            text.push_str(&rest[..=start]);
+            rest = &rest[start + 1..];
+            continue;
+        };
+
+        // the target may be followed by a title, `![alt](src "title")`
+        let target = target.split_whitespace().next().unwrap_or_default();
+        match rest[..start].strip_suffix('!') {
+            Some(before) => {
+                text.push_str(before);
+                images.push((label.trim().to_owned(), target.to_owned()));
+            }
+            None => {
+                text.push_str(&rest[..start]);
+                text.push_str(label);
+            }
+        }
+        rest = after;
+    }
+    text.push_str(rest);
+
+    let text = text.replace("**", "").replace("__", "").replace('`', "");
+    (text.trim().to_owned(), images)
+}
+
+/// The top element and the siblings scored close to it, in document order.
+fn content_blocks<'a>(
+    (top, top_score): (ElementRef<'a>, f64),
+    score: impl Fn(ElementRef) -> Option<f64>,
+) -> Vec<ElementRef<'a>> {
+    let threshold = (top_score * 0.2).max(10.0);
+    let Some(parent) = top.parent() else {
+        return vec![top];
+    };
+
+    parent
+        .children()
+        .filter_map(ElementRef::wrap)
+        .filter(|sibling| {
+            sibling.id() == top.id()
+                || score(*sibling)
+                    .is_some_and(|score| score * (1.0 - link_density(*sibling)) >= threshold)
+        })
+        .collect()
+}
+
+/// The paragraphs of `block`, the block text when it has none.
+fn paragraphs(block: ElementRef) -> Vec<String> {
+    let nested = ["p", "pre", "li", "blockquote"];
+    let mut paragraphs: Vec<String> = block
+        .select(&selector("p, h2, h3, h4, pre, li, blockquote"))
+        .filter(|p| !is_inside_unlikely(*p) && link_density(*p) < 0.5)
+        // the text of a `p` in a `blockquote` is the one of the blockquote
+        .filter(|p| {
+            !p.ancestors()
+                .filter_map(ElementRef::wrap)
+                .take_while(|a| a.id() != block.id())
+                .any(|a| nested.contains(&a.value().name()))
+        })
+        .map(|p| normalize(p.text()))
+        .filter(|text| !text.is_empty())
+        .collect();
+
+    if paragraphs.is_empty() {
+        let text = normalize(block.text());
+        if !text.is_empty() {
+            paragraphs.push(text);
+        }
+    }
+
+    paragraphs
+}
+
+fn images(blocks: &[ElementRef], base: Option<&Url>) -> Vec<ArticleImage> {
+    let img_selector = selector("img");
+    let mut images: Vec<ArticleImage> = vec![];
+
+    for img in blocks.iter().flat_map(|b| b.select(&img_selector)) {
+        if is_inside_unlikely(img) {
+            continue;
+        }
+        let Some(source) = ImageSource::of(img) else {
+            continue;
+        };
+        if is_icon(img, &source.url) {
+            continue;
+        }
+        let Some(url) = resolve(&source.url, base) else {
+            continue;
+        };
+        if images.iter().any(|i| i.url == url) {
+            continue;
+        }
+
+        let figure = img
+            .ancestors()
+            .filter_map(ElementRef::wrap)
+            .find(|a| a.value().name() == "figure");
+        let credit_line = figure
+            .and_then(|figure| {
+                figure.select(&selector("[class]")).find(|e| {
+                    class_words(*e)
+                        .iter()
+                        .any(|w| CREDIT_WORDS.contains(&w.as_str()))
+                })
+            })
+            .map(|credit| normalize(credit.text()))
+            .filter(|credit| !credit.is_empty());
+        let figcaption = figure
+            .and_then(|figure| figure.select(&selector("figcaption")).next())
+            .map(|caption| normalize(caption.text()));
+        let alt = img.value().attr("alt").map(|alt| alt.trim().to_owned());
+        let (caption, credit) = match (figcaption.or(alt), credit_line) {
+            (Some(caption), Some(credit)) => (
+                Some(caption.replace(&credit, "").trim().to_owned()),
+                Some(credit),
+            ),
+            (Some(caption), None) => split_credit(&caption),
+            (None, credit) => (None, credit),
+        };
+        let caption = caption.filter(|c| !c.is_empty());
+
+        let dimension = |name| {
+            img.value()
+                .attr(name)
+                .and_then(|v| v.trim().trim_end_matches("px").parse().ok())
+        };
+        let image = ArticleImage {
+            url,
+            caption,
+            credit,
+            width: source.width.or_else(|| dimension("width")),
+            height: dimension("height"),
+        };
+        if image.has_content_size() {
+            images.push(image);
+        }
+    }
+
+    images
+}
+
+/// The caption before a credit prefix and the credit after it,
+/// `江豚（图源：新华社）` is `江豚` by `新华社`.
+fn split_credit(caption: &str) -> (Option<String>, Option<String>) {
+    let brackets: &[char] = &[' ', '（', '(', '）', ')'];
+    for prefix in CREDIT_PREFIXES {
+        if let Some(at) = caption.find(prefix) {
+            let credit = caption[at + prefix.len()..].trim_matches(brackets);
+            let caption = caption[..at].trim_end_matches(brackets);
+            return (
+                Some(caption.to_owned()),
+                Some(credit.to_owned()).filter(|c| !c.is_empty()),
+            );
+        }
+    }
+    (Some(caption.to_owned()), None)
+}
+
+/// Where an `img` is loaded from, the largest of its `srcset`,
+/// else its lazy loaded source, else its `src`.
+struct ImageSource {
+    url: String,
+    /// the width descriptor in the `srcset`
+    width: Option<u32>,
+}
+
+impl ImageSource {
+    fn of(img: ElementRef) -> Option<Self> {
+        let attr = |name| {
+            img.value()
+                .attr(name)
+                .map(str::trim)
+                .filter(|v| !v.is_empty() && !v.starts_with("data:"))
+        };
+
+        if let Some(source) = attr("data-srcset")
+            .or_else(|| attr("srcset"))
+            .and_then(Self::largest)
+        {
+            return Some(source);
+        }
+
+        LAZY_SRC_ATTRS
+            .iter()
+            .find_map(|name| attr(name))
+            .or_else(|| attr("src"))
+            .map(|url| Self {
+                url: url.to_owned(),
+                width: None,
+            })
+    }
+
+    /// The candidate of `srcset` with the largest `w` or `x` descriptor.
+    fn largest(srcset: &str) -> Option<Self> {
+        srcset
+            .split(',')
+            .filter_map(|candidate| {
+                let mut parts = candidate.split_whitespace();
+                let url = parts.next()?;
+                let descriptor = parts.next().unwrap_or("1x");
+                let (width, density) = match descriptor.strip_suffix('w') {
+                    Some(w) => {
+                        let width = w.parse::<u32>().ok()?;
+                        (Some(width), f64::from(width))
+                    }
+                    None => (None, descriptor.strip_suffix('x')?.parse().ok()?),
+                };
+                Some((url, width, density))
+            })
+            .filter(|(url, ..)| !url.starts_with("data:"))
+            .max_by(|(.., a), (.., b)| a.total_cmp(b))
+            .map(|(url, width, _)| Self {
+                url: url.to_owned(),
+                width,
+            })
+    }
+}
+
+/// The absolute url without the query string and the fragment, which mostly resize or track.
+fn resolve(url: &str, base: Option<&Url>) -> Option<String> {
+    match base {
+        Some(base) => {
+            let mut url = base.join(url).ok()?;
+            url.set_query(None);
+            url.set_fragment(None);
+            Some(url.to_string())
+        }
+        None => url.split(['?', '#']).next().map(str::to_owned),
+    }
+}
+
+/// Icons, avatars, logos and the like, by the class, id, alt and `url` of the image
+/// and the class and id of its parent.
+fn is_icon(img: ElementRef, url: &str) -> bool {
+    let value = img.value();
+    let mut words = class_words(img);
+    words.extend(
+        [value.attr("alt").unwrap_or_default(), url]
+            .into_iter()
+            .flat_map(|attr| attr.split(|c: char| !c.is_alphanumeric()))
+            .map(str::to_lowercase),
+    );
+    if let Some(parent) = img.parent().and_then(ElementRef::wrap) {
+        words.extend(class_words(parent));
+    }
+
+    words.iter().any(|w| ICON_WORDS.contains(&w.as_str()))
+}
+
+/// `og:title`, else the first `h1`, else `title`.
+fn title(document: &Html) -> Option<String> {
+    document
+        .select(&selector(r#"meta[property="og:title"]"#))
+        .find_map(|meta| meta.value().attr("content").map(str::to_owned))
+        .or_else(|| {
+            ["h1", "title"].iter().find_map(|tag| {
+                document
+                    .select(&selector(tag))
+                    .next()
+                    .map(|e| normalize(e.text()))
+            })
+        })
+        .map(|title| title.trim().to_owned())
+        .filter(|title| !title.is_empty())
+}
+
+/// The score of a candidate before its paragraphs, by its tag and its class and id.
+fn initial_score(element: ElementRef) -> f64 {
+    let tag = match element.value().name() {
+        "article" | "main" => 10.0,
+        "div" | "section" => 5.0,
+        "pre" | "td" | "blockquote" => 3.0,
+        "ol" | "ul" | "dl" | "form" => -3.0,
+        "h1" | "h2" | "h3" | "h4" | "th" => -5.0,
+        _ => 0.0,
+    };
+
+    let words = class_words(element);
+    let class = if words.iter().any(|w| NEGATIVE_WORDS.contains(&w.as_str())) {
+        -25.0
+    } else if words.iter().any(|w| POSITIVE_WORDS.contains(&w.as_str())) {
+        25.0
+    } else {
+        0.0
+    };
+
+    tag + class
+}
+
+/// Inside a tag or a class that is never the content, like `nav` or `class="sidebar"`.
+fn is_inside_unlikely(element: ElementRef) -> bool {
+    std::iter::once(element)
+        .chain(element.ancestors().filter_map(ElementRef::wrap))
+        .any(|e| {
+            let name = e.value().name();
+            if UNLIKELY_TAGS.contains(&name) {
+                return true;
+            }
+            if matches!(name, "html" | "body" | "article" | "main") {
+                return false;
+            }
+
+            let words = class_words(e);
+            words.iter().any(|w| NEGATIVE_WORDS.contains(&w.as_str()))
+                && !words.iter().any(|w| POSITIVE_WORDS.contains(&w.as_str()))
+        })
+}
+
+/// The words of the class and id, `post-body_text` is `post`, `body`, `text`.
+fn class_words(element: ElementRef) -> Vec<String> {
+    let value = element.value();
+    [value.attr("class"), value.attr("id")]
+        .into_iter()
+        .flatten()
+        .flat_map(|attr| attr.split(|c: char| !c.is_alphanumeric()))
+        .filter(|word| !word.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// How much of the text of `element` is in links.
+fn link_density(element: ElementRef) -> f64 {
+    let chars = normalize(element.text()).chars().count();
+    if chars == 0 {
+        return 0.0;
+    }
+
+    let link_chars: usize = element
+        .select(&selector("a"))
+        .map(|a| normalize(a.text()).chars().count())
+        .sum();
+
+    link_chars as f64 / chars as f64
+}
+
+fn normalize<'a>(text: impl Iterator<Item = &'a str>) -> String {
+    text.collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn selector(selectors: &str) -> Selector {
+    Selector::parse(selectors).expect("valid selector")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = include_str!("../../tests/pages/article.html");
+
+    #[test]
+    fn extract_main_content() {
+        let base = Url::parse("https://news.example.com/2025/08/story.html").unwrap();
+        let article = Article::extract(PAGE, Some(&base)).unwrap();
+
+        assert_eq!(Some("江豚重现长江口".to_owned()), article.title);
+        assert_eq!(4, article.paragraphs.len());
+        assert!(article.paragraphs[0].starts_with("近日，在长江口"));
+        assert_eq!("监测人员介绍", &article.paragraphs[2][..18]);
+        assert!(
+            article
+                .paragraphs
+                .iter()
+                .all(|p| !p.contains("热门推荐") && !p.contains("版权所有"))
+        );
+
+        let image = |url: &str, caption: &str, credit: Option<&str>, width, height| ArticleImage {
+            url: url.to_owned(),
+            caption: Some(caption.to_owned()),
+            credit: credit.map(str::to_owned),
+            width,
+            height,
+        };
+        // no avatar nor divider, the lazy loaded and largest sources without the query
+        assert_eq!(
+            vec![
+                image(
+                    "https://news.example.com/img/porpoise.jpg",
+                    "江豚在长江口水域跃出水面",
+                    Some("新华社记者 张三 摄"),
+                    None,
+                    None
+                ),
+                image(
+                    "https://cdn.example.com/river.png",
+                    "长江口",
+                    None,
+                    None,
+                    None
+                ),
+                image(
+                    "https://news.example.com/img/boat.jpg",
+                    "巡护船",
+                    Some("示例网 李四"),
+                    Some(800),
+                    Some(450)
+                ),
+                image(
+                    "https://news.example.com/img/map-1080.jpg",
+                    "监测水域示意图",
+                    None,
+                    Some(1080),
+                    None
+                ),
+            ],
+            article.images
+        );
+    }
+
+    #[test]
+    fn from_markdown() {
+        let base = Url::parse("file:///home/me/news/release.md").unwrap();
+        let article = Article::from_text(
+            r#"# 新品发布
+
+我们**今天**发布了[新版本](https://example.com/v2)，欢迎试用。
+
+![发布会现场](img/stage.jpg "现场")
+- 支持离线模式
+## 更多
+![](/tmp/chart.png)
+"#,
+            Some(&base),
+        )
+        .unwrap();
+
+        assert_eq!(Some("新品发布".to_owned()), article.title);
+        assert_eq!(
+            vec!["我们今天发布了新版本，欢迎试用。", "支持离线模式", "更多"],
+            article.paragraphs
+        );
+        assert_eq!(
+            vec![
+                ("file:///home/me/news/img/stage.jpg", Some("发布会现场")),
+                ("file:///tmp/chart.png", None),
+            ],
+            article
+                .images
+                .iter()
+                .map(|i| (i.url.as_str(), i.caption.as_deref()))
+                .collect::<Vec<_>>()
+        );
+
+        assert_eq!(None, Article::from_text("\n---\n", None));
+    }
+
+    #[test]
+    fn no_article() {
+        assert_eq!(
+            None,
+            Article::extract("<html><body><nav>首页</nav></body></html>", None)
+        );
+    }
+}
@@ -0,0 +1,271 @@
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use reqwest::{Client, RequestBuilder, Response, StatusCode, header};
+use serde::{Deserialize, Serialize};
+use tokio::fs;
+
+use crate::{
+    news::{NewsCrawler, NewsCrawlerError, NewsTitle, stamp_crawled},
+    time::now,
+};
+
+/// The validators of a response, sent back to revalidate it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Validators {
+    pub etag: Option<String>,
+    pub last_modified: Option<String>,
+}
+
+impl Validators {
+    fn of(response: &Response) -> Self {
+        let header = |name| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_owned)
+        };
+
+        Self {
+            etag: header(header::ETAG),
+            last_modified: header(header::LAST_MODIFIED),
+        }
+    }
+
+    /// Add the `If-None-Match` and `If-Modified-Since` headers of the validators.
+    fn apply(&self, mut request: RequestBuilder) -> RequestBuilder {
+        if let Some(etag) = &self.etag {
+            request = request.header(header::IF_NONE_MATCH, etag);
+        }
+        if let Some(last_modified) = &self.last_modified {
+            request = request.header(header::IF_MODIFIED_SINCE, last_modified);
+        }
+        request
+    }
+}
+
+/// The answer of a conditional fetch of a hot news list.
+pub enum Fetched {
+    Modified {
+        titles: Vec<NewsTitle>,
+        validators: Validators,
+    },
+    /// the list has not changed since the validators
+    NotModified,
+}
+
+/// GET `url` unless it is unchanged since `validators`,
+/// the body and the validators of the response, `None` when not modified.
+pub async fn conditional_get(
+    client: &Client,
+    url: &str,
+    validators: &Validators,
+) -> Result<Option<(Vec<u8>, Validators)>, NewsCrawlerError> {
+    let to_network_err = |e: reqwest::Error| NewsCrawlerError::Network(e.to_string());
+
+    let response = validators
+        .apply(client.get(url))
+        .send()
+        .await
+        .map_err(to_network_err)?;
+    if response.status() == StatusCode::NOT_MODIFIED {
+        return Ok(None);
+    }
+
+    let response = response.error_for_status().map_err(to_network_err)?;
+    let validators = Validators::of(&response);
+    let body = response.bytes().await.map_err(to_network_err)?;
+
+    Ok(Some((body.to_vec(), validators)))
+}
+
+/// The last good list of a source.
+#[derive(Serialize, Deserialize)]
+struct CacheEntry {
+    titles: Vec<NewsTitle>,
+    validators: Validators,
+    /// unix seconds
+    fetched_at: u64,
+}
+
+/// Keeps the hot news lists on disk, one file per source.
+/// A list is used for `ttl`, then revalidated with its ETag or Last-Modified,
+/// and used again when the network fails.
+pub struct NewsCache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl NewsCache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Self {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// The hot news list of `source`, fetched with `crawler` when the cached one is older than
+    /// the ttl or on `refresh`.
+    pub async fn hot_news_list(
+        &self,
+        source: &str,
+        crawler: &(dyn NewsCrawler + Sync + Send),
+        refresh: bool,
+    ) -> Result<Vec<NewsTitle>, NewsCrawlerError> {
+        let file = self.file(source);
+        let cached = Self::read(&file).await;
+        let now = now();
+
+        if let Some(entry) = &cached
+            && !refresh
+            && now.saturating_sub(entry.fetched_at) < self.ttl.as_secs()
+        {
+            return Ok(entry.titles.clone());
+        }
+
+        let validators = cached
+            .as_ref()
+            .map(|entry| entry.validators.clone())
+            .unwrap_or_default();
+        let mut entry = match (crawler.fetch_hot_news_list(&validators).await, cached) {
+            (Ok(Fetched::Modified { titles, validators }), _) => CacheEntry {
+                titles,
+                validators,
+                fetched_at: now,
+            },
+            (Ok(Fetched::NotModified), Some(entry)) => CacheEntry {
+                fetched_at: now,
+                ..entry
+            },
+            (Ok(Fetched::NotModified), None) => {
+                return Err(NewsCrawlerError::Response(
+                    "not modified without a cached list".to_owned(),
+                ));
+            }
+            (Err(e), Some(entry)) => {
+                eprintln!("{}, using the hot news of {} cached", e, source);
+                return Ok(entry.titles);
+            }
+            (Err(e), None) => return Err(e),
+        };
+
+        // the titles revalidated keep the time they were first fetched
+        stamp_crawled(&mut entry.titles, now);
+
+        // the list is fine even if it can't be cached
+        if let Err(e) = Self::write(&file, &entry).await {
+            eprintln!("cannot cache the hot news of {}: {}", source, e);
+        }
+
+        Ok(entry.titles)
+    }
+
+    /// The source names are config keys, anything but a plain name is replaced.
+    fn file(&self, source: &str) -> PathBuf {
+        let name: String = source
+            .chars()
+            .map(|c| {
+                if c.is_alphanumeric() || c == '-' || c == '_' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.dir.join(format!("hot-news-{}.json", name))
+    }
+
+    /// A broken cache is as good as none.
+    async fn read(file: &Path) -> Option<CacheEntry> {
+        let bytes = fs::read(file).await.ok()?;
+        serde_json::from_slice(&bytes).ok()
+    }
+
+    async fn write(file: &Path, entry: &CacheEntry) -> std::io::Result<()> {
+        if let Some(dir) = file.parent() {
+            fs::create_dir_all(dir).await?;
+        }
+        fs::write(file, serde_json::to_vec(entry)?).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::news::feed_news::FeedNews;
+
+    use super::*;
+
+    const RSS: &str = include_str!("../../tests/feeds/rss.xml");
+
+    #[tokio::test]
+    async fn revalidates_and_falls_back() {
+        let mut server = mockito::Server::new_async().await;
+        let dir = tempdir::TempDir::new("news-cache").unwrap();
+        let feed = FeedNews::new("rss", format!("{}/feed.xml", server.url()));
+
+        let first = server
+            .mock("GET", "/feed.xml")
+            .match_header("if-none-match", mockito::Matcher::Missing)
+            .with_status(200)
+            .with_header("etag", "\"v1\"")
+            .with_body(RSS)
+            .expect(1)
+            .create_async()
+            .await;
+
+        // fresh for an hour, fetched once
+        let cache = NewsCache::new(dir.path(), Duration::from_secs(3600));
+        assert_eq!(
+            2,
+            cache
+                .hot_news_list("rss", &feed, false)
+                .await
+                .unwrap()
+                .len()
+        );
+        assert_eq!(
+            2,
+            cache
+                .hot_news_list("rss", &feed, false)
+                .await
+                .unwrap()
+                .len()
+        );
+        first.assert_async().await;
+        let cached = cache.hot_news_list("rss", &feed, false).await.unwrap();
+        assert!(cached.iter().all(|title| title.meta.crawled_at.is_some()));
+
+        // refreshed, the server answers that it has not changed
+        let not_modified = server
+            .mock("GET", "/feed.xml")
+            .match_header("if-none-match", "\"v1\"")
+            .with_status(304)
+            .expect(1)
+            .create_async()
+            .await;
+        assert_eq!(
+            2,
+            cache.hot_news_list("rss", &feed, true).await.unwrap().len()
+        );
+        not_modified.assert_async().await;
+
+        // offline, the last good list is used
+        let offline = FeedNews::new("rss", "http://127.0.0.1:9/feed.xml");
+        let stale = NewsCache::new(dir.path(), Duration::ZERO);
+        assert_eq!(
+            2,
+            stale
+                .hot_news_list("rss", &offline, false)
+                .await
+                .unwrap()
+                .len()
+        );
+        assert!(matches!(
+            stale.hot_news_list("other", &offline, false).await,
+            Err(NewsCrawlerError::Network(_))
+        ));
+    }
+}
@@ -0,0 +1,266 @@
+use feed_rs::model::{Entry, Link};
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    director::source::SourceName,
+    news::{
+        NEWS_MODEL_VERSION, NewsCrawler, NewsCrawlerError, NewsMeta, NewsTitle,
+        cache::{Fetched, Validators, conditional_get},
+    },
+};
+
+const IMAGE_EXTENSIONS: [&str; 6] = ["jpg", "jpeg", "png", "gif", "webp", "avif"];
+const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "webm", "mov", "m4v", "m3u8"];
+
+/// A feed in the config, as `[FEEDS.<name>]`, the name is the one of the source.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct FeedSettings {
+    /// the url of the RSS or Atom feed
+    pub url: String,
+    /// shown in the source picker, the name of the source when unset
+    pub display_name: Option<String>,
+}
+
+/// Lists the entries of an RSS 2.0 or Atom feed as the hot news.
+pub struct FeedNews {
+    client: Client,
+    source: SourceName,
+    url: String,
+}
+
+impl FeedNews {
+    pub fn new(source: impl Into<SourceName>, url: impl Into<String>) -> Self {
+        Self {
+            client: Client::new(),
+            source: source.into(),
+            url: url.into(),
+        }
+    }
+
+    /// The entries of `feed` that link to an article, in the order of the feed.
+    fn parse(&self, feed: &[u8]) -> Result<Vec<NewsTitle>, NewsCrawlerError> {
+        let feed = feed_rs::parser::parse(feed)
+            .map_err(|e| NewsCrawlerError::Response(format!("invalid feed: {}", e)))?;
+
+        Ok(feed
+            .entries
+            .iter()
+            .filter_map(|entry| self.news_title(entry, feed.language.as_deref()))
+            .collect())
+    }
+
+    /// The news of `entry`, in the `language` of the feed unless the entry has its own.
+    fn news_title(&self, entry: &Entry, language: Option<&str>) -> Option<NewsTitle> {
+        let url = entry
+            .links
+            .iter()
+            .find(|link| matches!(link.rel.as_deref(), None | Some("alternate")))?
+            .href
+            .clone();
+        let title = entry
+            .title
+            .as_ref()
+            .map(|title| title.content.trim().to_owned())
+            .unwrap_or_default();
+
+        let mut pics = vec![];
+        let mut videos = vec![];
+        let mut add = |url: &str, media_type: Option<&str>| {
+            let list = match Media::of(url, media_type) {
+                Some(Media::Image) => &mut pics,
+                Some(Media::Video) => &mut videos,
+                None => return,
+            };
+            if !list.iter().any(|u| u == url) {
+                list.push(url.to_owned());
+            }
+        };
+
+        // enclosures and `media:content` are both media objects
+        for media in &entry.media {
+            for content in &media.content {
+                if let Some(url) = &content.url {
+                    let media_type = content.content_type.as_ref().map(|t| t.to_string());
+                    add(url.as_str(), media_type.as_deref());
+                }
+            }
+            for thumbnail in &media.thumbnails {
+                add(&thumbnail.image.uri, Some("image/*"));
+            }
+        }
+        // Atom enclosures
+        for Link {
+            href, media_type, ..
+        } in entry
+            .links
+            .iter()
+            .filter(|link| link.rel.as_deref() == Some("enclosure"))
+        {
+            add(href, media_type.as_deref());
+        }
+
+        let categories: Vec<String> = entry
+            .categories
+            .iter()
+            .map(|category| category.label.clone().unwrap_or(category.term.clone()))
+            .collect();
+
+        Some(NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: self.source.clone(),
+            title,
+            url,
+            pics,
+            videos,
+            meta: NewsMeta {
+                published_at: entry
+                    .published
+                    .or(entry.updated)
+                    .and_then(|time| u64::try_from(time.timestamp()).ok()),
+                author: entry.authors.first().map(|author| author.name.clone()),
+                channel: categories.first().cloned(),
+                tags: categories,
+                language: entry.language.clone().or(language.map(str::to_owned)),
+                summary: entry
+                    .summary
+                    .as_ref()
+                    .map(|summary| summary.content.trim().to_owned())
+                    .filter(|summary| !summary.is_empty()),
+                ..Default::default()
+            },
+        })
+    }
+}
+
+#[async_trait::async_trait]
+impl NewsCrawler for FeedNews {
+    async fn get_hot_news_list(&self) -> Result<Vec<NewsTitle>, NewsCrawlerError> {
+        match self.fetch_hot_news_list(&Validators::default()).await? {
+            Fetched::Modified { titles, .. } => Ok(titles),
+            // nothing to compare with
+            Fetched::NotModified => Ok(vec![]),
+        }
+    }
+
+    async fn fetch_hot_news_list(
+        &self,
+        validators: &Validators,
+    ) -> Result<Fetched, NewsCrawlerError> {
+        let Some((feed, validators)) = conditional_get(&self.client, &self.url, validators).await?
+        else {
+            return Ok(Fetched::NotModified);
+        };
+
+        Ok(Fetched::Modified {
+            titles: self.parse(&feed)?,
+            validators,
+        })
+    }
+}
+
+enum Media {
+    Image,
+    Video,
+}
+
+impl Media {
+    /// By the media type, else by the extension of `url`.
+    fn of(url: &str, media_type: Option<&str>) -> Option<Self> {
+        match media_type.and_then(|t| t.split_once('/')).map(|(ty, _)| ty) {
+            Some("image") => return Some(Self::Image),
+            Some("video") => return Some(Self::Video),
+            Some(_) => return None,
+            None => {}
+        }
+
+        let path = url.split(['?', '#']).next().unwrap_or(url);
+        let extension = path.rsplit_once('.')?.1.to_lowercase();
+        if IMAGE_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Image)
+        } else if VIDEO_EXTENSIONS.contains(&extension.as_str()) {
+            Some(Self::Video)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSS: &str = include_str!("../../tests/feeds/rss.xml");
+    const ATOM: &str = include_str!("../../tests/feeds/atom.xml");
+
+    #[test]
+    fn parse_rss() {
+        let titles = FeedNews::new("rss", "").parse(RSS.as_bytes()).unwrap();
+
+        // the item without a link is skipped
+        assert_eq!(2, titles.len());
+        assert_eq!("rss", titles[0].source);
+        assert_eq!("First news", titles[0].title);
+        assert_eq!("https://example.com/news/1", titles[0].url);
+        assert_eq!(
+            vec![
+                "https://example.com/img/1.jpg".to_owned(),
+                "https://example.com/img/1-thumb.png".to_owned(),
+            ],
+            titles[0].pics
+        );
+        assert_eq!(vec!["https://example.com/video/1.mp4"], titles[0].videos);
+        assert_eq!(Some(1754294400), titles[0].meta.published_at);
+        assert_eq!(Some("World"), titles[0].meta.channel.as_deref());
+        assert_eq!(vec!["World", "Europe"], titles[0].meta.tags);
+        assert_eq!(Some("en-gb"), titles[0].meta.language.as_deref());
+        assert_eq!(
+            Some("What happened first"),
+            titles[0].meta.summary.as_deref()
+        );
+
+        // an enclosure typed by its extension only
+        assert_eq!(vec!["https://example.com/img/2.webp?w=800"], titles[1].pics);
+        assert!(titles[1].videos.is_empty());
+    }
+
+    #[test]
+    fn parse_atom() {
+        let titles = FeedNews::new("atom", "").parse(ATOM.as_bytes()).unwrap();
+
+        assert_eq!(1, titles.len());
+        assert_eq!("Atom news", titles[0].title);
+        assert_eq!("https://example.org/2025/atom-news", titles[0].url);
+        assert_eq!(vec!["https://example.org/img/atom.jpg"], titles[0].pics);
+        assert_eq!(
+            vec!["https://example.org/video/atom.webm"],
+            titles[0].videos
+        );
+    }
+
+    #[tokio::test]
+    async fn get_hot_news_list() {
+        let mut server = mockito::Server::new_async().await;
+        let _mock = server
+            .mock("GET", "/feed.xml")
+            .with_status(200)
+            .with_header("content-type", "application/rss+xml")
+            .with_body(RSS)
+            .create();
+        let _bad = server
+            .mock("GET", "/bad.xml")
+            .with_status(200)
+            .with_body("<html></html>")
+            .create();
+
+        let feed = FeedNews::new("rss", format!("{}/feed.xml", server.url()));
+        assert_eq!(2, feed.get_hot_news_list().await.unwrap().len());
+
+        let feed = FeedNews::new("rss", format!("{}/bad.xml", server.url()));
+        assert!(matches!(
+            feed.get_hot_news_list().await,
+            Err(NewsCrawlerError::Response(_))
+        ));
+    }
+}
@@ -0,0 +1,351 @@
+use std::collections::{BTreeMap, HashSet};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::{director::source::SourceName, news::NewsTitle};
+
+/// The rules the hot news lists are filtered and ranked by, in the config as `[FILTER]`
+/// or `[PROFILES.<name>.FILTER]`. The keywords are matched ignoring case.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", default)]
+pub struct FilterSettings {
+    /// a news must contain one of them, the more it contains the higher it ranks
+    pub include: Vec<String>,
+    /// a news containing any of them is never shown
+    pub exclude: Vec<String>,
+    /// like `include`, as regexes
+    pub include_regex: Vec<String>,
+    /// like `exclude`, as regexes
+    pub exclude_regex: Vec<String>,
+    /// the channels and tags allowed or left out, by source
+    pub categories: BTreeMap<SourceName, CategoryFilter>,
+    /// the news whose normalized titles are at least this similar, from 0 to 1,
+    /// are shown once, the higher ranked one. No dedupe when unset.
+    pub dedupe_similarity: Option<f64>,
+    /// leave out the news already in the library
+    pub skip_rendered: bool,
+}
+
+/// The channels and tags of a source, as `[FILTER.CATEGORIES.<source>]`.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", default)]
+pub struct CategoryFilter {
+    /// a news of the source must have one of them as channel or tag
+    pub include: Vec<String>,
+    /// a news of the source with one of them as channel or tag is never shown
+    pub exclude: Vec<String>,
+}
+
+impl FilterSettings {
+    /// The keys that are set but wrong with why, `EXCLUDE_REGEX` for `exclude_regex`.
+    pub fn invalid(&self) -> Vec<(&'static str, String)> {
+        let mut errors = vec![];
+
+        for (key, regexes) in [
+            ("INCLUDE_REGEX", &self.include_regex),
+            ("EXCLUDE_REGEX", &self.exclude_regex),
+        ] {
+            for regex in regexes {
+                if Regex::new(regex).is_err() {
+                    errors.push((key, format!("has an invalid regex `{}`", regex)));
+                }
+            }
+        }
+        if let Some(similarity) = self.dedupe_similarity
+            && !(0.0..=1.0).contains(&similarity)
+        {
+            errors.push(("DEDUPE_SIMILARITY", "must be from 0 to 1".to_owned()));
+        }
+
+        errors
+    }
+}
+
+/// The rules of `FilterSettings` ready to apply, the regexes compiled.
+#[derive(Clone, Debug, Default)]
+pub struct NewsFilter {
+    include: Vec<String>,
+    exclude: Vec<String>,
+    include_regex: Vec<Regex>,
+    exclude_regex: Vec<Regex>,
+    categories: BTreeMap<SourceName, CategoryFilter>,
+    dedupe_similarity: Option<f64>,
+    skip_rendered: bool,
+}
+
+impl NewsFilter {
+    /// The filter of `settings`, the invalid regexes are left out,
+    /// they have been reported by the validation of the config.
+    pub fn new(settings: &FilterSettings) -> Self {
+        let lowercase = |words: &Vec<String>| -> Vec<String> {
+            words
+                .iter()
+                .filter(|word| !word.is_empty())
+                .map(|word| word.to_lowercase())
+                .collect()
+        };
+        let compile = |regexes: &Vec<String>| -> Vec<Regex> {
+            regexes.iter().filter_map(|r| Regex::new(r).ok()).collect()
+        };
+
+        Self {
+            include: lowercase(&settings.include),
+            exclude: lowercase(&settings.exclude),
+            include_regex: compile(&settings.include_regex),
+            exclude_regex: compile(&settings.exclude_regex),
+            categories: settings.categories.clone(),
+            dedupe_similarity: settings.dedupe_similarity,
+            skip_rendered: settings.skip_rendered,
+        }
+    }
+
+    /// The news of `titles` the rules allow, the ones matching the most include rules first,
+    /// else in their order. `rendered` tells whether the news at a url is in the library.
+    pub fn apply(&self, titles: Vec<NewsTitle>, rendered: &dyn Fn(&str) -> bool) -> Vec<NewsTitle> {
+        let mut ranked: Vec<(usize, NewsTitle)> = titles
+            .into_iter()
+            .filter(|title| !(self.skip_rendered && rendered(&title.url)))
+            .filter(|title| self.allows_category(title))
+            .filter_map(|title| Some((self.score(&title)?, title)))
+            .collect();
+        // stable, the ties keep the order of the lists
+        ranked.sort_by(|(a, _), (b, _)| b.cmp(a));
+
+        let mut kept: Vec<NewsTitle> = vec![];
+        let mut normalized: Vec<String> = vec![];
+        let mut urls = HashSet::new();
+        for (_, title) in ranked {
+            if !urls.insert(title.url.clone()) {
+                continue;
+            }
+
+            if let Some(threshold) = self.dedupe_similarity {
+                let name = normalize(&title.title);
+                if normalized
+                    .iter()
+                    .any(|other| similarity(&name, other) >= threshold)
+                {
+                    continue;
+                }
+                normalized.push(name);
+            }
+
+            kept.push(title);
+        }
+
+        kept
+    }
+
+    /// How many include rules `title` matches, `None` when it is excluded
+    /// or matches none of the include rules there are.
+    fn score(&self, title: &NewsTitle) -> Option<usize> {
+        let text = Self::text(title);
+        let lowercase = text.to_lowercase();
+
+        if self.exclude.iter().any(|word| lowercase.contains(word))
+            || self.exclude_regex.iter().any(|r| r.is_match(&text))
+        {
+            return None;
+        }
+
+        if self.include.is_empty() && self.include_regex.is_empty() {
+            return Some(0);
+        }
+        let score = self
+            .include
+            .iter()
+            .filter(|word| lowercase.contains(*word))
+            .count()
+            + self
+                .include_regex
+                .iter()
+                .filter(|r| r.is_match(&text))
+                .count();
+
+        (score > 0).then_some(score)
+    }
+
+    /// Whether the channel and tags of `title` pass the category rules of its source.
+    fn allows_category(&self, title: &NewsTitle) -> bool {
+        let Some(filter) = self.categories.get(&title.source) else {
+            return true;
+        };
+
+        let categories: Vec<String> = title
+            .meta
+            .channel
+            .iter()
+            .chain(title.meta.tags.iter())
+            .map(|category| category.to_lowercase())
+            .collect();
+        let listed = |names: &Vec<String>| {
+            names
+                .iter()
+                .any(|name| categories.contains(&name.to_lowercase()))
+        };
+
+        !listed(&filter.exclude) && (filter.include.is_empty() || listed(&filter.include))
+    }
+
+    /// What the rules are matched against, the title and the list summary.
+    fn text(title: &NewsTitle) -> String {
+        match &title.meta.summary {
+            Some(summary) => format!("{}\n{}", title.title, summary),
+            None => title.title.clone(),
+        }
+    }
+}
+
+/// The letters and digits of `title` in lowercase, the punctuation and spaces dropped.
+fn normalize(title: &str) -> String {
+    title
+        .chars()
+        .filter(|c| c.is_alphanumeric())
+        .flat_map(char::to_lowercase)
+        .collect()
+}
+
+/// The Dice coefficient of the character bigrams of `a` and `b`,
+/// 1 for the same titles, 0 for titles sharing no bigram.
+fn similarity(a: &str, b: &str) -> f64 {
+    if a == b {
+        return 1.0;
+    }
+
+    let bigrams = |s: &str| -> Vec<(char, char)> {
+        let chars: Vec<char> = s.chars().collect();
+        chars.windows(2).map(|w| (w[0], w[1])).collect()
+    };
+    let (a, mut b) = (bigrams(a), bigrams(b));
+    if a.is_empty() || b.is_empty() {
+        return 0.0;
+    }
+
+    let total = a.len() + b.len();
+    let mut shared = 0;
+    for bigram in a {
+        if let Some(index) = b.iter().position(|other| *other == bigram) {
+            b.swap_remove(index);
+            shared += 1;
+        }
+    }
+
+    2.0 * shared as f64 / total as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::news::{NEWS_MODEL_VERSION, NewsMeta};
+
+    use super::*;
+
+    fn title(source: &str, title: &str, url: &str, channel: Option<&str>) -> NewsTitle {
+        NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: source.to_owned(),
+            title: title.to_owned(),
+            url: url.to_owned(),
+            pics: vec![],
+            videos: vec![],
+            meta: NewsMeta {
+                channel: channel.map(str::to_owned),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn titles(list: &[NewsTitle]) -> Vec<&str> {
+        list.iter().map(|t| t.title.as_str()).collect()
+    }
+
+    #[test]
+    fn keywords_and_regexes() {
+        let filter = NewsFilter::new(&FilterSettings {
+            include: vec!["火箭".to_owned(), "launch".to_owned()],
+            exclude: vec!["娱乐".to_owned()],
+            exclude_regex: vec![r"^\[广告\]".to_owned()],
+            ..Default::default()
+        });
+
+        let list = filter.apply(
+            vec![
+                title("pengpai", "天气预报", "u1", None),
+                title("pengpai", "火箭发射", "u2", None),
+                title("pengpai", "[广告]火箭玩具", "u3", None),
+                title("bbc", "Rocket launch: 火箭 lifts off", "u4", None),
+                title("pengpai", "娱乐圈的火箭少女", "u5", None),
+            ],
+            &|_| false,
+        );
+
+        // the news matching both keywords ranks first
+        assert_eq!(
+            vec!["Rocket launch: 火箭 lifts off", "火箭发射"],
+            titles(&list)
+        );
+    }
+
+    #[test]
+    fn categories_of_a_source() {
+        let filter = NewsFilter::new(&FilterSettings {
+            categories: BTreeMap::from([(
+                "pengpai".to_owned(),
+                CategoryFilter {
+                    include: vec!["国际".to_owned(), "财经".to_owned()],
+                    exclude: vec!["财经".to_owned()],
+                },
+            )]),
+            ..Default::default()
+        });
+
+        let list = filter.apply(
+            vec![
+                title("pengpai", "一", "u1", Some("国际")),
+                title("pengpai", "二", "u2", Some("财经")),
+                title("pengpai", "三", "u3", None),
+                title("bbc", "四", "u4", None),
+            ],
+            &|_| false,
+        );
+
+        assert_eq!(vec!["一", "四"], titles(&list));
+    }
+
+    #[test]
+    fn dedupe_and_rendered() {
+        let filter = NewsFilter::new(&FilterSettings {
+            dedupe_similarity: Some(0.8),
+            skip_rendered: true,
+            ..Default::default()
+        });
+
+        let list = filter.apply(
+            vec![
+                title("pengpai", "长江江豚数量回升", "u1", None),
+                title("bbc", "长江江豚数量回升！", "u2", None),
+                title("pengpai", "长江江豚数量回升了", "u3", None),
+                title("pengpai", "台风登陆", "u4", None),
+                title("pengpai", "台风登陆", "u4", None),
+                title("pengpai", "已经做过的新闻", "u5", None),
+            ],
+            &|url| url == "u5",
+        );
+
+        assert_eq!(vec!["长江江豚数量回升", "台风登陆"], titles(&list));
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let settings = FilterSettings {
+            exclude_regex: vec!["(".to_owned()],
+            dedupe_similarity: Some(1.5),
+            ..Default::default()
+        };
+
+        let keys: Vec<&str> = settings.invalid().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(vec!["EXCLUDE_REGEX", "DEDUPE_SIMILARITY"], keys);
+        // the invalid regex is left out
+        assert!(NewsFilter::new(&settings).exclude_regex.is_empty());
+    }
+}
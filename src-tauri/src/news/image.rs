@@ -0,0 +1,132 @@
+use std::io::Cursor;
+
+use image::ImageReader;
+use reqwest::{Client, Url, header};
+
+use crate::news::article::ArticleImage;
+
+/// the headers of the usual formats are in the first bytes
+const PROBE_BYTES: usize = 64 * 1024;
+
+/// The size of the image at `url`, read from its first bytes, `file://` urls are read from disk.
+/// `None` when it can't be downloaded or is not an image.
+pub async fn probe(client: &Client, url: &str) -> Option<(u32, u32)> {
+    if let Some(path) = Url::parse(url)
+        .ok()
+        .filter(|url| url.scheme() == "file")
+        .and_then(|url| url.to_file_path().ok())
+    {
+        return tokio::task::spawn_blocking(move || image::image_dimensions(path).ok())
+            .await
+            .ok()?;
+    }
+
+    let mut response = client
+        .get(url)
+        .header(header::RANGE, format!("bytes=0-{}", PROBE_BYTES - 1))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .ok()?;
+
+    // the server may ignore the range
+    let mut bytes = vec![];
+    while let Some(chunk) = response.chunk().await.ok()? {
+        bytes.extend_from_slice(&chunk);
+        if let Some(size) = dimensions(&bytes) {
+            return Some(size);
+        }
+        if bytes.len() >= PROBE_BYTES {
+            break;
+        }
+    }
+
+    dimensions(&bytes)
+}
+
+fn dimensions(bytes: &[u8]) -> Option<(u32, u32)> {
+    ImageReader::new(Cursor::new(bytes))
+        .with_guessed_format()
+        .ok()?
+        .into_dimensions()
+        .ok()
+}
+
+/// Probe the size of the `images` whose size is unknown,
+/// and keep the ones of a content size.
+/// An image that can't be probed is kept, its page has it.
+pub async fn with_content_size(client: &Client, images: Vec<ArticleImage>) -> Vec<ArticleImage> {
+    let mut kept = vec![];
+    for mut image in images {
+        if (image.width.is_none() || image.height.is_none())
+            && let Some((width, height)) = probe(client, &image.url).await
+        {
+            image.width = Some(width);
+            image.height = Some(height);
+        }
+        if image.has_content_size() {
+            kept.push(image);
+        }
+    }
+    kept
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, RgbImage};
+
+    use super::*;
+
+    fn png(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = Cursor::new(vec![]);
+        RgbImage::new(width, height)
+            .write_to(&mut bytes, ImageFormat::Png)
+            .unwrap();
+        bytes.into_inner()
+    }
+
+    #[tokio::test]
+    async fn probe_sizes() {
+        let mut server = mockito::Server::new_async().await;
+        let _photo = server
+            .mock("GET", "/photo.png")
+            .match_header("range", "bytes=0-65535")
+            .with_status(206)
+            .with_body(png(640, 360))
+            .create();
+        let _icon = server
+            .mock("GET", "/icon.png")
+            .with_status(200)
+            .with_body(png(32, 32))
+            .create();
+        let _missing = server.mock("GET", "/missing.png").with_status(404).create();
+
+        let client = Client::new();
+        let url = |path: &str| format!("{}{}", server.url(), path);
+        assert_eq!(Some((640, 360)), probe(&client, &url("/photo.png")).await);
+
+        let image = |path: &str| ArticleImage {
+            url: url(path),
+            caption: None,
+            credit: None,
+            width: None,
+            height: None,
+        };
+        let images = with_content_size(
+            &client,
+            vec![
+                image("/photo.png"),
+                image("/icon.png"),
+                image("/missing.png"),
+            ],
+        )
+        .await;
+        assert_eq!(
+            vec![(Some(640), Some(360)), (None, None)],
+            images
+                .iter()
+                .map(|i| (i.width, i.height))
+                .collect::<Vec<_>>()
+        );
+    }
+}
@@ -0,0 +1,272 @@
+use std::{
+    io,
+    path::{Path, PathBuf},
+};
+
+use reqwest::Url;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+use tokio::fs;
+
+use crate::news::{
+    MaterialExtractor, NEWS_MODEL_VERSION, NewsCrawler, NewsCrawlerError, NewsMaterialError,
+    NewsMaterialResult, NewsMeta, NewsTitle, article::Article, summarizer::LlmMaterialExtractor,
+};
+
+pub const SOURCE_NAME: &str = "local";
+/// the dir the pasted texts are saved to, in the app data dir
+pub const LOCAL_DIR: &str = "local";
+
+/// the longest title taken from the first line of a text
+const MAX_TITLE_CHARS: usize = 30;
+
+#[derive(Error, Debug)]
+pub enum LocalNewsError {
+    #[error("a file or a text is required")]
+    Empty,
+    #[error("{0}: {1}")]
+    File(PathBuf, io::Error),
+    #[error("file error: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// A news we already have, an HTML, Markdown or text file or a pasted text,
+/// and the pictures on disk to show with it.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LocalNewsInput {
+    /// the first heading or line of the text when absent
+    pub title: Option<String>,
+    pub file: Option<PathBuf>,
+    /// Markdown or plain text, used when there is no file
+    pub text: Option<String>,
+    #[serde(default)]
+    pub images: Vec<PathBuf>,
+}
+
+impl LocalNewsInput {
+    /// The news title of the input, with the `file://` url of the document and the pictures.
+    /// A pasted text is saved in `dir` first.
+    /// The url changes with the content, so an edited file is summarized again.
+    pub async fn into_news_title(self, dir: &Path) -> Result<NewsTitle, LocalNewsError> {
+        let (path, content) = match (self.file, self.text) {
+            (Some(file), _) => {
+                let path = absolute(&file).await?;
+                let content = fs::read(&path)
+                    .await
+                    .map_err(|e| LocalNewsError::File(file, e))?;
+                (path, content)
+            }
+            (None, Some(text)) if !text.trim().is_empty() => {
+                let hash = hex(&text);
+                fs::create_dir_all(dir).await?;
+                let path = absolute(dir)
+                    .await?
+                    .join(format!("text-{}.md", &hash[..16]));
+                fs::write(&path, &text).await?;
+                (path, text.into_bytes())
+            }
+            _ => return Err(LocalNewsError::Empty),
+        };
+
+        let mut pics = vec![];
+        for image in self.images {
+            pics.push(file_url(&absolute(&image).await?)?.to_string());
+        }
+
+        let mut url = file_url(&path)?;
+        url.set_fragment(Some(&hex(&content)[..16]));
+
+        let title = self
+            .title
+            .filter(|title| !title.trim().is_empty())
+            .unwrap_or_else(|| default_title(&path, &content));
+
+        Ok(NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: SOURCE_NAME.to_owned(),
+            title,
+            url: url.to_string(),
+            pics,
+            videos: vec![],
+            meta: NewsMeta::default(),
+        })
+    }
+}
+
+async fn absolute(path: &Path) -> Result<PathBuf, LocalNewsError> {
+    fs::canonicalize(path)
+        .await
+        .map_err(|e| LocalNewsError::File(path.to_owned(), e))
+}
+
+fn file_url(path: &Path) -> Result<Url, LocalNewsError> {
+    Url::from_file_path(path).map_err(|_| {
+        LocalNewsError::File(
+            path.to_owned(),
+            io::Error::new(io::ErrorKind::InvalidInput, "not an absolute path"),
+        )
+    })
+}
+
+fn hex(content: impl AsRef<[u8]>) -> String {
+    format!("{:x}", Sha256::digest(content))
+}
+
+/// The title of the document, else its first line, else the file name.
+fn default_title(path: &Path, content: &[u8]) -> String {
+    let content = String::from_utf8_lossy(content);
+    let article = match Document::of(path) {
+        Document::Html => Article::extract(&content, None),
+        Document::Text => Article::from_text(&content, None),
+    };
+
+    article
+        .and_then(|a| a.title.or_else(|| a.paragraphs.into_iter().next()))
+        .map(|title| title.chars().take(MAX_TITLE_CHARS).collect())
+        .or_else(|| {
+            path.file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+        })
+        .unwrap_or_default()
+}
+
+enum Document {
+    Html,
+    /// Markdown or plain text
+    Text,
+}
+
+impl Document {
+    fn of(path: &Path) -> Self {
+        match path
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .as_deref()
+        {
+            Some("html" | "htm" | "xhtml") => Self::Html,
+            _ => Self::Text,
+        }
+    }
+}
+
+/// The local news are given one by one, there is no list of them.
+pub struct LocalNews;
+
+#[async_trait::async_trait]
+impl NewsCrawler for LocalNews {
+    async fn get_hot_news_list(&self) -> Result<Vec<NewsTitle>, NewsCrawlerError> {
+        Ok(vec![])
+    }
+}
+
+/// Reads the document of a local news from disk and summarizes it like a news page.
+pub struct LocalNewsMaterialExtractor {
+    summarizer: LlmMaterialExtractor,
+}
+
+impl LocalNewsMaterialExtractor {
+    pub fn new(summarizer: LlmMaterialExtractor) -> Self {
+        Self { summarizer }
+    }
+}
+
+#[async_trait::async_trait]
+impl MaterialExtractor for LocalNewsMaterialExtractor {
+    fn cache_key(&self) -> String {
+        self.summarizer.cache_key()
+    }
+
+    async fn get_material(&self, hot_news: &NewsTitle) -> NewsMaterialResult {
+        let (url, path) = Url::parse(&hot_news.url)
+            .ok()
+            .and_then(|url| Some((url.clone(), url.to_file_path().ok()?)))
+            .ok_or_else(|| NewsMaterialError::File(format!("not a file: {}", hot_news.url)))?;
+        let content = fs::read_to_string(&path)
+            .await
+            .map_err(|e| NewsMaterialError::File(format!("{}: {}", path.display(), e)))?;
+
+        // the relative pictures are next to the document
+        let article = match Document::of(&path) {
+            Document::Html => Article::extract(&content, Some(&url)),
+            Document::Text => Article::from_text(&content, Some(&url)),
+        }
+        .ok_or(NewsMaterialError::NoBody)?;
+
+        self.summarizer.summarize(hot_news, &article).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn pasted_text() {
+        let dir = tempdir::TempDir::new("local").unwrap();
+        let image = dir.path().join("photo.jpg");
+        std::fs::write(&image, b"").unwrap();
+
+        let input = LocalNewsInput {
+            text: Some("新品今天发布，欢迎大家试用。\n第二段".to_owned()),
+            images: vec![image.clone()],
+            ..Default::default()
+        };
+        let title = input
+            .clone()
+            .into_news_title(&dir.path().join(LOCAL_DIR))
+            .await
+            .unwrap();
+
+        assert_eq!(SOURCE_NAME, title.source);
+        assert_eq!("新品今天发布，欢迎大家试用。", title.title);
+        let url = Url::parse(&title.url).unwrap();
+        assert_eq!("file", url.scheme());
+        assert!(url.to_file_path().unwrap().exists());
+        assert_eq!(
+            vec![
+                Url::from_file_path(image.canonicalize().unwrap())
+                    .unwrap()
+                    .to_string()
+            ],
+            title.pics
+        );
+
+        // the same text is the same news
+        let again = input.into_news_title(&dir.path().join(LOCAL_DIR)).await;
+        assert_eq!(title.url, again.unwrap().url);
+
+        assert!(matches!(
+            LocalNewsInput::default().into_news_title(dir.path()).await,
+            Err(LocalNewsError::Empty)
+        ));
+    }
+
+    #[tokio::test]
+    async fn edited_file() {
+        let dir = tempdir::TempDir::new("local").unwrap();
+        let file = dir.path().join("release.html");
+        std::fs::write(&file, "<html><head><title>发布会</title></head></html>").unwrap();
+        let input = LocalNewsInput {
+            file: Some(file.clone()),
+            ..Default::default()
+        };
+
+        let first = input.clone().into_news_title(dir.path()).await.unwrap();
+        assert_eq!("release", first.title);
+
+        std::fs::write(&file, "<html><body><h1>发布会</h1></body></html>").unwrap();
+        let edited = input.into_news_title(dir.path()).await.unwrap();
+        assert_ne!(first.url, edited.url);
+
+        let missing = LocalNewsInput {
+            file: Some(dir.path().join("missing.md")),
+            ..Default::default()
+        };
+        assert!(matches!(
+            missing.into_news_title(dir.path()).await,
+            Err(LocalNewsError::File(..))
+        ));
+    }
+}
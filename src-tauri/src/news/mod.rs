@@ -1,17 +1,80 @@
+pub mod article;
+pub mod cache;
+pub mod feed_news;
+pub mod filter;
+pub mod image;
+pub mod local_news;
 pub mod pengpai_news;
+pub mod summarizer;
 
 use serde::{Deserialize, Serialize};
 use thiserror::Error;
 
-use crate::director::source::SourceName;
+use crate::{
+    director::source::SourceName,
+    news::cache::{Fetched, Validators},
+};
+
+/// The version of the `NewsTitle` and `NewsMaterial` written now,
+/// the ones saved before they had a version are version 1.
+pub const NEWS_MODEL_VERSION: u32 = 2;
+
+fn first_version() -> u32 {
+    1
+}
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct NewsTitle {
+    #[serde(default = "first_version")]
+    pub version: u32,
     pub source: SourceName,
     pub title: String,
     pub url: String,
     pub pics: Vec<String>,
     pub videos: Vec<String>,
+    #[serde(default)]
+    pub meta: NewsMeta,
+}
+
+/// What a list tells of a news besides its title, when it does.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase", default)]
+pub struct NewsMeta {
+    /// unix seconds
+    pub published_at: Option<u64>,
+    /// unix seconds, when the list was fetched
+    pub crawled_at: Option<u64>,
+    pub author: Option<String>,
+    /// the channel or category
+    pub channel: Option<String>,
+    pub tags: Vec<String>,
+    /// a language tag, e.g. `zh-CN`
+    pub language: Option<String>,
+    pub summary: Option<String>,
+}
+
+/// A page of a channel or of search results, the pages are numbered from 1.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct NewsPage {
+    pub titles: Vec<NewsTitle>,
+    pub page: u32,
+    pub has_next: bool,
+}
+
+/// A channel of a source, for `NewsCrawler::get_channel_news`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Channel {
+    pub id: String,
+    pub name: String,
+}
+
+/// Record `at` as the crawl time of the `titles` that have none.
+pub fn stamp_crawled(titles: &mut [NewsTitle], at: u64) {
+    for title in titles {
+        title.meta.crawled_at.get_or_insert(at);
+    }
 }
 
 impl NewsTitle {
@@ -27,23 +90,212 @@ impl NewsTitle {
 pub type NewsMaterialResult = Result<NewsMaterial, NewsMaterialError>;
 
 #[derive(Error, Debug)]
-pub enum NewsMaterialError {}
+pub enum NewsMaterialError {
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("the news page has no body")]
+    NoBody,
+    #[error("cannot read the news file: {0}")]
+    File(String),
+    #[error("llm request failed: {0}")]
+    Llm(String),
+    #[error("unexpected llm answer: {0}")]
+    Answer(String),
+}
 
+#[derive(Error, Debug)]
+pub enum NewsCrawlerError {
+    #[error("network error: {0}")]
+    Network(String),
+    #[error("unexpected response: {0}")]
+    Response(String),
+    #[error("the source has no {0}")]
+    Unsupported(&'static str),
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct NewsMaterial {
+    #[serde(default = "first_version")]
+    pub version: u32,
     pub title: String,
     pub summary: Vec<String>,
-    pub pics: Vec<String>,
+    pub pics: Vec<NewsPic>,
     pub videos: Vec<String>,
+    /// the paragraphs the summary was made of
+    #[serde(default)]
+    pub article: Vec<String>,
+    /// `None` when the summary was not made by an LLM, or before it was recorded
+    #[serde(default)]
+    pub provenance: Option<Provenance>,
+}
+
+impl NewsMaterial {
+    pub fn pic_urls(&self) -> Vec<String> {
+        self.pics.iter().map(|pic| pic.url.clone()).collect()
+    }
+}
+
+/// A picture of the material and what its page tells of it.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(from = "StoredPic")]
+pub struct NewsPic {
+    pub url: String,
+    pub caption: Option<String>,
+    /// who the picture is credited to
+    pub credit: Option<String>,
+}
+
+impl From<&str> for NewsPic {
+    fn from(url: &str) -> Self {
+        Self {
+            url: url.to_owned(),
+            ..Default::default()
+        }
+    }
+}
+
+/// The pictures were plain urls in version 1.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum StoredPic {
+    Url(String),
+    Pic {
+        url: String,
+        caption: Option<String>,
+        credit: Option<String>,
+    },
+}
+
+impl From<StoredPic> for NewsPic {
+    fn from(pic: StoredPic) -> Self {
+        match pic {
+            StoredPic::Url(url) => url.as_str().into(),
+            StoredPic::Pic {
+                url,
+                caption,
+                credit,
+            } => Self {
+                url,
+                caption,
+                credit,
+            },
+        }
+    }
+}
+
+/// What made the summary of a material.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Provenance {
+    /// the LLM model
+    pub model: String,
+    /// the version of the prompt, bumped when it changes
+    pub prompt_version: u32,
+    /// unix seconds
+    pub created_at: u64,
 }
 
 /// crawler, indicats how to get the news title list
 #[async_trait::async_trait]
 pub trait NewsCrawler {
-    async fn get_hot_news_list(&self) -> Vec<NewsTitle>;
+    async fn get_hot_news_list(&self) -> Result<Vec<NewsTitle>, NewsCrawlerError>;
+
+    /// The list unless it is unchanged since the response of `validators`,
+    /// the crawlers over HTTP revalidate, the others fetch it again.
+    async fn fetch_hot_news_list(
+        &self,
+        _validators: &Validators,
+    ) -> Result<Fetched, NewsCrawlerError> {
+        Ok(Fetched::Modified {
+            titles: self.get_hot_news_list().await?,
+            validators: Validators::default(),
+        })
+    }
+
+    /// The channels `get_channel_news` takes, none by default.
+    fn channels(&self) -> Vec<Channel> {
+        vec![]
+    }
+
+    /// The news of the channel `channel`, `page` from 1.
+    async fn get_channel_news(
+        &self,
+        _channel: &str,
+        _page: u32,
+    ) -> Result<NewsPage, NewsCrawlerError> {
+        Err(NewsCrawlerError::Unsupported("channels"))
+    }
+
+    /// The news matching `keyword`, `page` from 1.
+    async fn search_news(&self, _keyword: &str, _page: u32) -> Result<NewsPage, NewsCrawlerError> {
+        Err(NewsCrawlerError::Unsupported("search"))
+    }
 }
 
 /// Extractor, responsible for extractor news material: news summary, pictures, videos.
 #[async_trait::async_trait]
 pub trait MaterialExtractor {
+    /// The settings the material depends on, part of the cache key of the stage.
+    fn cache_key(&self) -> String {
+        String::new()
+    }
+
     async fn get_material(&self, hot_news: &NewsTitle) -> NewsMaterialResult;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn read_first_version() {
+        let material: NewsMaterial = serde_json::from_str(
+            r#"{"title":"T","summary":["一"],"pics":["https://example.com/a.jpg"],"videos":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(1, material.version);
+        assert_eq!(
+            vec![NewsPic::from("https://example.com/a.jpg")],
+            material.pics
+        );
+        assert_eq!(None, material.provenance);
+
+        let title: NewsTitle = serde_json::from_str(
+            r#"{"source":"pengpai","title":"T","url":"u","pics":[],"videos":[]}"#,
+        )
+        .unwrap();
+        assert_eq!(1, title.version);
+        assert_eq!(NewsMeta::default(), title.meta);
+    }
+
+    #[test]
+    fn round_trip() {
+        let material = NewsMaterial {
+            version: NEWS_MODEL_VERSION,
+            title: "T".to_owned(),
+            summary: vec!["一".to_owned()],
+            pics: vec![NewsPic {
+                url: "https://example.com/a.jpg".to_owned(),
+                caption: Some("江豚".to_owned()),
+                credit: Some("新华社".to_owned()),
+            }],
+            videos: vec![],
+            article: vec!["正文".to_owned()],
+            provenance: Some(Provenance {
+                model: "deepseek-chat".to_owned(),
+                prompt_version: 2,
+                created_at: 1754294400,
+            }),
+        };
+
+        let json = serde_json::to_value(&material).unwrap();
+        assert_eq!("新华社", json["pics"][0]["credit"]);
+        assert_eq!(2, json["provenance"]["promptVersion"]);
+
+        let read: NewsMaterial = serde_json::from_value(json).unwrap();
+        assert_eq!(material.pics, read.pics);
+        assert_eq!(material.provenance, read.provenance);
+        assert_eq!(NEWS_MODEL_VERSION, read.version);
+    }
+}
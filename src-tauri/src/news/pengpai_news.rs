@@ -1,51 +1,154 @@
-use openai::{
-    Credentials,
-    chat::{ChatCompletion, ChatCompletionMessage, ChatCompletionMessageRole},
-};
 use reqwest::Client;
-use scraper::Selector;
-use std::{cell::RefCell, sync::Mutex};
+use scraper::Html;
 
-use crate::news::{MaterialExtractor, NewsCrawler, NewsMaterial, NewsMaterialResult, NewsTitle};
+use crate::news::{
+    Channel, NEWS_MODEL_VERSION, NewsCrawler, NewsCrawlerError, NewsMeta, NewsPage, NewsTitle,
+    cache::{Fetched, Validators, conditional_get},
+};
 
 pub const SOURCE_NAME: &str = "pengpai";
-
+const LANGUAGE: &str = "zh-CN";
+
+const CACHE_HOST: &str = "https://cache.thepaper.cn";
+const API_HOST: &str = "https://api.thepaper.cn";
+const HOT_NEWS_PATH: &str = "/contentapi/wwwIndex/rightSidebar";
+const CHANNEL_PATH: &str = "/contentapi/nodeCont/getByChannelId";
+const SEARCH_PATH: &str = "/search/web/news";
+const PAGE_SIZE: u32 = 20;
+/// the channels of the home page, `(id, name)`
+const CHANNELS: [(&str, &str); 6] = [
+    ("25950", "时事"),
+    ("25951", "财经"),
+    ("122908", "国际"),
+    ("119908", "科技"),
+    ("25952", "思想"),
+    ("25953", "生活"),
+];
+
+/// The hot list of thepaper.cn, its channels and its search.
 pub struct PengPaiNews {
     client: Client,
-    hot_news_resp: Mutex<RefCell<Vec<PengPaiHotNews>>>,
-    // pub articles: Vec<Article>,
+    cache_host: String,
+    api_host: String,
 }
 
 impl PengPaiNews {
     pub fn new() -> Self {
         Self {
             client: Client::new(),
-            hot_news_resp: Mutex::new(RefCell::new(vec![])),
-            // articles: vec![],
+            cache_host: CACHE_HOST.to_owned(),
+            api_host: API_HOST.to_owned(),
+        }
+    }
+
+    /// Where the hot list and the other APIs are, the hosts of thepaper.cn by default.
+    pub fn with_hosts(self, cache_host: impl Into<String>, api_host: impl Into<String>) -> Self {
+        Self {
+            cache_host: cache_host.into(),
+            api_host: api_host.into(),
+            ..self
         }
     }
+
+    /// POST `body` to the paged list API at `path`.
+    async fn post_list(
+        &self,
+        path: &str,
+        body: serde_json::Value,
+        page: u32,
+    ) -> Result<NewsPage, NewsCrawlerError> {
+        let response = self
+            .client
+            .post(format!("{}{}", self.api_host, path))
+            .json(&body)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| NewsCrawlerError::Network(e.to_string()))?;
+        let json: ListResponse = response
+            .json()
+            .await
+            .map_err(|e| NewsCrawlerError::Response(e.to_string()))?;
+
+        Ok(NewsPage {
+            titles: json
+                .data
+                .list
+                .into_iter()
+                .map(PengPaiCont::into_news_title)
+                .collect(),
+            page,
+            has_next: json.data.has_next,
+        })
+    }
 }
 
 #[async_trait::async_trait]
 impl NewsCrawler for PengPaiNews {
-    async fn get_hot_news_list(&self) -> Vec<super::NewsTitle> {
-        let url = "https://cache.thepaper.cn/contentapi/wwwIndex/rightSidebar";
+    async fn get_hot_news_list(&self) -> Result<Vec<super::NewsTitle>, NewsCrawlerError> {
+        match self.fetch_hot_news_list(&Validators::default()).await? {
+            Fetched::Modified { titles, .. } => Ok(titles),
+            // nothing to compare with
+            Fetched::NotModified => Ok(vec![]),
+        }
+    }
 
-        let response = self.client.get(url).send().await.unwrap();
-        let json: ResponseContent = response.json().await.unwrap();
+    async fn fetch_hot_news_list(
+        &self,
+        validators: &Validators,
+    ) -> Result<Fetched, NewsCrawlerError> {
+        let url = format!("{}{}", self.cache_host, HOT_NEWS_PATH);
+        let Some((body, validators)) = conditional_get(&self.client, &url, validators).await?
+        else {
+            return Ok(Fetched::NotModified);
+        };
+        let json: ResponseContent =
+            serde_json::from_slice(&body).map_err(|e| NewsCrawlerError::Response(e.to_string()))?;
+
+        Ok(Fetched::Modified {
+            titles: json
+                .data
+                .hot_news
+                .into_iter()
+                .map(PengPaiHotNews::to_hot_news)
+                .collect(),
+            validators,
+        })
+    }
 
-        let resp_data = json.data.hot_news;
+    fn channels(&self) -> Vec<Channel> {
+        CHANNELS
+            .iter()
+            .map(|(id, name)| Channel {
+                id: id.to_string(),
+                name: name.to_string(),
+            })
+            .collect()
+    }
 
-        {
-            let mut lock = self.hot_news_resp.lock().unwrap();
-            let list = lock.get_mut();
-            *list = resp_data.clone();
-        }
+    async fn get_channel_news(
+        &self,
+        channel: &str,
+        page: u32,
+    ) -> Result<NewsPage, NewsCrawlerError> {
+        let body = serde_json::json!({
+            "channelId": channel,
+            "pageNum": page,
+            "pageSize": PAGE_SIZE,
+        });
+        self.post_list(CHANNEL_PATH, body, page).await
+    }
 
-        resp_data
-            .into_iter()
-            .map(PengPaiHotNews::to_hot_news)
-            .collect()
+    async fn search_news(&self, keyword: &str, page: u32) -> Result<NewsPage, NewsCrawlerError> {
+        // the newest first
+        let body = serde_json::json!({
+            "word": keyword,
+            "orderType": 3,
+            "pageNum": page,
+            "pageSize": PAGE_SIZE,
+            "searchType": 1,
+        });
+        self.post_list(SEARCH_PATH, body, page).await
     }
 }
 
@@ -81,144 +184,226 @@ impl PengPaiHotNews {
         }
 
         NewsTitle {
+            version: NEWS_MODEL_VERSION,
             source: SOURCE_NAME.to_owned(),
             title: self.name,
-            url: format!(
-                "https://www.thepaper.cn/newsDetail_forward_{}",
-                self.cont_id
-            ),
+            url: news_url(&self.cont_id),
             pics,
             videos,
+            meta: NewsMeta {
+                language: Some(LANGUAGE.to_owned()),
+                ..Default::default()
+            },
         }
     }
 }
 
+fn news_url(cont_id: &str) -> String {
+    format!("https://www.thepaper.cn/newsDetail_forward_{}", cont_id)
+}
+
+/// The answer of the channel and search APIs.
+#[derive(Debug, serde::Deserialize)]
+struct ListResponse {
+    pub data: ListData,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListData {
+    pub list: Vec<PengPaiCont>,
+    #[serde(default)]
+    pub has_next: bool,
+}
+
+/// A news of a channel or of the search results.
 #[derive(Debug, serde::Deserialize, Clone)]
-struct Video {
-    #[serde(rename = "url")]
-    pub url: String,
-    #[serde(rename = "coverUrlFirstFrame")]
-    pub cover: String,
+#[serde(rename_all = "camelCase")]
+struct PengPaiCont {
+    pub cont_id: String,
+    /// the search results highlight the keyword with `<em>`
+    pub name: String,
+    pub pic: Option<String>,
+    pub videos: Option<Video>,
+    /// unix milliseconds
+    pub pub_time_long: Option<u64>,
+    pub author_info: Option<AuthorInfo>,
+    pub node_info: Option<NodeInfo>,
+    pub summary: Option<String>,
+    #[serde(default)]
+    pub tag_list: Vec<Tag>,
 }
 
-pub struct PengPaiNewsMaterialExtractor {
-    credentials: Credentials,
+#[derive(Debug, serde::Deserialize, Clone)]
+struct Tag {
+    pub tag: String,
 }
 
-impl PengPaiNewsMaterialExtractor {
-    pub fn from_deepseek(api_key: impl Into<String>) -> Self {
-        Self {
-            credentials: Credentials::new(api_key, "https://api.deepseek.com"),
-        }
-    }
+#[derive(Debug, serde::Deserialize, Clone)]
+struct AuthorInfo {
+    pub sname: String,
 }
 
-#[async_trait::async_trait]
-impl MaterialExtractor for PengPaiNewsMaterialExtractor {
-    async fn get_material(&self, hot_news: &NewsTitle) -> NewsMaterialResult {
-        let http = Client::new();
-        let response = http.get(hot_news.url.clone()).send().await.unwrap();
-        let raw_content = response.text().await.unwrap();
+#[derive(Debug, serde::Deserialize, Clone)]
+struct NodeInfo {
+    pub name: String,
+}
 
-        let body = Self::get_body_inner_text(raw_content);
+impl PengPaiCont {
+    fn into_news_title(self) -> NewsTitle {
+        let mut pics: Vec<String> = self.pic.into_iter().filter(|p| !p.is_empty()).collect();
+        let mut videos = vec![];
 
-        let deepseek_result = self.ask_deepseek(&body).await;
+        if let Some(video) = self.videos {
+            videos.push(video.url);
+            pics.push(video.cover);
+        }
 
-        let mut pics = hot_news.pics.clone();
-        pics.extend(deepseek_result.images);
+        let text = |value: Option<String>| value.map(|v| strip_tags(&v)).filter(|v| !v.is_empty());
 
-        Ok(NewsMaterial {
-            title: hot_news.title.clone(),
-            summary: deepseek_result.summary.clone(),
-            videos: hot_news.videos.clone(),
+        NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: SOURCE_NAME.to_owned(),
+            title: strip_tags(&self.name),
+            url: news_url(&self.cont_id),
             pics,
-        })
+            videos,
+            meta: NewsMeta {
+                published_at: self.pub_time_long.map(|millis| millis / 1000),
+                author: text(self.author_info.map(|a| a.sname)),
+                channel: text(self.node_info.map(|n| n.name)),
+                tags: self.tag_list.into_iter().map(|t| t.tag).collect(),
+                language: Some(LANGUAGE.to_owned()),
+                summary: text(self.summary),
+                ..Default::default()
+            },
+        }
     }
 }
 
-#[derive(Debug, serde::Deserialize, Clone)]
-struct DeepSeekResult {
-    pub summary: Vec<String>,
-    pub images: Vec<String>,
+fn strip_tags(html: &str) -> String {
+    Html::parse_fragment(html)
+        .root_element()
+        .text()
+        .collect::<String>()
+        .trim()
+        .to_owned()
 }
 
-impl PengPaiNewsMaterialExtractor {
-    fn get_body_inner_text(raw_content: String) -> String {
-        use scraper::Html;
-        let document = Html::parse_document(&raw_content);
-        let body = Selector::parse("body").unwrap();
+#[derive(Debug, serde::Deserialize, Clone)]
+struct Video {
+    #[serde(rename = "url")]
+    pub url: String,
+    #[serde(rename = "coverUrlFirstFrame")]
+    pub cover: String,
+}
 
-        let mut bodys = document.select(&body);
-        let body = bodys.next().unwrap();
-        body.inner_html()
+#[cfg(test)]
+mod tests {
+    use mockito::Matcher;
+    use serde_json::json;
+
+    use super::*;
+
+    const HOT: &str = include_str!("../../tests/pengpai/hot.json");
+    const CHANNEL: &str = include_str!("../../tests/pengpai/channel.json");
+    const SEARCH: &str = include_str!("../../tests/pengpai/search.json");
+
+    #[tokio::test]
+    async fn hot_news_list() {
+        let mut server = mockito::Server::new_async().await;
+        let _hot = server
+            .mock("GET", HOT_NEWS_PATH)
+            .with_status(200)
+            .with_body(HOT)
+            .create_async()
+            .await;
+
+        let crawler = PengPaiNews::new().with_hosts(server.url(), server.url());
+        let titles = crawler.get_hot_news_list().await.unwrap();
+
+        assert_eq!(2, titles.len());
+        assert_eq!("台风“竹节草”登陆浙江", titles[0].title);
+        assert_eq!(
+            "https://www.thepaper.cn/newsDetail_forward_31200001",
+            titles[0].url
+        );
+        assert_eq!(2, titles[0].pics.len());
+        assert_eq!(
+            vec!["https://cloudvideo.thepaper.cn/31200001.mp4"],
+            titles[0].videos
+        );
+        assert_eq!(Some("zh-CN"), titles[1].meta.language.as_deref());
+        assert_eq!(None, titles[1].meta.published_at);
     }
 
-    async fn ask_deepseek(&self, news_content: &str) -> DeepSeekResult {
-        let messages = vec![
-            ChatCompletionMessage {
-                role: ChatCompletionMessageRole::System,
-                content: Some(Self::get_prompt().to_string()),
-                name: None,
-                function_call: None,
-                tool_call_id: None,
-                tool_calls: None,
-            },
-            ChatCompletionMessage {
-                role: ChatCompletionMessageRole::User,
-                content: Some(news_content.to_string()),
-                name: None,
-                function_call: None,
-                tool_call_id: None,
-                tool_calls: None,
+    #[tokio::test]
+    async fn channel_news() {
+        let mut server = mockito::Server::new_async().await;
+        let _channel = server
+            .mock("POST", CHANNEL_PATH)
+            .match_body(Matcher::PartialJson(
+                json!({"channelId": "25951", "pageNum": 2}),
+            ))
+            .with_status(200)
+            .with_body(CHANNEL)
+            .create_async()
+            .await;
+
+        let crawler = PengPaiNews::new().with_hosts(server.url(), server.url());
+        let page = crawler.get_channel_news("25951", 2).await.unwrap();
+
+        assert_eq!(2, page.page);
+        assert!(page.has_next);
+        assert_eq!(2, page.titles.len());
+        let first = &page.titles[0];
+        assert_eq!(SOURCE_NAME, first.source);
+        assert_eq!(
+            NewsMeta {
+                published_at: Some(1754294400),
+                crawled_at: None,
+                author: Some("澎湃新闻记者 王晓".to_owned()),
+                channel: Some("金融界".to_owned()),
+                tags: vec!["货币政策".to_owned(), "央行".to_owned()],
+                language: Some("zh-CN".to_owned()),
+                summary: Some("央行表示将继续实施适度宽松的货币政策。".to_owned()),
             },
-        ];
-
-        let chat_completion = ChatCompletion::builder("deepseek-chat", messages.clone())
-            .credentials(self.credentials.clone())
-            .create()
-            .await
-            .unwrap();
-
-        let returned_message = chat_completion.choices.first().unwrap().message.clone();
-
-        let raw_json = returned_message
-            .content
-            .unwrap()
-            .trim_start_matches("```json")
-            .trim_start_matches("```JSON")
-            .trim_end_matches("```")
-            .trim()
-            .to_string();
-
-        println!("raw_json: {}", raw_json);
-        serde_json::from_str(&raw_json).unwrap()
+            first.meta
+        );
+        // no picture, no author and an empty summary
+        let second = &page.titles[1];
+        assert!(second.pics.is_empty());
+        assert_eq!(None, second.meta.author);
+        assert_eq!(None, second.meta.summary);
+
+        assert!(matches!(
+            crawler.get_channel_news("25952", 1).await,
+            Err(NewsCrawlerError::Network(_))
+        ));
     }
 
-    fn get_prompt() -> &'static str {
-        r#"
-你是一个爆款短视频的作者，我会给你一个 HTML 格式的新闻稿，你要根据要求总结里面的新闻，并提取正文的图片，具体要求为：
-1. 将新闻内容浓缩为200字内的短视频风格摘要，严格控制在200字以内，使用吸引眼球的短视频的风格夸张语气和俏皮。
-风格夸张俏皮，喜欢使用网络热词和热梗，保持事实准确，突出核心事件、关键人物和戏剧性细节，纯文字输出，禁止使用表情符号，时间地点人物等关键信息必须准确，注意中文标点符号使用规范。正文要根据逗号、句号分割，放在数组内，如：
-
-```json
-["句子1", "句子2"]
-```
-
-2. 从新闻稿HTML中提取仅正文部分的图片链接（排除封面、视频缩略图、图标等非正文内容），并去除URL中的querystring参数。严格限定在正文内容区域，排除所有非正文图片（封面/视频缩略图/广告等），清除URL中?及后面的参数，结果以JSON数组格式返回，若无符合条件图片则返回空数组[]。如：
-
-```json
-[
-        "https://imgpai.cn/newpai/image/175423202.jpg",
-        "https://imgpai.cn/newpai/image/17540.jpg"
-      ]
-```
-
-上面两点要求按照 JSON 格式输出，如：
-
-{
-  "summary": ["句子1", "句子2"],
-  "images": ["图片1", "图片2"]
-}
-        "#
+    #[tokio::test]
+    async fn search_news() {
+        let mut server = mockito::Server::new_async().await;
+        let _search = server
+            .mock("POST", SEARCH_PATH)
+            .match_body(Matcher::PartialJson(json!({"word": "台风", "pageNum": 3})))
+            .with_status(200)
+            .with_body(SEARCH)
+            .create_async()
+            .await;
+
+        let crawler = PengPaiNews::new().with_hosts(server.url(), server.url());
+        let page = crawler.search_news("台风", 3).await.unwrap();
+
+        assert!(!page.has_next);
+        assert_eq!(1, page.titles.len());
+        // the highlights are removed
+        assert_eq!("台风“竹节草”登陆浙江舟山", page.titles[0].title);
+        assert_eq!(
+            Some("受台风影响，舟山航线停航。"),
+            page.titles[0].meta.summary.as_deref()
+        );
+        assert_eq!(Some(1754380800), page.titles[0].meta.published_at);
     }
 }
@@ -0,0 +1,291 @@
+use reqwest::{Client, Url};
+
+use crate::{
+    llm::{LlmClient, LlmMessage},
+    news::{
+        MaterialExtractor, NEWS_MODEL_VERSION, NewsMaterial, NewsMaterialError, NewsMaterialResult,
+        NewsPic, NewsTitle, Provenance,
+        article::{Article, ArticleImage},
+        image,
+    },
+    time::now,
+};
+
+/// bumped when the prompt changes, the materials of an older prompt are summarized again
+const PROMPT_VERSION: u32 = 2;
+
+/// Summarizes the main content of a news page with a chat model, whatever the source.
+pub struct LlmMaterialExtractor {
+    llm: Box<dyn LlmClient + Sync + Send + 'static>,
+    /// extra instructions on the style of the summary
+    style: Option<String>,
+}
+
+impl LlmMaterialExtractor {
+    /// Summarizes with the chat model `llm`.
+    pub fn new(llm: Box<dyn LlmClient + Sync + Send + 'static>) -> Self {
+        Self { llm, style: None }
+    }
+
+    /// Extra instructions on the style of the summary, added to the prompt.
+    pub fn with_style(self, style: Option<String>) -> Self {
+        Self { style, ..self }
+    }
+}
+
+#[async_trait::async_trait]
+impl MaterialExtractor for LlmMaterialExtractor {
+    fn cache_key(&self) -> String {
+        format!(
+            "{}\n{}\n{}",
+            self.llm.cache_key(),
+            PROMPT_VERSION,
+            self.style.as_deref().unwrap_or_default()
+        )
+    }
+
+    async fn get_material(&self, hot_news: &NewsTitle) -> NewsMaterialResult {
+        let to_network_err = |e: reqwest::Error| NewsMaterialError::Network(e.to_string());
+
+        let http = Client::new();
+        let response = http
+            .get(hot_news.url.clone())
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(to_network_err)?;
+        let raw_content = response.text().await.map_err(to_network_err)?;
+
+        let base = Url::parse(&hot_news.url).ok();
+        let article =
+            Article::extract(&raw_content, base.as_ref()).ok_or(NewsMaterialError::NoBody)?;
+
+        self.summarize(hot_news, &article).await
+    }
+}
+
+#[derive(Debug, serde::Deserialize, Clone)]
+struct LlmResult {
+    pub summary: Vec<String>,
+    /// the numbers of the picked images in the list, from 1
+    pub images: Vec<usize>,
+}
+
+impl LlmMaterialExtractor {
+    /// The material of `hot_news` from the main content of its page,
+    /// any page or document summarized the same way.
+    pub async fn summarize(&self, hot_news: &NewsTitle, article: &Article) -> NewsMaterialResult {
+        // the candidates are the images of the article, the LLM only picks among them
+        let images = image::with_content_size(&Client::new(), article.images.clone()).await;
+
+        let llm_result = self.ask_llm(&Self::news_content(article, &images)).await?;
+
+        let mut pics: Vec<NewsPic> = hot_news
+            .pics
+            .iter()
+            .map(|url| url.as_str().into())
+            .collect();
+        for url in Self::pick_images(&images, &llm_result.images) {
+            if pics.iter().any(|pic| pic.url == url) {
+                continue;
+            }
+            let image = images.iter().find(|image| image.url == url);
+            pics.push(NewsPic {
+                caption: image.and_then(|image| image.caption.clone()),
+                credit: image.and_then(|image| image.credit.clone()),
+                url,
+            });
+        }
+
+        Ok(NewsMaterial {
+            version: NEWS_MODEL_VERSION,
+            title: hot_news.title.clone(),
+            summary: llm_result.summary,
+            videos: hot_news.videos.clone(),
+            pics,
+            article: article.paragraphs.clone(),
+            provenance: Some(Provenance {
+                model: self.llm.model(),
+                prompt_version: PROMPT_VERSION,
+                created_at: now(),
+            }),
+        })
+    }
+
+    /// The text of the article and the numbered list of its `images` as sent to the LLM.
+    fn news_content(article: &Article, images: &[ArticleImage]) -> String {
+        let mut content = String::new();
+        if let Some(title) = &article.title {
+            content.push_str(&format!("标题：{}\n\n", title));
+        }
+        content.push_str("正文：\n");
+        for paragraph in &article.paragraphs {
+            content.push_str(paragraph);
+            content.push('\n');
+        }
+        content.push_str("\n图片：\n");
+        for (i, image) in images.iter().enumerate() {
+            let caption = image.caption.as_deref().unwrap_or("无说明");
+            content.push_str(&format!("{}. {} {}\n", i + 1, image.url, caption));
+        }
+        content
+    }
+
+    /// The urls of the images numbered in `picked`, in its order.
+    /// All the images when none of the numbers is in the list.
+    fn pick_images(images: &[ArticleImage], picked: &[usize]) -> Vec<String> {
+        let mut urls: Vec<String> = vec![];
+        for image in picked
+            .iter()
+            .filter_map(|n| n.checked_sub(1).and_then(|i| images.get(i)))
+        {
+            if !urls.contains(&image.url) {
+                urls.push(image.url.clone());
+            }
+        }
+
+        if urls.is_empty() {
+            urls = images.iter().map(|image| image.url.clone()).collect();
+        }
+        urls
+    }
+
+    async fn ask_llm(&self, news_content: &str) -> Result<LlmResult, NewsMaterialError> {
+        let messages = [
+            LlmMessage::system(self.prompt()),
+            LlmMessage::user(news_content),
+        ];
+
+        let answer = self
+            .llm
+            .chat(&messages)
+            .await
+            .map_err(|e| NewsMaterialError::Llm(e.to_string()))?;
+        let raw_json = answer
+            .trim()
+            .trim_start_matches("```json")
+            .trim_start_matches("```JSON")
+            .trim_end_matches("```")
+            .trim()
+            .to_string();
+
+        serde_json::from_str(&raw_json)
+            .map_err(|e| NewsMaterialError::Answer(format!("{}: {}", e, raw_json)))
+    }
+
+    /// The prompt with the style instructions, if any.
+    fn prompt(&self) -> String {
+        match &self.style {
+            Some(style) => format!("{}\n另外，摘要的风格要求：{}\n", Self::get_prompt(), style),
+            None => Self::get_prompt().to_owned(),
+        }
+    }
+
+    fn get_prompt() -> &'static str {
+        r#"
+你是一个爆款短视频的作者，我会给你一个新闻稿的正文和正文里的图片列表（每行是图片的序号、链接和图片说明），你要根据要求总结里面的新闻，并挑选正文的图片，具体要求为：
+1. 将新闻内容浓缩为200字内的短视频风格摘要，严格控制在200字以内，使用吸引眼球的短视频的风格夸张语气和俏皮。
+风格夸张俏皮，喜欢使用网络热词和热梗，保持事实准确，突出核心事件、关键人物和戏剧性细节，纯文字输出，禁止使用表情符号，时间地点人物等关键信息必须准确，注意中文标点符号使用规范。正文要根据逗号、句号分割，放在数组内，如：
+
+```json
+["句子1", "句子2"]
+```
+
+2. 从图片列表中挑选与新闻内容相关的图片（排除二维码、广告等），按与新闻的相关程度从高到低排列，返回图片的序号，结果以JSON数组格式返回，若列表为空则返回空数组[]。如：
+
+```json
+[3, 1]
+```
+
+上面两点要求按照 JSON 格式输出，如：
+
+{
+  "summary": ["句子1", "句子2"],
+  "images": [3, 1]
+}
+        "#
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{llm::mock::MockLlm, news::NewsMeta};
+
+    use super::*;
+
+    #[test]
+    fn pick_images() {
+        let images: Vec<ArticleImage> = ["a.jpg", "b.jpg", "c.jpg"]
+            .iter()
+            .map(|url| ArticleImage {
+                url: url.to_string(),
+                caption: None,
+                credit: None,
+                width: None,
+                height: None,
+            })
+            .collect();
+
+        assert_eq!(
+            vec!["c.jpg", "a.jpg"],
+            LlmMaterialExtractor::pick_images(&images, &[3, 1, 3])
+        );
+        // numbers out of the list are not images
+        assert_eq!(
+            vec!["b.jpg"],
+            LlmMaterialExtractor::pick_images(&images, &[0, 2, 9])
+        );
+        assert_eq!(
+            vec!["a.jpg", "b.jpg", "c.jpg"],
+            LlmMaterialExtractor::pick_images(&images, &[7])
+        );
+    }
+
+    #[tokio::test]
+    async fn summarize_with_the_llm() {
+        let llm = MockLlm::new("qwen-plus")
+            .with_answer(
+                "```json\n{\"summary\": [\"台风来了，\", \"注意安全。\"], \"images\": []}\n```",
+            )
+            .with_answer("不是 JSON");
+        let extractor =
+            LlmMaterialExtractor::new(Box::new(llm)).with_style(Some("严肃".to_owned()));
+        let hot_news = NewsTitle {
+            version: NEWS_MODEL_VERSION,
+            source: "pengpai".to_owned(),
+            title: "台风“竹节草”登陆浙江".to_owned(),
+            url: "https://www.thepaper.cn/newsDetail_forward_31200001".to_owned(),
+            pics: vec!["https://imgpai.thepaper.cn/cover.jpg".to_owned()],
+            videos: vec![],
+            meta: NewsMeta::default(),
+        };
+        let article = Article {
+            title: Some("台风“竹节草”登陆浙江".to_owned()),
+            paragraphs: vec!["台风“竹节草”今晨登陆浙江舟山。".to_owned()],
+            images: vec![],
+        };
+
+        let material = extractor.summarize(&hot_news, &article).await.unwrap();
+        assert_eq!(vec!["台风来了，", "注意安全。"], material.summary);
+        assert_eq!(
+            vec!["https://imgpai.thepaper.cn/cover.jpg"],
+            material
+                .pics
+                .iter()
+                .map(|pic| pic.url.as_str())
+                .collect::<Vec<_>>()
+        );
+        assert_eq!("qwen-plus", material.provenance.unwrap().model);
+
+        // the answer is kept in the error
+        assert!(matches!(
+            extractor.summarize(&hot_news, &article).await,
+            Err(NewsMaterialError::Answer(message)) if message.ends_with("不是 JSON")
+        ));
+        // out of answers
+        assert!(matches!(
+            extractor.summarize(&hot_news, &article).await,
+            Err(NewsMaterialError::Llm(_))
+        ));
+    }
+}
@@ -0,0 +1,136 @@
+use std::{fmt, str::FromStr};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{config::Settings, news::filter::FilterSettings};
+
+/// A named set of overrides of the top level keys, in the config file as `[PROFILES.<name>]`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct ProfileSettings {
+    pub voice: Option<String>,
+    pub tts_model: Option<String>,
+    pub llm_model: Option<String>,
+    pub llm_prompt: Option<String>,
+    pub resolution: Option<String>,
+    pub subtitle_style: Option<String>,
+    pub watermark: Option<String>,
+    /// replaces the top level `FILTER` as a whole
+    pub filter: Option<FilterSettings>,
+}
+
+/// How a video is made, the top level keys overridden by the keys of a profile.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Profile {
+    /// `None` for the top level keys alone
+    pub name: Option<String>,
+    /// the voice of the dubbing
+    pub voice: String,
+    pub tts_model: String,
+    /// the chat model summarizing the news
+    pub llm_model: String,
+    /// extra instructions on the style of the summary
+    pub llm_prompt: Option<String>,
+    pub resolution: Resolution,
+    /// the ASS style of the subtitles, e.g. `FontSize=24,PrimaryColour=&H00FFFFFF`
+    pub subtitle_style: Option<String>,
+    /// the text drawn in a corner of the video
+    pub watermark: Option<String>,
+    /// the rules the hot news lists are filtered and ranked by
+    pub filter: FilterSettings,
+}
+
+impl Profile {
+    /// The keys of `profile` over the top level keys of `settings`,
+    /// an empty prompt, subtitle style or watermark in `profile` turns it off.
+    /// The resolution has been checked by the validation of the settings.
+    pub fn resolve(settings: &Settings, name: Option<&str>, profile: &ProfileSettings) -> Self {
+        let or = |value: &Option<String>, default: &String| {
+            value.clone().unwrap_or_else(|| default.clone())
+        };
+        let or_optional = |value: &Option<String>, default: &Option<String>| {
+            value
+                .clone()
+                .or_else(|| default.clone())
+                .filter(|value| !value.is_empty())
+        };
+        let resolution = profile
+            .resolution
+            .as_deref()
+            .unwrap_or(&settings.resolution);
+
+        Self {
+            name: name.map(str::to_owned),
+            voice: or(&profile.voice, &settings.voice),
+            tts_model: or(&profile.tts_model, &settings.tts_model),
+            llm_model: or(&profile.llm_model, &settings.llm_model),
+            llm_prompt: or_optional(&profile.llm_prompt, &settings.llm_prompt),
+            resolution: resolution.parse().unwrap_or_default(),
+            subtitle_style: or_optional(&profile.subtitle_style, &settings.subtitle_style),
+            watermark: or_optional(&profile.watermark, &settings.watermark),
+            filter: profile
+                .filter
+                .clone()
+                .unwrap_or_else(|| settings.filter.clone()),
+        }
+    }
+}
+
+/// The size of the video, `720x1280` in the config.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Resolution {
+    pub width: u32,
+    pub height: u32,
+}
+
+impl Default for Resolution {
+    fn default() -> Self {
+        Self {
+            width: 720,
+            height: 1280,
+        }
+    }
+}
+
+impl FromStr for Resolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (width, height) = s
+            .split_once('x')
+            .and_then(|(w, h)| Some((w.trim().parse().ok()?, h.trim().parse().ok()?)))
+            .ok_or("is not like `720x1280`")?;
+
+        // libx264 only encodes even sizes
+        if width == 0 || height == 0 || width % 2 != 0 || height % 2 != 0 {
+            return Err("must be even and not zero".to_owned());
+        }
+
+        Ok(Self { width, height })
+    }
+}
+
+impl fmt::Display for Resolution {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}x{}", self.width, self.height)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_resolution() {
+        assert_eq!(
+            Ok(Resolution {
+                width: 1080,
+                height: 1920
+            }),
+            "1080x1920".parse()
+        );
+        assert!("1080".parse::<Resolution>().is_err());
+        assert!("721x1280".parse::<Resolution>().is_err());
+        assert_eq!("720x1280", Resolution::default().to_string());
+    }
+}
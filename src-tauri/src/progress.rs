@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Emitter};
+
+use crate::job::JobId;
+
+/// The Tauri event the progress is emitted as, see `TauriReporter`
+pub const PROGRESS_EVENT: &str = "director-progress";
+
+/// The stages of generating one short video, in order.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Stage {
+    /// fetch the news and summarize it
+    Material,
+    /// text to speech, then the voice effect
+    Dubbing,
+    Subtitle,
+    /// download the pictures and render the slideshow
+    Video,
+    /// put video, dubbing and subtitle together
+    Compose,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+pub enum ProgressEvent {
+    StageStarted {
+        stage: Stage,
+    },
+    StageFinished {
+        stage: Stage,
+    },
+    /// the stage was skipped, its artifacts from a previous run are reused
+    StageCached {
+        stage: Stage,
+    },
+    /// sentence `index` (from 1) of `total` has been synthesized
+    SentenceSynthesized {
+        index: usize,
+        total: usize,
+    },
+    /// image `index` (from 1) of `total` has been downloaded
+    ImageDownloaded {
+        index: usize,
+        total: usize,
+    },
+    /// a progress line of the running ffmpeg, `time` in seconds
+    #[serde(rename = "ffmpeg")]
+    FFmpeg {
+        frame: Option<u64>,
+        time: Option<f64>,
+    },
+}
+
+/// Receives the progress of the pipeline, must be cheap, it is called inline.
+pub trait ProgressReporter: Send + Sync {
+    fn report(&self, event: ProgressEvent);
+}
+
+/// Drops every event.
+pub struct NoopReporter;
+
+impl ProgressReporter for NoopReporter {
+    fn report(&self, _: ProgressEvent) {}
+}
+
+impl<F> ProgressReporter for F
+where
+    F: Fn(ProgressEvent) + Send + Sync,
+{
+    fn report(&self, event: ProgressEvent) {
+        self(event)
+    }
+}
+
+#[derive(Clone, Serialize)]
+struct ProgressPayload<'a> {
+    /// the job generating the news, it can be cancelled with this id
+    job: &'a str,
+    /// the url of the news being generated
+    url: &'a str,
+    event: ProgressEvent,
+}
+
+/// Emits the progress of one job as `PROGRESS_EVENT` app events.
+pub struct TauriReporter {
+    app: AppHandle,
+    job: JobId,
+    url: String,
+}
+
+impl TauriReporter {
+    pub fn new(app: AppHandle, job: JobId, url: impl Into<String>) -> Self {
+        Self {
+            app,
+            job,
+            url: url.into(),
+        }
+    }
+}
+
+impl ProgressReporter for TauriReporter {
+    fn report(&self, event: ProgressEvent) {
+        _ = self.app.emit(
+            PROGRESS_EVENT,
+            ProgressPayload {
+                job: &self.job,
+                url: &self.url,
+                event,
+            },
+        );
+    }
+}
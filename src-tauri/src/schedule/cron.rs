@@ -0,0 +1,203 @@
+use std::str::FromStr;
+
+use chrono::{Datelike, NaiveDateTime, TimeDelta, Timelike};
+
+/// How far `CronSchedule::next_after` looks, a `0 0 29 2 *` is due within 8 years
+const LOOKAHEAD_DAYS: i64 = 366 * 8;
+
+/// A cron expression, `minute hour day-of-month month day-of-week`,
+/// or one of `@hourly`, `@daily` and `@weekly`.
+/// A field is `*`, a number, a range `1-5`, a list `1,15` or a step `*/15`, `0-30/10`.
+/// The days of the week are 0 to 6 from Sunday, 7 is Sunday too.
+/// Like cron, when both days are restricted a time matching either of them is due.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    /// the day of the month is `*`
+    any_day: bool,
+    /// the day of the week is `*`
+    any_weekday: bool,
+}
+
+impl CronSchedule {
+    /// The first minute after `after` the expression matches, in the same local time.
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let limit = after + TimeDelta::days(LOOKAHEAD_DAYS);
+        let mut time =
+            after.date().and_hms_opt(after.hour(), after.minute(), 0)? + TimeDelta::minutes(1);
+
+        while time <= limit {
+            if !has(self.months, time.month()) || !self.day_matches(time) {
+                time = time.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+            } else if !has(self.hours, time.hour()) {
+                time = time.date().and_hms_opt(time.hour(), 0, 0)? + TimeDelta::hours(1);
+            } else if !has(self.minutes, time.minute()) {
+                time += TimeDelta::minutes(1);
+            } else {
+                return Some(time);
+            }
+        }
+
+        None
+    }
+
+    fn day_matches(&self, time: NaiveDateTime) -> bool {
+        let day = has(self.days, time.day());
+        let weekday = has(self.weekdays, time.weekday().num_days_from_sunday());
+
+        if self.any_day || self.any_weekday {
+            day && weekday
+        } else {
+            day || weekday
+        }
+    }
+}
+
+impl FromStr for CronSchedule {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let expression = match s.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            expression => expression,
+        };
+
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        let [minutes, hours, days, months, weekdays] = fields[..] else {
+            return Err(format!("`{}` does not have 5 fields", s));
+        };
+
+        // Sunday is 0 or 7
+        let mut weekday_bits = field(weekdays, 0, 7)?;
+        if has(weekday_bits, 7) {
+            weekday_bits = (weekday_bits | 1) & !(1 << 7);
+        }
+
+        Ok(Self {
+            minutes: field(minutes, 0, 59)?,
+            hours: field(hours, 0, 23)?,
+            days: field(days, 1, 31)?,
+            months: field(months, 1, 12)?,
+            weekdays: weekday_bits,
+            any_day: days.starts_with('*'),
+            any_weekday: weekdays.starts_with('*'),
+        })
+    }
+}
+
+fn has(bits: u64, value: u32) -> bool {
+    bits & (1 << value) != 0
+}
+
+/// The bits of the values `field` matches, from `min` to `max`.
+fn field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let number = |s: &str| {
+        s.parse::<u32>()
+            .ok()
+            .filter(|n| (min..=max).contains(n))
+            .ok_or_else(|| format!("`{}` is not a number from {} to {}", s, min, max))
+    };
+
+    let mut bits = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => match step.parse::<u32>() {
+                Ok(step) if step > 0 => (range, Some(step)),
+                _ => return Err(format!("`{}` has an invalid step", part)),
+            },
+            None => (part, None),
+        };
+
+        let (from, to) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((from, to)) => (number(from)?, number(to)?),
+            // `5/10` is from 5 to the end
+            None if step.is_some() => (number(range)?, max),
+            None => {
+                let n = number(range)?;
+                (n, n)
+            }
+        };
+        if from > to {
+            return Err(format!("`{}` is an empty range", range));
+        }
+
+        for value in (from..=to).step_by(step.unwrap_or(1) as usize) {
+            bits |= 1 << value;
+        }
+    }
+
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn next(expression: &str, after: &str) -> NaiveDateTime {
+        expression
+            .parse::<CronSchedule>()
+            .unwrap()
+            .next_after(at(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn next_times() {
+        assert_eq!(
+            at("2026-10-17 08:15"),
+            next("*/15 8-18 * * *", "2026-10-17 08:00")
+        );
+        assert_eq!(
+            at("2026-10-18 08:00"),
+            next("*/15 8-18 * * *", "2026-10-17 18:45")
+        );
+        assert_eq!(at("2026-10-18 00:00"), next("@daily", "2026-10-17 12:34"));
+        // the 19th is a Monday
+        assert_eq!(
+            at("2026-10-19 09:30"),
+            next("30 9 * * 1-5", "2026-10-17 09:30")
+        );
+        // Sunday as 7
+        assert_eq!(
+            at("2026-10-18 07:00"),
+            next("0 7 * * 7", "2026-10-17 07:00")
+        );
+        // either day when both are restricted
+        assert_eq!(
+            at("2026-10-18 00:00"),
+            next("0 0 1 * 0", "2026-10-17 00:00")
+        );
+        assert_eq!(
+            at("2028-02-29 00:00"),
+            next("0 0 29 2 *", "2026-10-17 00:00")
+        );
+    }
+
+    #[test]
+    fn invalid_expressions() {
+        for expression in [
+            "* * * *",
+            "60 * * * *",
+            "5-1 * * * *",
+            "*/0 * * * *",
+            "a * * * *",
+        ] {
+            assert!(
+                expression.parse::<CronSchedule>().is_err(),
+                "{} should be invalid",
+                expression
+            );
+        }
+    }
+}
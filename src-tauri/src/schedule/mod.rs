@@ -0,0 +1,654 @@
+pub mod cron;
+
+use std::{
+    fmt, fs, io,
+    path::PathBuf,
+    str::FromStr,
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+use chrono::{DateTime, Local, NaiveDateTime, NaiveTime, TimeDelta, TimeZone};
+use serde::{Deserialize, Serialize};
+use tauri::{AppHandle, Manager};
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    config::SharedConfig,
+    director::{Director, DirectorResult, RankedNews, SourceFailure, source::SourceName},
+    job::queue::JobQueue,
+    library::Library,
+    schedule::cron::CronSchedule,
+    time::now,
+};
+
+/// The file in the app data dir the state of the scheduler is persisted to
+pub const SCHEDULE_FILE: &str = "schedule.json";
+
+/// A run started this many minutes after it was due has been missed, the app was closed or asleep
+const MISSED_AFTER_MINUTES: i64 = 5;
+/// The longest the scheduler sleeps before it looks at the clock and the settings again
+const TICK: Duration = Duration::from_secs(60);
+
+/// The unattended generation, in the config as `[SCHEDULE]`.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE", default)]
+pub struct ScheduleSettings {
+    pub enabled: bool,
+    /// when to run, cron expressions in local time, e.g. `0 8,20 * * *`
+    pub cron: Vec<String>,
+    /// the sources the news are picked from, `SOURCES` when empty
+    pub sources: Vec<SourceName>,
+    /// the config profile picking and rendering the news, the default one when unset
+    pub profile: Option<String>,
+    /// how many news are rendered per run at most
+    pub count: usize,
+    /// no run starts within this window, `23:00-07:00`, the runs due are put off to its end
+    pub quiet_hours: Option<String>,
+    /// what to do with the runs that were due while the app was closed
+    pub missed: MissedRuns,
+}
+
+impl Default for ScheduleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cron: vec![],
+            sources: vec![],
+            profile: None,
+            count: 3,
+            quiet_hours: None,
+            missed: MissedRuns::default(),
+        }
+    }
+}
+
+impl ScheduleSettings {
+    /// The keys that are set but wrong with why, `QUIET_HOURS` for `quiet_hours`.
+    pub fn invalid(&self) -> Vec<(&'static str, String)> {
+        let mut errors = vec![];
+
+        for cron in &self.cron {
+            if let Err(e) = cron.parse::<CronSchedule>() {
+                errors.push(("CRON", e));
+            }
+        }
+        if self.enabled && self.cron.is_empty() {
+            errors.push(("CRON", "is empty".to_owned()));
+        }
+        if self.count == 0 {
+            errors.push(("COUNT", "must be at least 1".to_owned()));
+        }
+        if let Some(Err(e)) = self.quiet_hours.as_deref().map(str::parse::<QuietHours>) {
+            errors.push(("QUIET_HOURS", e));
+        }
+
+        errors
+    }
+}
+
+/// What to do with the runs that were due while the app was closed.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MissedRuns {
+    /// run once for all of them as soon as possible
+    #[default]
+    RunOnce,
+    /// wait for the next one
+    Skip,
+}
+
+/// A daily window, it may span midnight.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct QuietHours {
+    pub start: NaiveTime,
+    pub end: NaiveTime,
+}
+
+impl QuietHours {
+    pub fn contains(&self, time: NaiveTime) -> bool {
+        if self.start < self.end {
+            self.start <= time && time < self.end
+        } else {
+            self.start <= time || time < self.end
+        }
+    }
+
+    /// `time`, or the end of the window when `time` is within it.
+    pub fn put_off(&self, time: NaiveDateTime) -> NaiveDateTime {
+        if !self.contains(time.time()) {
+            return time;
+        }
+
+        let end = time.date().and_time(self.end);
+        if end > time {
+            end
+        } else {
+            end + TimeDelta::days(1)
+        }
+    }
+}
+
+impl FromStr for QuietHours {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let time = |s: &str| NaiveTime::parse_from_str(s.trim(), "%H:%M").ok();
+        let (start, end) = s
+            .split_once('-')
+            .and_then(|(start, end)| Some((time(start)?, time(end)?)))
+            .ok_or("is not like `23:00-07:00`")?;
+
+        if start == end {
+            return Err("starts and ends at the same time".to_owned());
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for QuietHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}-{}",
+            self.start.format("%H:%M"),
+            self.end.format("%H:%M")
+        )
+    }
+}
+
+/// What the scheduler does next.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Plan {
+    Run,
+    /// a run was missed and `MissedRuns::Skip` says to leave it
+    Skip,
+    Wait(NaiveDateTime),
+    /// no time matches the cron expressions
+    Never,
+}
+
+/// The times of the runs, in local time.
+#[derive(Clone, Debug)]
+pub struct Schedule {
+    crons: Vec<CronSchedule>,
+    quiet_hours: Option<QuietHours>,
+    missed: MissedRuns,
+}
+
+impl Schedule {
+    /// The schedule of `settings`, they have been checked by the validation of the config.
+    pub fn new(settings: &ScheduleSettings) -> Result<Self, String> {
+        Ok(Self {
+            crons: settings
+                .cron
+                .iter()
+                .map(|cron| cron.parse::<CronSchedule>())
+                .collect::<Result<_, _>>()?,
+            quiet_hours: settings
+                .quiet_hours
+                .as_deref()
+                .map(str::parse::<QuietHours>)
+                .transpose()?,
+            missed: settings.missed,
+        })
+    }
+
+    /// The first run after `after`, out of the quiet hours.
+    pub fn next_run(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let next = self
+            .crons
+            .iter()
+            .filter_map(|cron| cron.next_after(after))
+            .min()?;
+
+        Some(match &self.quiet_hours {
+            Some(quiet_hours) => quiet_hours.put_off(next),
+            None => next,
+        })
+    }
+
+    /// What to do at `now`, the runs up to `since` having been handled.
+    pub fn plan(&self, since: NaiveDateTime, now: NaiveDateTime) -> Plan {
+        let Some(due) = self.next_run(since) else {
+            return Plan::Never;
+        };
+
+        if due > now {
+            Plan::Wait(due)
+        } else if now - due < TimeDelta::minutes(MISSED_AFTER_MINUTES)
+            || self.missed == MissedRuns::RunOnce
+        {
+            Plan::Run
+        } else {
+            Plan::Skip
+        }
+    }
+}
+
+/// A run of the scheduler.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledRunRecord {
+    /// unix seconds
+    pub started_at: u64,
+    /// unix seconds
+    pub finished_at: u64,
+    /// the urls of the news picked
+    pub picked: Vec<String>,
+    /// the sources left out because they failed
+    #[serde(default)]
+    pub failures: Vec<SourceFailure>,
+    pub error: Option<String>,
+}
+
+/// What the scheduler has done, kept across restarts.
+#[derive(Clone, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleState {
+    /// the runs due up to then have been handled, unix seconds.
+    /// `None` until the schedule is first enabled.
+    pub handled_until: Option<u64>,
+    pub last_run: Option<ScheduledRunRecord>,
+}
+
+/// The scheduler as shown to the UI.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduleStatus {
+    pub enabled: bool,
+    /// unix seconds, `None` when disabled or when no time matches
+    pub next_run_at: Option<u64>,
+    #[serde(flatten)]
+    pub state: ScheduleState,
+}
+
+/// Generates the news of the scheduled runs.
+#[async_trait::async_trait]
+pub trait ScheduledRun: Send + Sync {
+    /// The settings of the schedule now, they may change between two runs.
+    async fn settings(&self) -> ScheduleSettings;
+
+    /// Pick the new news and generate their videos,
+    /// returns the news picked and the sources that failed.
+    async fn run(&self, settings: &ScheduleSettings) -> DirectorResult<RankedNews>;
+}
+
+/// Runs `ScheduledRun` at the times of the schedule, the state saved to a JSON file.
+pub struct Scheduler {
+    path: PathBuf,
+    state: Mutex<ScheduleState>,
+}
+
+impl Scheduler {
+    /// Load the state saved at `path`, an absent file is a scheduler that never ran.
+    pub fn load(path: PathBuf) -> io::Result<Self> {
+        let state = match fs::read_to_string(&path) {
+            Ok(content) => serde_json::from_str(&content)?,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => ScheduleState::default(),
+            Err(e) => return Err(e),
+        };
+
+        Ok(Self {
+            path,
+            state: Mutex::new(state),
+        })
+    }
+
+    pub fn state(&self) -> ScheduleState {
+        self.state.lock().unwrap().clone()
+    }
+
+    /// The state and the next run with `settings`.
+    pub fn status(&self, settings: &ScheduleSettings) -> ScheduleStatus {
+        let state = self.state();
+        let next_run_at = Schedule::new(settings)
+            .ok()
+            .filter(|_| settings.enabled)
+            .and_then(|schedule| {
+                let current = local(now())?;
+                let since = state.handled_until.and_then(local).unwrap_or(current);
+                schedule.next_run(since).map(|due| due.max(current))
+            })
+            .and_then(timestamp);
+
+        ScheduleStatus {
+            enabled: settings.enabled,
+            next_run_at,
+            state,
+        }
+    }
+
+    /// Run `target` when it is due until `cancel` is cancelled.
+    pub async fn run(&self, target: &dyn ScheduledRun, cancel: &CancellationToken) {
+        while !cancel.is_cancelled() {
+            let wait = self.tick(target).await;
+
+            if cancel
+                .run_until_cancelled(tokio::time::sleep(wait))
+                .await
+                .is_none()
+            {
+                break;
+            }
+        }
+    }
+
+    /// Run `target` if it is due, returns how long to wait before the next tick.
+    async fn tick(&self, target: &dyn ScheduledRun) -> Duration {
+        let settings = target.settings().await;
+        if !settings.enabled {
+            // the runs while disabled are not missed ones
+            self.update(|state| state.handled_until = None);
+            return TICK;
+        }
+
+        let schedule = match Schedule::new(&settings) {
+            Ok(schedule) => schedule,
+            Err(e) => {
+                eprintln!("invalid schedule: {}", e);
+                return TICK;
+            }
+        };
+
+        let started_at = now();
+        let Some(current) = local(started_at) else {
+            return TICK;
+        };
+        let Some(since) = self.state().handled_until.and_then(local) else {
+            self.update(|state| state.handled_until = Some(started_at));
+            return Duration::ZERO;
+        };
+
+        match schedule.plan(since, current) {
+            Plan::Run => {
+                self.update(|state| state.handled_until = Some(started_at));
+                let res = target.run(&settings).await;
+                let record = match res {
+                    Ok(ranked) => ScheduledRunRecord {
+                        started_at,
+                        finished_at: now(),
+                        picked: ranked.titles.iter().map(|n| n.url.clone()).collect(),
+                        failures: ranked.failures,
+                        error: None,
+                    },
+                    Err(e) => {
+                        eprintln!("scheduled run failed: {}", e);
+                        ScheduledRunRecord {
+                            started_at,
+                            finished_at: now(),
+                            picked: vec![],
+                            failures: vec![],
+                            error: Some(e.to_string()),
+                        }
+                    }
+                };
+                self.update(|state| state.last_run = Some(record));
+                Duration::ZERO
+            }
+            Plan::Skip => {
+                eprintln!("skipped the scheduled run missed since {}", since);
+                self.update(|state| state.handled_until = Some(started_at));
+                Duration::ZERO
+            }
+            Plan::Wait(due) => (due - current).to_std().unwrap_or_default().min(TICK),
+            Plan::Never => TICK,
+        }
+    }
+
+    /// Change the state and save it, when it changed.
+    fn update(&self, change: impl FnOnce(&mut ScheduleState)) {
+        let mut state = self.state.lock().unwrap();
+        let before = state.clone();
+        change(&mut state);
+        if *state != before {
+            self.save(&state);
+        }
+    }
+
+    fn save(&self, state: &ScheduleState) {
+        let res = self
+            .path
+            .parent()
+            .map_or(Ok(()), fs::create_dir_all)
+            .and_then(|_| fs::write(&self.path, serde_json::to_vec_pretty(state)?));
+
+        if let Err(e) = res {
+            eprintln!("failed to save schedule to {}: {}", self.path.display(), e);
+        }
+    }
+}
+
+/// The news a run renders: the hot news of the sources of `settings`, else of `sources`
+/// (the `SOURCES` of the config), filtered and ranked by the filter of its profile,
+/// without the news in the library, at most `COUNT` of them.
+/// The sources that failed are left out, it fails when they all fail.
+pub async fn pick(
+    director: &Director,
+    sources: &[SourceName],
+    settings: &ScheduleSettings,
+    rendered: &dyn Fn(&str) -> bool,
+) -> DirectorResult<RankedNews> {
+    let sources = if settings.sources.is_empty() {
+        sources
+    } else {
+        &settings.sources
+    };
+
+    let ranked = director.get_ranked_news(sources, true, rendered).await?;
+
+    Ok(RankedNews {
+        titles: ranked
+            .titles
+            .into_iter()
+            .filter(|news_title| !rendered(&news_title.url))
+            .take(settings.count)
+            .collect(),
+        failures: ranked.failures,
+    })
+}
+
+/// The local time of `secs` unix seconds.
+fn local(secs: u64) -> Option<NaiveDateTime> {
+    let time = DateTime::from_timestamp(secs as i64, 0)?;
+    Some(time.with_timezone(&Local).naive_local())
+}
+
+/// The unix seconds of the local `time`, `None` when the clocks skip it.
+fn timestamp(time: NaiveDateTime) -> Option<u64> {
+    let time = Local.from_local_datetime(&time).earliest()?;
+    u64::try_from(time.timestamp()).ok()
+}
+
+/// Queues the news of the runs in the `JobQueue` of the app, the videos go to the library.
+pub struct QueueRun {
+    app: AppHandle,
+}
+
+impl QueueRun {
+    pub fn new(app: AppHandle) -> Self {
+        Self { app }
+    }
+}
+
+#[async_trait::async_trait]
+impl ScheduledRun for QueueRun {
+    async fn settings(&self) -> ScheduleSettings {
+        let config = self.app.state::<SharedConfig>().get();
+        config.settings().schedule.clone()
+    }
+
+    async fn run(&self, settings: &ScheduleSettings) -> DirectorResult<RankedNews> {
+        let library = self.app.state::<Arc<Library>>();
+        let rendered = |url: &str| library.find_by_url(url).is_some();
+
+        let config = self.app.state::<SharedConfig>().get();
+        let director = crate::new_director(&config, settings.profile.as_deref(), self.app.clone())?;
+        let picked = pick(&director, &config.settings().sources, settings, &rendered).await?;
+
+        // the queue emits the events of the jobs
+        let queue = self.app.state::<Arc<JobQueue>>();
+        queue.enqueue_with_profile(picked.titles.clone(), settings.profile.clone(), false);
+
+        Ok(picked)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use crate::news::{NEWS_MODEL_VERSION, NewsTitle};
+
+    use super::*;
+
+    fn at(s: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(s, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    fn schedule(quiet_hours: Option<&str>, missed: MissedRuns) -> Schedule {
+        Schedule::new(&ScheduleSettings {
+            enabled: true,
+            cron: vec!["0 8,23 * * *".to_owned()],
+            quiet_hours: quiet_hours.map(str::to_owned),
+            missed,
+            ..Default::default()
+        })
+        .unwrap()
+    }
+
+    #[test]
+    fn quiet_hours() {
+        let quiet: QuietHours = "22:30-07:15".parse().unwrap();
+        assert_eq!("22:30-07:15", quiet.to_string());
+        assert!(quiet.contains(NaiveTime::from_hms_opt(23, 0, 0).unwrap()));
+        assert!(!quiet.contains(NaiveTime::from_hms_opt(12, 0, 0).unwrap()));
+        assert_eq!(
+            at("2026-10-18 07:15"),
+            quiet.put_off(at("2026-10-17 23:00"))
+        );
+        assert_eq!(
+            at("2026-10-17 07:15"),
+            quiet.put_off(at("2026-10-17 01:00"))
+        );
+        assert_eq!(
+            at("2026-10-17 12:00"),
+            quiet.put_off(at("2026-10-17 12:00"))
+        );
+
+        assert!("7-8".parse::<QuietHours>().is_err());
+        assert!("07:00-07:00".parse::<QuietHours>().is_err());
+    }
+
+    #[test]
+    fn plans() {
+        let plain = schedule(None, MissedRuns::RunOnce);
+        assert_eq!(
+            Plan::Wait(at("2026-10-17 08:00")),
+            plain.plan(at("2026-10-17 07:00"), at("2026-10-17 07:30"))
+        );
+        assert_eq!(
+            Plan::Run,
+            plain.plan(at("2026-10-17 07:00"), at("2026-10-17 08:00"))
+        );
+        // missed while the app was closed
+        assert_eq!(
+            Plan::Run,
+            plain.plan(at("2026-10-15 09:00"), at("2026-10-17 12:00"))
+        );
+
+        let skip = schedule(None, MissedRuns::Skip);
+        assert_eq!(
+            Plan::Skip,
+            skip.plan(at("2026-10-15 09:00"), at("2026-10-17 12:00"))
+        );
+        assert_eq!(
+            Plan::Run,
+            skip.plan(at("2026-10-17 07:00"), at("2026-10-17 08:01"))
+        );
+
+        // the 23:00 run is put off to the end of the quiet hours
+        let quiet = schedule(Some("22:00-06:00"), MissedRuns::RunOnce);
+        assert_eq!(
+            Plan::Wait(at("2026-10-18 06:00")),
+            quiet.plan(at("2026-10-17 09:00"), at("2026-10-17 22:30"))
+        );
+    }
+
+    #[test]
+    fn invalid_settings() {
+        let settings = ScheduleSettings {
+            enabled: true,
+            cron: vec!["0 25 * * *".to_owned()],
+            count: 0,
+            quiet_hours: Some("late".to_owned()),
+            ..Default::default()
+        };
+
+        let keys: Vec<&str> = settings.invalid().into_iter().map(|(k, _)| k).collect();
+        assert_eq!(vec!["CRON", "COUNT", "QUIET_HOURS"], keys);
+    }
+
+    /// Counts the runs, the settings fixed.
+    struct CountRun {
+        settings: ScheduleSettings,
+        runs: Mutex<usize>,
+    }
+
+    #[async_trait::async_trait]
+    impl ScheduledRun for CountRun {
+        async fn settings(&self) -> ScheduleSettings {
+            self.settings.clone()
+        }
+
+        async fn run(&self, _: &ScheduleSettings) -> DirectorResult<RankedNews> {
+            *self.runs.lock().unwrap() += 1;
+            Ok(RankedNews {
+                titles: vec![NewsTitle {
+                    version: NEWS_MODEL_VERSION,
+                    source: "fake".to_owned(),
+                    title: "TITLE".to_owned(),
+                    url: "https://example.com/1".to_owned(),
+                    pics: vec![],
+                    videos: vec![],
+                    meta: Default::default(),
+                }],
+                failures: vec![],
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn missed_run_persists() {
+        let dir = TempDir::new("schedule").unwrap();
+        let path = dir.path().join(SCHEDULE_FILE);
+        let target = CountRun {
+            settings: ScheduleSettings {
+                enabled: true,
+                cron: vec!["* * * * *".to_owned()],
+                ..Default::default()
+            },
+            runs: Mutex::new(0),
+        };
+
+        // the first tick only starts the schedule
+        let scheduler = Scheduler::load(path.clone()).unwrap();
+        assert_eq!(Duration::ZERO, scheduler.tick(&target).await);
+        assert_eq!(0, *target.runs.lock().unwrap());
+
+        // as if the app had been closed for an hour
+        let hour_ago = now() - 3600;
+        scheduler.update(|state| state.handled_until = Some(hour_ago));
+
+        let scheduler = Scheduler::load(path.clone()).unwrap();
+        scheduler.tick(&target).await;
+        assert_eq!(1, *target.runs.lock().unwrap());
+
+        let state = Scheduler::load(path).unwrap().state();
+        assert!(state.handled_until.unwrap() > hour_ago);
+        let last_run = state.last_run.unwrap();
+        assert_eq!(vec!["https://example.com/1".to_owned()], last_run.picked);
+        assert_eq!(None, last_run.error);
+    }
+}
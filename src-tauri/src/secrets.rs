@@ -0,0 +1,421 @@
+use std::{
+    collections::BTreeMap,
+    io::{self, Write},
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use base64::{Engine, prelude::BASE64_STANDARD};
+use ring::{
+    aead::{Aad, CHACHA20_POLY1305, LessSafeKey, NONCE_LEN, Nonce, UnboundKey},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::DocumentMut;
+
+/// The config keys kept in the secret store instead of the config file
+pub const SECRET_KEYS: [&str; 2] = ["OPENAI_KEY", "ALI_DASHSCOPE_API_KEY"];
+/// The encrypted vault in the app config dir
+pub const VAULT_FILE: &str = "secrets.vault";
+/// The passphrase of the vault, for headless use
+pub const VAULT_PASSPHRASE_ENV: &str = "REPORTER_VAULT_PASSPHRASE";
+/// The Secret Service attribute the keys are stored under
+const KEYRING_SERVICE: &str = "com.aiursoft.reporter";
+
+#[derive(Error, Debug)]
+pub enum SecretError {
+    #[error("keyring error: {0}")]
+    Keyring(String),
+    #[error("the passphrase is wrong or the vault is damaged")]
+    WrongPassphrase,
+    #[error("vault error: {0}")]
+    Vault(String),
+    #[error("IO error: {0}")]
+    IO(#[from] io::Error),
+}
+
+/// Where the API keys are kept, by config key.
+pub trait SecretStore {
+    /// `keyring` or `vault`, shown as the source of the keys
+    fn name(&self) -> &'static str;
+
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError>;
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError>;
+
+    fn delete(&self, key: &str) -> Result<(), SecretError>;
+}
+
+pub type SharedSecretStore = Arc<dyn SecretStore + Sync + Send + 'static>;
+
+/// The keyring when there is one, else the vault in `dir` when `REPORTER_VAULT_PASSPHRASE` is set.
+/// `None` leaves the keys to the config file and the env vars.
+pub fn open_store(dir: &Path) -> Result<Option<SharedSecretStore>, SecretError> {
+    if KeyringStore::is_available() {
+        return Ok(Some(Arc::new(KeyringStore)));
+    }
+
+    match std::env::var(VAULT_PASSPHRASE_ENV) {
+        Ok(passphrase) => Ok(Some(Arc::new(VaultStore::open(
+            dir.join(VAULT_FILE),
+            &passphrase,
+        )?))),
+        Err(_) => Ok(None),
+    }
+}
+
+/// Move the keys set in the plaintext config `file` into `store`,
+/// returns the keys moved.
+pub fn migrate(file: &Path, store: &dyn SecretStore) -> Result<Vec<&'static str>, SecretError> {
+    let content = match std::fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e.into()),
+    };
+    let mut document: DocumentMut = content
+        .parse()
+        .map_err(|e: toml_edit::TomlError| SecretError::Vault(e.to_string()))?;
+
+    let mut migrated = vec![];
+    for key in SECRET_KEYS {
+        let Some(value) = document.get(key).and_then(|v| v.as_str()) else {
+            continue;
+        };
+        if !value.is_empty() {
+            store.set(key, value)?;
+            migrated.push(key);
+        }
+        document.remove(key);
+    }
+
+    if document.to_string() != content {
+        std::fs::write(file, document.to_string())?;
+    }
+
+    Ok(migrated)
+}
+
+/// The Secret Service of the desktop (GNOME Keyring, KWallet), through `secret-tool`.
+pub struct KeyringStore;
+
+impl KeyringStore {
+    /// Whether `secret-tool` runs and a Secret Service answers.
+    pub fn is_available() -> bool {
+        if !cfg!(target_os = "linux") || std::env::var_os("DBUS_SESSION_BUS_ADDRESS").is_none() {
+            return false;
+        }
+
+        // a lookup of an absent key exits with 1 and says nothing
+        Self::secret_tool(&["lookup", "service", KEYRING_SERVICE, "key", "probe"])
+            .is_ok_and(|output| output.stderr.is_empty())
+    }
+
+    fn secret_tool(args: &[&str]) -> io::Result<std::process::Output> {
+        Command::new("secret-tool")
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+    }
+
+    fn failed(output: &std::process::Output) -> SecretError {
+        SecretError::Keyring(String::from_utf8_lossy(&output.stderr).trim().to_owned())
+    }
+}
+
+impl SecretStore for KeyringStore {
+    fn name(&self) -> &'static str {
+        "keyring"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        let output = Self::secret_tool(&["lookup", "service", KEYRING_SERVICE, "key", key])?;
+
+        if output.status.success() {
+            Ok(Some(String::from_utf8_lossy(&output.stdout).into_owned()))
+        } else if output.stderr.is_empty() {
+            Ok(None)
+        } else {
+            Err(Self::failed(&output))
+        }
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        let label = format!("reporter {}", key);
+        let mut child = Command::new("secret-tool")
+            .args([
+                "store",
+                "--label",
+                &label,
+                "service",
+                KEYRING_SERVICE,
+                "key",
+                key,
+            ])
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        // the secret goes through stdin, never the command line
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(value.as_bytes())?;
+        }
+
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            return Err(Self::failed(&output));
+        }
+
+        Ok(())
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        let output = Self::secret_tool(&["clear", "service", KEYRING_SERVICE, "key", key])?;
+        if !output.status.success() && !output.stderr.is_empty() {
+            return Err(Self::failed(&output));
+        }
+
+        Ok(())
+    }
+}
+
+/// PBKDF2 rounds deriving the key of a new vault
+const ITERATIONS: u32 = 600_000;
+const SALT_LEN: usize = 16;
+
+/// The vault as saved, the keys encrypted as one JSON object.
+#[derive(Serialize, Deserialize)]
+struct VaultFile {
+    version: u32,
+    iterations: u32,
+    salt: String,
+    nonce: String,
+    ciphertext: String,
+}
+
+/// A file encrypted with ChaCha20-Poly1305, with a key derived from a passphrase by PBKDF2.
+/// The keys are decrypted once when opened.
+pub struct VaultStore {
+    path: PathBuf,
+    key: LessSafeKey,
+    salt: [u8; SALT_LEN],
+    iterations: u32,
+    values: Mutex<BTreeMap<String, String>>,
+}
+
+impl VaultStore {
+    /// Open the vault at `path`, a new one is created on the first `set`.
+    pub fn open(path: impl Into<PathBuf>, passphrase: &str) -> Result<Self, SecretError> {
+        Self::open_with(path.into(), passphrase, ITERATIONS)
+    }
+
+    /// `iterations` only applies to a new vault.
+    fn open_with(path: PathBuf, passphrase: &str, iterations: u32) -> Result<Self, SecretError> {
+        let file: Option<VaultFile> = match std::fs::read(&path) {
+            Ok(bytes) => Some(
+                serde_json::from_slice(&bytes).map_err(|e| SecretError::Vault(e.to_string()))?,
+            ),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+            Err(e) => return Err(e.into()),
+        };
+
+        let Some(file) = file else {
+            let mut salt = [0u8; SALT_LEN];
+            fill_random(&mut salt)?;
+            return Ok(Self {
+                key: derive_key(passphrase, &salt, iterations)?,
+                path,
+                salt,
+                iterations,
+                values: Mutex::new(BTreeMap::new()),
+            });
+        };
+
+        let salt: [u8; SALT_LEN] = decode(&file.salt)?
+            .try_into()
+            .map_err(|_| SecretError::Vault("bad salt".to_owned()))?;
+        let nonce = Nonce::try_assume_unique_for_key(&decode(&file.nonce)?)
+            .map_err(|_| SecretError::Vault("bad nonce".to_owned()))?;
+        let key = derive_key(passphrase, &salt, file.iterations)?;
+
+        let mut in_out = decode(&file.ciphertext)?;
+        let plain = key
+            .open_in_place(nonce, Aad::empty(), &mut in_out)
+            .map_err(|_| SecretError::WrongPassphrase)?;
+        let values = serde_json::from_slice(plain).map_err(|_| SecretError::WrongPassphrase)?;
+
+        Ok(Self {
+            path,
+            key,
+            salt,
+            iterations: file.iterations,
+            values: Mutex::new(values),
+        })
+    }
+
+    fn save(&self, values: &BTreeMap<String, String>) -> Result<(), SecretError> {
+        let mut nonce = [0u8; NONCE_LEN];
+        fill_random(&mut nonce)?;
+
+        let mut in_out =
+            serde_json::to_vec(values).map_err(|e| SecretError::Vault(e.to_string()))?;
+        self.key
+            .seal_in_place_append_tag(
+                Nonce::assume_unique_for_key(nonce),
+                Aad::empty(),
+                &mut in_out,
+            )
+            .map_err(|_| SecretError::Vault("encryption failed".to_owned()))?;
+
+        let file = VaultFile {
+            version: 1,
+            iterations: self.iterations,
+            salt: BASE64_STANDARD.encode(self.salt),
+            nonce: BASE64_STANDARD.encode(nonce),
+            ciphertext: BASE64_STANDARD.encode(in_out),
+        };
+
+        if let Some(dir) = self.path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(
+            &self.path,
+            serde_json::to_vec_pretty(&file).map_err(|e| SecretError::Vault(e.to_string()))?,
+        )?;
+
+        Ok(())
+    }
+}
+
+impl SecretStore for VaultStore {
+    fn name(&self) -> &'static str {
+        "vault"
+    }
+
+    fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+        Ok(self.values.lock().unwrap().get(key).cloned())
+    }
+
+    fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+        let mut values = self.values.lock().unwrap();
+        values.insert(key.to_owned(), value.to_owned());
+        self.save(&values)
+    }
+
+    fn delete(&self, key: &str) -> Result<(), SecretError> {
+        let mut values = self.values.lock().unwrap();
+        if values.remove(key).is_some() {
+            self.save(&values)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8], iterations: u32) -> Result<LessSafeKey, SecretError> {
+    let iterations =
+        NonZeroU32::new(iterations).ok_or(SecretError::Vault("zero iterations".to_owned()))?;
+
+    let mut key = [0u8; 32];
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+
+    let key = UnboundKey::new(&CHACHA20_POLY1305, &key)
+        .map_err(|_| SecretError::Vault("bad key".to_owned()))?;
+    Ok(LessSafeKey::new(key))
+}
+
+fn fill_random(bytes: &mut [u8]) -> Result<(), SecretError> {
+    SystemRandom::new()
+        .fill(bytes)
+        .map_err(|_| SecretError::Vault("no random source".to_owned()))
+}
+
+fn decode(text: &str) -> Result<Vec<u8>, SecretError> {
+    BASE64_STANDARD
+        .decode(text)
+        .map_err(|e| SecretError::Vault(e.to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use tempdir::TempDir;
+
+    use super::*;
+
+    /// Few rounds, the tests run unoptimized.
+    const TEST_ITERATIONS: u32 = 1000;
+
+    #[test]
+    fn vault_roundtrip() {
+        let dir = TempDir::new("vault").unwrap();
+        let path = dir.path().join(VAULT_FILE);
+
+        let vault = VaultStore::open_with(path.clone(), "passphrase", TEST_ITERATIONS).unwrap();
+        vault.set("OPENAI_KEY", "sk-secret").unwrap();
+        vault.set("ALI_DASHSCOPE_API_KEY", "ali-secret").unwrap();
+        vault.delete("ALI_DASHSCOPE_API_KEY").unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(!content.contains("sk-secret"));
+
+        let vault = VaultStore::open(&path, "passphrase").unwrap();
+        assert_eq!(
+            Some("sk-secret".to_owned()),
+            vault.get("OPENAI_KEY").unwrap()
+        );
+        assert_eq!(None, vault.get("ALI_DASHSCOPE_API_KEY").unwrap());
+    }
+
+    #[test]
+    fn vault_wrong_passphrase() {
+        let dir = TempDir::new("vault").unwrap();
+        let path = dir.path().join(VAULT_FILE);
+
+        VaultStore::open_with(path.clone(), "passphrase", TEST_ITERATIONS)
+            .unwrap()
+            .set("OPENAI_KEY", "sk-secret")
+            .unwrap();
+
+        assert!(matches!(
+            VaultStore::open(&path, "wrong"),
+            Err(SecretError::WrongPassphrase)
+        ));
+    }
+
+    #[test]
+    fn migrate_moves_plaintext_keys() {
+        let dir = TempDir::new("vault").unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(
+            &file,
+            "VOICE=\"Cherry\"\nOPENAI_KEY=\"sk-secret\"\nALI_DASHSCOPE_API_KEY=\"\"\n",
+        )
+        .unwrap();
+        let vault =
+            VaultStore::open_with(dir.path().join(VAULT_FILE), "passphrase", TEST_ITERATIONS)
+                .unwrap();
+
+        assert_eq!(vec!["OPENAI_KEY"], migrate(&file, &vault).unwrap());
+        assert_eq!(
+            Some("sk-secret".to_owned()),
+            vault.get("OPENAI_KEY").unwrap()
+        );
+        assert_eq!(
+            "VOICE=\"Cherry\"\n",
+            std::fs::read_to_string(&file).unwrap()
+        );
+
+        // nothing left to move
+        assert!(migrate(&file, &vault).unwrap().is_empty());
+    }
+}
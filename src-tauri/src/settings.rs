@@ -0,0 +1,321 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+};
+
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+use toml_edit::DocumentMut;
+
+use crate::{
+    config::{ConfigError, ConfigLayer, GlobalConfig, Settings},
+    secrets::{SECRET_KEYS, SecretError, SecretStore, SharedSecretStore},
+};
+
+/// Shown in place of a secret that is set, sent back it keeps the secret as is.
+pub const MASK: &str = "********";
+
+#[derive(Error, Debug)]
+pub enum SettingsError {
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+    #[error("failed to write the config file: {0}")]
+    IO(#[from] io::Error),
+    #[error("the config file is not valid toml: {0}")]
+    Toml(#[from] toml_edit::TomlError),
+    #[error("failed to save the secrets: {0}")]
+    Secret(#[from] SecretError),
+}
+
+/// The settings as shown to the UI, the secrets masked.
+#[derive(Clone, Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SettingsView {
+    pub settings: Settings,
+    /// the layer each value came from, a value set by an env var cannot be changed here
+    pub sources: BTreeMap<String, ConfigLayer>,
+    /// the file the updates are saved to
+    pub file: PathBuf,
+    /// where the API keys are saved, `None` when they are in the config file
+    pub secret_store: Option<&'static str>,
+}
+
+impl SettingsView {
+    pub fn new(config: &GlobalConfig, file: &Path) -> Self {
+        let mut settings = config.settings().clone();
+        for secret in [
+            &mut settings.openai_key,
+            &mut settings.ali_dashscope_api_key,
+        ] {
+            if !secret.is_empty() {
+                *secret = MASK.to_owned();
+            }
+        }
+
+        Self {
+            settings,
+            sources: config.sources().clone(),
+            file: file.to_owned(),
+            secret_store: config.secrets().map(|store| store.name()),
+        }
+    }
+}
+
+/// The settings to change, the absent ones are left as they are.
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub struct SettingsUpdate {
+    /// `MASK` keeps the key
+    pub openai_key: Option<String>,
+    /// `MASK` keeps the key
+    pub ali_dashscope_api_key: Option<String>,
+    pub tts_url: Option<String>,
+    pub voice: Option<String>,
+    pub tts_model: Option<String>,
+    pub llm_model: Option<String>,
+    /// empty for the default dir
+    pub output_dir: Option<String>,
+}
+
+impl SettingsUpdate {
+    /// The keys to write, `None` removes the key from the file.
+    fn changes(self) -> Vec<(&'static str, Option<String>)> {
+        let mut changes = vec![];
+
+        for (key, value) in [
+            ("OPENAI_KEY", self.openai_key),
+            ("ALI_DASHSCOPE_API_KEY", self.ali_dashscope_api_key),
+        ] {
+            if let Some(value) = value
+                && value != MASK
+            {
+                changes.push((key, Some(value)));
+            }
+        }
+        for (key, value) in [
+            ("TTS_URL", self.tts_url),
+            ("VOICE", self.voice),
+            ("TTS_MODEL", self.tts_model),
+            ("LLM_MODEL", self.llm_model),
+        ] {
+            if let Some(value) = value {
+                changes.push((key, Some(value)));
+            }
+        }
+        if let Some(dir) = self.output_dir {
+            changes.push(("OUTPUT_DIR", (!dir.is_empty()).then_some(dir)));
+        }
+
+        changes
+    }
+}
+
+/// Write `update` to the config `file`, keeping its comments, and load the config again.
+/// The API keys go to `secrets` when there is a store, an empty key is deleted from it.
+/// The file and the keys are restored when the new config does not load.
+pub fn save(
+    file: &Path,
+    secrets: Option<&SharedSecretStore>,
+    update: SettingsUpdate,
+) -> Result<GlobalConfig, SettingsError> {
+    let original = match std::fs::read_to_string(file) {
+        Ok(content) => Some(content),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+
+    let mut document: DocumentMut = original.as_deref().unwrap_or_default().parse()?;
+    let mut secret_changes = vec![];
+    for (key, value) in update.changes() {
+        if secrets.is_some() && SECRET_KEYS.contains(&key) {
+            secret_changes.push((key, value));
+            continue;
+        }
+
+        match value {
+            Some(value) => document[key] = toml_edit::value(value),
+            None => {
+                document.remove(key);
+            }
+        }
+    }
+
+    if let Some(dir) = file.parent() {
+        std::fs::create_dir_all(dir)?;
+    }
+    std::fs::write(file, document.to_string())?;
+
+    let mut previous = vec![];
+    let res = match secrets {
+        Some(store) => set_secrets(&**store, secret_changes, &mut previous),
+        None => Ok(()),
+    }
+    .map_err(SettingsError::from)
+    .and_then(|()| Ok(GlobalConfig::load(file, secrets.cloned())?));
+
+    res.or_else(|e| {
+        if let Some(store) = secrets {
+            restore_secrets(&**store, previous);
+        }
+        match original {
+            Some(content) => std::fs::write(file, content)?,
+            None => std::fs::remove_file(file)?,
+        }
+        Err(e)
+    })
+}
+
+/// Apply `changes` to `store`, recording in `previous` the value each key had before.
+fn set_secrets(
+    store: &dyn SecretStore,
+    changes: Vec<(&'static str, Option<String>)>,
+    previous: &mut Vec<(&'static str, Option<String>)>,
+) -> Result<(), SecretError> {
+    for (key, value) in changes {
+        previous.push((key, store.get(key)?));
+        match value.as_deref() {
+            Some("") | None => store.delete(key)?,
+            Some(value) => store.set(key, value)?,
+        }
+    }
+
+    Ok(())
+}
+
+/// Put back the values recorded by `set_secrets`, as far as the store lets it.
+fn restore_secrets(store: &dyn SecretStore, previous: Vec<(&'static str, Option<String>)>) {
+    for (key, value) in previous.into_iter().rev() {
+        let res = match value {
+            Some(value) => store.set(key, &value),
+            None => store.delete(key),
+        };
+        if let Err(e) = res {
+            eprintln!("failed to restore {}: {}", key, e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tempdir::TempDir;
+
+    use crate::config::CONFIG_FILE;
+
+    use super::*;
+
+    #[test]
+    fn masks_secrets() {
+        let dir = TempDir::new("settings").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(&file, "OPENAI_KEY=\"sk-secret\"").unwrap();
+
+        let view = SettingsView::new(&GlobalConfig::load(&file, None).unwrap(), &file);
+        assert_eq!(MASK, view.settings.openai_key);
+        // not set, nothing to mask
+        assert_eq!("", view.settings.ali_dashscope_api_key);
+    }
+
+    #[test]
+    fn save_keeps_comments_and_masked_secrets() {
+        let dir = TempDir::new("settings").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(
+            &file,
+            "# my keys\nOPENAI_KEY=\"sk-secret\"\nOUTPUT_DIR=\"/videos\"\n",
+        )
+        .unwrap();
+
+        let config = save(
+            &file,
+            None,
+            SettingsUpdate {
+                openai_key: Some(MASK.to_owned()),
+                voice: Some("Cherry".to_owned()),
+                output_dir: Some("".to_owned()),
+                ..Default::default()
+            },
+        )
+        .unwrap();
+
+        assert_eq!("sk-secret", config.get_deepseek_api_key().unwrap());
+        assert_eq!("Cherry", config.settings().voice);
+        assert_eq!(None, config.get_output_dir());
+
+        let content = std::fs::read_to_string(&file).unwrap();
+        assert!(content.starts_with("# my keys\n"));
+        assert!(!content.contains("OUTPUT_DIR"));
+    }
+
+    #[test]
+    fn invalid_update_restores_file() {
+        let dir = TempDir::new("settings").unwrap();
+        let file = dir.path().join(CONFIG_FILE);
+        std::fs::write(&file, "VOICE=\"Cherry\"\n").unwrap();
+
+        let res = save(
+            &file,
+            None,
+            SettingsUpdate {
+                tts_url: Some("not a url".to_owned()),
+                ..Default::default()
+            },
+        );
+
+        assert!(matches!(
+            res,
+            Err(SettingsError::Config(ConfigError::Invalid(_)))
+        ));
+        assert_eq!(
+            "VOICE=\"Cherry\"\n",
+            std::fs::read_to_string(&file).unwrap()
+        );
+
+        // the keys are put back too
+        let store = Arc::new(MemoryStore::default());
+        store.set("OPENAI_KEY", "sk-old").unwrap();
+        let secrets: SharedSecretStore = store.clone();
+        let res = save(
+            &file,
+            Some(&secrets),
+            SettingsUpdate {
+                openai_key: Some("sk-new".to_owned()),
+                ali_dashscope_api_key: Some("ali-new".to_owned()),
+                tts_url: Some("not a url".to_owned()),
+                ..Default::default()
+            },
+        );
+
+        assert!(res.is_err());
+        assert_eq!(Some("sk-old".to_owned()), store.get("OPENAI_KEY").unwrap());
+        assert_eq!(None, store.get("ALI_DASHSCOPE_API_KEY").unwrap());
+    }
+
+    #[derive(Default)]
+    struct MemoryStore(Mutex<BTreeMap<String, String>>);
+
+    impl SecretStore for MemoryStore {
+        fn name(&self) -> &'static str {
+            "memory"
+        }
+
+        fn get(&self, key: &str) -> Result<Option<String>, SecretError> {
+            Ok(self.0.lock().unwrap().get(key).cloned())
+        }
+
+        fn set(&self, key: &str, value: &str) -> Result<(), SecretError> {
+            self.0
+                .lock()
+                .unwrap()
+                .insert(key.to_owned(), value.to_owned());
+            Ok(())
+        }
+
+        fn delete(&self, key: &str) -> Result<(), SecretError> {
+            self.0.lock().unwrap().remove(key);
+            Ok(())
+        }
+    }
+}
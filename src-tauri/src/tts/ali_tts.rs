@@ -3,16 +3,24 @@ use std::path::PathBuf;
 use nanoid::nanoid;
 use reqwest::{Client, header};
 use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
 
-use crate::tts::{get_wav_len, TTSError, TTSFile, TTSService};
+use crate::{
+    progress::{ProgressEvent, ProgressReporter},
+    tts::{TTSError, TTSFile, TTSService, get_wav_len},
+};
 
 const DEFAULT_TEMP_DIR: &str = "temp";
+const DEFAULT_VOICE: &str = "Serena";
+const DEFAULT_MODEL: &str = "qwen-tts";
 
 pub struct AliTTS {
     http: Client,
     url: String,
     key: String,
     temp_dir: String,
+    voice: String,
+    model: String,
 }
 
 impl AliTTS {
@@ -25,12 +33,30 @@ impl AliTTS {
             url,
             key,
             temp_dir: DEFAULT_TEMP_DIR.to_owned(),
+            voice: DEFAULT_VOICE.to_owned(),
+            model: DEFAULT_MODEL.to_owned(),
         }
     }
 
-    pub fn with_temp_dir<'a>(self, temp_dir: impl AsRef<&'a str>) -> Self {
+    /// The qwen-tts voice, `Serena` by default.
+    pub fn with_voice(self, voice: impl Into<String>) -> Self {
         Self {
-            temp_dir: temp_dir.as_ref().to_string(),
+            voice: voice.into(),
+            ..self
+        }
+    }
+
+    /// The TTS model, `qwen-tts` by default.
+    pub fn with_model(self, model: impl Into<String>) -> Self {
+        Self {
+            model: model.into(),
+            ..self
+        }
+    }
+
+    pub fn with_temp_dir(self, temp_dir: impl Into<String>) -> Self {
+        Self {
+            temp_dir: temp_dir.into(),
             ..self
         }
     }
@@ -38,10 +64,22 @@ impl AliTTS {
 
 #[async_trait::async_trait]
 impl TTSService for AliTTS {
-    async fn tts(&self, text_list: &Vec<String>) -> Result<Vec<TTSFile>, TTSError> {
-        let mut list = vec![];
+    fn voice(&self) -> String {
+        self.voice.clone()
+    }
 
-        let to_network_err = |e: reqwest::Error| TTSError::Network(e.to_string());
+    fn cache_key(&self) -> String {
+        // another model or endpoint reads the same voice differently
+        format!("{}\n{}\n{}", self.url, self.model, self.voice)
+    }
+
+    async fn tts(
+        &self,
+        text_list: &Vec<String>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<TTSFile>, TTSError> {
+        let mut list: Vec<TTSFile> = vec![];
 
         let tmp_path = PathBuf::from(&self.temp_dir);
         tokio::fs::create_dir_all(&tmp_path).await?;
@@ -53,71 +91,112 @@ impl TTSService for AliTTS {
         let mut index = 1usize;
 
         for text in text_list {
-            let body = build_body(text);
-            let response = self
-                .http
-                .post(&self.url)
-                .header(header::AUTHORIZATION, format!("Bearer {}", self.key))
-                .header(header::CONTENT_TYPE, "application/json")
-                .body(body)
-                .send()
-                .await
-                .map_err(to_network_err)?;
-
-            if response.status() != 200 {
-                let data = response.text().await.unwrap();
-                return Err(TTSError::Network(format!(
-                    "request ali tts failed: {}",
-                    data
-                )));
-            }
-
-            let data: ApiResponse = response.json().await.map_err(to_network_err)?;
-
-            let audio_url = data.output.audio.url;
+            let file = tmp_path.join(format!("voice_{}_{:03}.wav", nanoid!(10), index));
 
-            let response = self
-                .http
-                .get(audio_url)
-                .send()
-                .await
-                .map_err(to_network_err)?;
-            let bytes = response.bytes().await.map_err(to_network_err)?;
+            let synthesized = async {
+                cancel
+                    .run_until_cancelled(self.synthesize(text, &file))
+                    .await
+                    .ok_or(TTSError::Cancelled)??;
+                get_wav_len(&file).await
+            }
+            .await;
+            let duration = match synthesized {
+                Ok(duration) => duration,
+                Err(e) => {
+                    // cancelled or failed, the sentences done are no use without the rest
+                    _ = tokio::fs::remove_file(&file).await;
+                    for tts_file in list {
+                        _ = tokio::fs::remove_file(&tts_file.path).await;
+                    }
+                    return Err(e);
+                }
+            };
 
-            let file = tmp_path.join(format!("voice_{}_{:03}.wav", nanoid!(10), index));
-            index += 1;
-            tokio::fs::write(&file, bytes).await?;
-            
-            let duration = get_wav_len(&file).await?;
-           
             list.push(TTSFile {
                 path: file,
                 text: text.clone(),
                 duration,
             });
+
+            progress.report(ProgressEvent::SentenceSynthesized {
+                index,
+                total: text_list.len(),
+            });
+            index += 1;
+        }
+
+        Ok(list)
+    }
+}
+
+impl AliTTS {
+    /// Synthesize one text and write the audio to `file`.
+    async fn synthesize(&self, text: &String, file: &PathBuf) -> Result<(), TTSError> {
+        let to_network_err = |e: reqwest::Error| TTSError::Network(e.to_string());
+
+        let body = build_body(text, &self.model, &self.voice);
+        let response = self
+            .http
+            .post(&self.url)
+            .header(header::AUTHORIZATION, format!("Bearer {}", self.key))
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(body)
+            .send()
+            .await
+            .map_err(to_network_err)?;
+
+        if response.status() != 200 {
+            let data = response.text().await.map_err(to_network_err)?;
+            return Err(TTSError::Network(format!(
+                "request ali tts failed: {}",
+                data
+            )));
         }
 
-        fn build_body(text: &String) -> String {
+        let data: ApiResponse = response.json().await.map_err(to_network_err)?;
+
+        let audio_url = data.output.audio.url;
+
+        let response = self
+            .http
+            .get(audio_url)
+            .send()
+            .await
+            .map_err(to_network_err)?;
+        let bytes = response.bytes().await.map_err(to_network_err)?;
+
+        tokio::fs::write(file, bytes).await?;
+
+        fn build_body(text: &String, model: &str, voice: &str) -> String {
             let len = text.chars().count();
             let mut body = String::with_capacity(100 + len);
 
             body.push_str(
                 r#"{
-    "model": "qwen-tts",
+    "model": ""#,
+            );
+            body.push_str(model);
+            body.push_str(
+                r#"",
     "input": {
         "text": ""#,
             );
             body.push_str(&text);
             body.push_str(
                 r#"",
-        "voice": "Serena"
+        "voice": ""#,
+            );
+            body.push_str(voice);
+            body.push_str(
+                r#""
     }"#,
             );
 
             body
         }
 
-        Ok(list)
+        Ok(())
     }
 }
 
@@ -165,6 +244,8 @@ struct ApiResponse {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::progress::NoopReporter;
+    use std::sync::Mutex;
     use tokio::fs;
 
     #[tokio::test]
@@ -204,7 +285,13 @@ mod tests {
             .create();
 
         let ali_tts = AliTTS::new(format!("{}/first", url), "test_key".to_string());
-        let tts_files = ali_tts.tts(&vec!["测试".to_owned()]).await;
+        let tts_files = ali_tts
+            .tts(
+                &vec!["测试".to_owned()],
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await;
 
         assert!(tts_files.is_ok());
         let tts_files = tts_files.unwrap();
@@ -253,8 +340,13 @@ mod tests {
             .create();
 
         let ali_tts = AliTTS::new(format!("{}/first", url), "test_key".to_string());
+        let events = Mutex::new(vec![]);
         let tts_files = ali_tts
-            .tts(&vec!["测试".to_owned(), "测试".to_owned()])
+            .tts(
+                &vec!["测试".to_owned(), "测试".to_owned()],
+                &|e: ProgressEvent| events.lock().unwrap().push(e),
+                &CancellationToken::new(),
+            )
             .await;
 
         assert!(tts_files.is_ok());
@@ -269,5 +361,94 @@ mod tests {
         assert!(tts_files[1].path.exists());
         _ = fs::remove_file(&tts_files[1].path);
 
+        assert_eq!(
+            vec![
+                ProgressEvent::SentenceSynthesized { index: 1, total: 2 },
+                ProgressEvent::SentenceSynthesized { index: 2, total: 2 },
+            ],
+            *events.lock().unwrap()
+        );
+    }
+
+    #[test]
+    fn cache_key() {
+        let tts = AliTTS::new("https://tts.example.com".to_owned(), "key".to_owned());
+        let key = tts.cache_key();
+
+        assert_ne!(key, tts.with_model("qwen-tts-latest").cache_key());
+        // not the key
+        let other = AliTTS::new("https://tts.example.com".to_owned(), "other".to_owned());
+        assert_eq!(key, other.cache_key());
+    }
+
+    #[tokio::test]
+    async fn tts_cancelled() {
+        let server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+
+        let ali_tts = AliTTS::new(format!("{}/first", url), "test_key".to_string());
+        let tts_files = ali_tts
+            .tts(&vec!["测试".to_owned()], &NoopReporter, &cancel)
+            .await;
+
+        assert!(matches!(tts_files, Err(TTSError::Cancelled)));
+    }
+
+    #[tokio::test]
+    async fn tts_failed_removes_files() {
+        let wav_data = vec![
+            0x52, 0x49, 0x46, 0x46, 0x24, 0x00, 0x00, 0x00, 0x57, 0x41, 0x56, 0x45, 0x66, 0x6D,
+            0x74, 0x20, 0x10, 0x00, 0x00, 0x00, 0x01, 0x00, 0x01, 0x00, 0x40, 0x1F, 0x00, 0x00,
+            0x40, 0x1F, 0x00, 0x00, 0x01, 0x00, 0x08, 0x00, 0x64, 0x61, 0x74, 0x61, 0x00, 0x00,
+            0x00, 0x00,
+        ];
+
+        let mut server = mockito::Server::new_async().await;
+        let url = server.url();
+
+        let first_body = format!(
+            r#"{{"output":{{"finish_reason":"stop","audio":{{"expires_at":1751529865,"data":"","id":"audio_1","url":"{}/second"}}}},"usage":{{"input_tokens_details":{{"text_tokens":19}},"total_tokens":224,"output_tokens":205,"input_tokens":19,"output_tokens_details":{{"audio_tokens":205,"text_tokens":0}}}},"request_id":"1"}}"#,
+            url
+        );
+
+        let _ok = server
+            .mock("POST", "/first")
+            .match_body(mockito::Matcher::Regex("第一句".to_owned()))
+            .with_status(200)
+            .with_header(header::CONTENT_TYPE, "application/json")
+            .with_body(first_body)
+            .create_async()
+            .await;
+        let _failed = server
+            .mock("POST", "/first")
+            .match_body(mockito::Matcher::Regex("第二句".to_owned()))
+            .with_status(500)
+            .with_body("quota exceeded")
+            .create_async()
+            .await;
+        let _wav = server
+            .mock("GET", "/second")
+            .with_status(200)
+            .with_body(wav_data)
+            .create_async()
+            .await;
+
+        let temp = tempdir::TempDir::new("tts").unwrap();
+        let ali_tts = AliTTS::new(format!("{}/first", url), "test_key".to_string())
+            .with_temp_dir(temp.path().to_str().unwrap());
+        let tts_files = ali_tts
+            .tts(
+                &vec!["第一句".to_owned(), "第二句".to_owned()],
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await;
+
+        assert!(matches!(tts_files, Err(TTSError::Network(_))));
+        // the wav of the first sentence is removed too
+        assert_eq!(0, std::fs::read_dir(temp.path()).unwrap().count());
     }
 }
@@ -3,10 +3,29 @@ pub mod ali_tts;
 use hound::WavReader;
 use std::{io, path::PathBuf, time::Duration};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
+
+use crate::progress::ProgressReporter;
 
 #[async_trait::async_trait]
 pub trait TTSService {
-    async fn tts(&self, text_list: &Vec<String>) -> Result<Vec<TTSFile>, TTSError>;
+    /// The name of the voice the text is read in.
+    fn voice(&self) -> String;
+
+    /// The settings the audio depends on, part of the cache key of each sentence.
+    fn cache_key(&self) -> String {
+        self.voice()
+    }
+
+    /// Synthesize each text into its own audio file,
+    /// reporting `SentenceSynthesized` to `progress` as each one is done.
+    /// Stops with `TTSError::Cancelled` and removes the files when `cancel` is cancelled.
+    async fn tts(
+        &self,
+        text_list: &Vec<String>,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> Result<Vec<TTSFile>, TTSError>;
 }
 
 pub struct TTSFile {
@@ -30,6 +49,8 @@ pub enum TTSError {
     Network(String),
     #[error("IO error: {0}")]
     IO(#[from] io::Error),
+    #[error("cancelled")]
+    Cancelled,
 }
 
 /// Get the audio playback duration in seconds.
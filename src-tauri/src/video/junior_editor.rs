@@ -1,17 +1,18 @@
-use reqwest::Client;
-use std::{path::PathBuf, time::Duration};
+use reqwest::{Client, Url};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
 use tokio::fs;
+use tokio_util::sync::CancellationToken;
 
 use crate::{
     mpeg::VideoEditTool,
-    news::NewsMaterial,
+    progress::ProgressReporter,
     video::{VideoEditor, VideoEditorError, VideoEditorResult},
 };
 
-const DEFAULT_TEMP_DIR: &str = "temp";
-
 pub struct JuniorEditor {
-    temp_dir: &'static str,
     http: Client,
     video_editor_tool: Box<dyn VideoEditTool + Sync + Send + 'static>,
 }
@@ -22,7 +23,6 @@ impl JuniorEditor {
         T: VideoEditTool + Sync + Send + 'static,
     {
         Self {
-            temp_dir: DEFAULT_TEMP_DIR,
             http: Client::new(),
             video_editor_tool: Box::new(tool),
         }
@@ -31,18 +31,99 @@ impl JuniorEditor {
 
 #[async_trait::async_trait]
 impl VideoEditor for JuniorEditor {
-    async fn do_edit(&self, material: &NewsMaterial, dur: Duration) -> VideoEditorResult<PathBuf> {
-        let pic_urls = Self::get_need_pics(&material.pics, dur);
-        let pic_files = self.save_pics(pic_urls).await?;
+    fn cache_key(&self) -> String {
+        self.video_editor_tool.cache_key()
+    }
+
+    fn pick_pics(&self, pics: &Vec<String>, dur: Duration) -> Vec<String> {
+        Self::get_need_pics(pics, dur)
+    }
+
+    /// Download one picture to `file_path`, the extension comes from the content-type.
+    /// A `file://` picture is copied.
+    async fn save_pic(&self, pic_url: &String, file_path: PathBuf) -> VideoEditorResult<PathBuf> {
+        if let Some(path) = Url::parse(pic_url)
+            .ok()
+            .filter(|url| url.scheme() == "file")
+            .and_then(|url| url.to_file_path().ok())
+        {
+            let file_path = match path.extension() {
+                Some(extension) => file_path.with_extension(extension),
+                None => file_path,
+            };
+            fs::copy(&path, &file_path).await?;
+            return Ok(file_path);
+        }
+
+        let response = self
+            .http
+            .get(pic_url)
+            .send()
+            .await
+            .map_err(|e| VideoEditorError::NetWork(e.to_string()))?;
+
+        let format = {
+            let content_type = response
+                .headers()
+                .get("content-type")
+                .ok_or(VideoEditorError::NetWork(
+                    "cannot find image format".to_owned(),
+                ))?
+                .to_str()
+                .map_err(|_| VideoEditorError::NetWork("malformed content-type".to_owned()))?;
+
+            content_type
+                .split('/')
+                .nth(1)
+                .ok_or(VideoEditorError::NetWork(
+                    "malformed content-type".to_owned(),
+                ))?
+                .to_string()
+        };
 
-        let path = self.compose_pics(&pic_files).await?;
+        let bytes = response
+            .bytes()
+            .await
+            .map_err(|e| VideoEditorError::NetWork(e.to_string()))?;
 
-        Ok(path)
+        let file_path = file_path.with_extension(format);
+        fs::write(&file_path, bytes).await?;
+
+        Ok(file_path)
+    }
+
+    async fn compose_pics(
+        &self,
+        pics: &Vec<PathBuf>,
+        output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> VideoEditorResult<()> {
+        if pics.is_empty() {
+            return Err(VideoEditorError::Image("no pics to compose".to_owned()));
+        }
+
+        let file_list_path = self.build_file_list(pics).await?;
+
+        let res = self
+            .video_editor_tool
+            .compose_images(&file_list_path, output, progress, cancel)
+            .await;
+
+        _ = fs::remove_file(file_list_path).await;
+
+        res?;
+        Ok(())
     }
 }
 
 impl JuniorEditor {
+    /// None when there is no picture to show.
     fn get_need_pics(pics: &Vec<String>, dur: Duration) -> Vec<String> {
+        if pics.is_empty() {
+            return vec![];
+        }
+
         let mut pic_need = (dur.as_secs() / 2).max(1) as usize;
         if dur.subsec_millis() > 0 {
             pic_need += 1;
@@ -71,131 +152,62 @@ impl JuniorEditor {
         pics
     }
 
-    async fn save_pics(&self, pics: Vec<String>) -> VideoEditorResult<Vec<PathBuf>> {
-        let id = nanoid::nanoid!(10);
-
-        let mut pic_files = vec![];
-
-        let path = PathBuf::from(format!("{}", self.temp_dir));
-        fs::create_dir_all(&path).await?;
-        // let path = path.canonicalize().unwrap();
-
-        for (i, pic_url) in pics.iter().enumerate() {
-            let response = self
-                .http
-                .get(pic_url)
-                .send()
-                .await
-                .map_err(|e| VideoEditorError::NetWork(e.to_string()))?;
-
-            let format = {
-                let content_type = response
-                    .headers()
-                    .get("content-type")
-                    .ok_or(VideoEditorError::NetWork(
-                        "cannot find image format".to_owned(),
-                    ))?
-                    .to_str()
-                    .unwrap();
-
-                content_type
-                    .split('/')
-                    .nth(1)
-                    .ok_or(VideoEditorError::NetWork(
-                        "malformed content-type".to_owned(),
-                    ))?
-                    .to_string()
-            };
-
-            let bytes = response
-                .bytes()
-                .await
-                .map_err(|e| VideoEditorError::NetWork(e.to_string()))?;
-
-            let file_path = path.join(format!("{}{:03}.{}", id, i, format));
-            fs::write(&file_path, bytes).await?;
-
-            pic_files.push(file_path);
-        }
-
-        Ok(pic_files)
-    }
-
-    async fn compose_pics(&self, pics: &Vec<PathBuf>) -> VideoEditorResult<PathBuf> {
-        if pics.is_empty() {
-            return Err(VideoEditorError::Image("no pics to compose".to_owned()));
-        }
-
-        let output_path = PathBuf::from(format!(
-            "{}/{}-composed.mp4",
-            self.temp_dir,
-            nanoid::nanoid!(10)
-        ));
-
-        let file_list_path = self.build_file_list(pics).await?;
-
-        self.video_editor_tool
-            .compose_images(&file_list_path, &output_path)
-            .await?;
-
-        _ = fs::remove_file(file_list_path).await;
-
-        for pic in pics {
-            _ = fs::remove_file(pic).await;
-        }
-        Ok(output_path)
-    }
-
     async fn build_file_list(&self, pics: &Vec<PathBuf>) -> VideoEditorResult<PathBuf> {
-        if pics.is_empty() {
+        let Some(first) = pics.first() else {
             return Err(VideoEditorError::Image("no pics to compose".to_owned()));
-        }
+        };
 
-        let file_list_path = PathBuf::from(format!(
-            "{}/{}-file-list.txt",
-            self.temp_dir,
-            nanoid::nanoid!(10)
-        ));
+        // next to the pictures, the list refers to them by file name
+        let file_list_path = first.with_file_name(format!("{}-file-list.txt", nanoid::nanoid!(10)));
 
         let mut file_list_content = String::new();
         for pic in pics {
-            file_list_content.push_str(&format!(
-                "file '{}'\n",
-                pic.file_name().unwrap().to_str().unwrap()
-            ));
+            file_list_content.push_str(&format!("file '{}'\n", Self::file_name(pic)?));
             file_list_content.push_str(&format!("duration 2\n\n"));
         }
 
-        file_list_content.push_str(&format!(
-            "file '{}'\n",
-            pics.get(0).unwrap().file_name().unwrap().to_str().unwrap()
-        ));
+        file_list_content.push_str(&format!("file '{}'\n", Self::file_name(first)?));
         file_list_content.push_str(&format!("duration 0\n\n"));
 
         fs::write(&file_list_path, file_list_content).await?;
 
         Ok(file_list_path)
     }
+
+    fn file_name(pic: &Path) -> VideoEditorResult<String> {
+        pic.file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .ok_or_else(|| VideoEditorError::Image(format!("not a file: {}", pic.display())))
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use std::time::Duration;
 
-    use crate::mpeg::VideoEditToolError;
+    use tempdir::TempDir;
+
+    use crate::{
+        mpeg::VideoEditToolError,
+        news::{NEWS_MODEL_VERSION, NewsMaterial},
+        progress::NoopReporter,
+    };
 
     use super::*;
 
     #[tokio::test]
     async fn edit_video_need_three() {
         let material = NewsMaterial {
+            version: NEWS_MODEL_VERSION,
             title: "TITLE".to_owned(),
             summary: vec![],
-            pics: vec!["pic1".to_owned(), "pic2".to_owned()],
+            pics: vec!["pic1".into(), "pic2".into()],
             videos: vec![],
+            article: vec![],
+            provenance: None,
         };
 
-        let res = JuniorEditor::get_need_pics(&material.pics, Duration::from_millis(5300));
+        let res = JuniorEditor::get_need_pics(&material.pic_urls(), Duration::from_millis(5300));
         assert_eq!(res.len(), 3);
 
         let expected_pics = vec!["pic1".to_owned(), "pic2".to_owned(), "pic1".to_owned()];
@@ -206,13 +218,16 @@ mod tests {
     #[tokio::test]
     async fn edit_video_need_two() {
         let material = NewsMaterial {
+            version: NEWS_MODEL_VERSION,
             title: "TITLE".to_owned(),
             summary: vec![],
-            pics: vec!["pic1".to_owned(), "pic2".to_owned()],
+            pics: vec!["pic1".into(), "pic2".into()],
             videos: vec![],
+            article: vec![],
+            provenance: None,
         };
 
-        let res = JuniorEditor::get_need_pics(&material.pics, Duration::from_millis(4000));
+        let res = JuniorEditor::get_need_pics(&material.pic_urls(), Duration::from_millis(4000));
         assert_eq!(2, res.len());
 
         let expected_pics = vec!["pic1".to_owned(), "pic2".to_owned()];
@@ -223,13 +238,16 @@ mod tests {
     #[tokio::test]
     async fn edit_video_need_one() {
         let material = NewsMaterial {
+            version: NEWS_MODEL_VERSION,
             title: "TITLE".to_owned(),
             summary: vec![],
-            pics: vec!["pic1".to_owned(), "pic2".to_owned()],
+            pics: vec!["pic1".into(), "pic2".into()],
             videos: vec![],
+            article: vec![],
+            provenance: None,
         };
 
-        let res = JuniorEditor::get_need_pics(&material.pics, Duration::from_millis(1000));
+        let res = JuniorEditor::get_need_pics(&material.pic_urls(), Duration::from_millis(1000));
         assert_eq!(1, res.len());
 
         let expected_pics = vec!["pic1".to_owned()];
@@ -240,21 +258,24 @@ mod tests {
     #[tokio::test]
     async fn edit_video_need_many() {
         let material = NewsMaterial {
+            version: NEWS_MODEL_VERSION,
             title: "TITLE".to_owned(),
             summary: vec![],
             pics: vec![
-                "pic1".to_owned(),
-                "pic2".to_owned(),
-                "pic3".to_owned(),
-                "pic4".to_owned(),
-                "pic5".to_owned(),
-                "pic6".to_owned(),
-                "pic7".to_owned(),
+                "pic1".into(),
+                "pic2".into(),
+                "pic3".into(),
+                "pic4".into(),
+                "pic5".into(),
+                "pic6".into(),
+                "pic7".into(),
             ],
             videos: vec![],
+            article: vec![],
+            provenance: None,
         };
 
-        let res = JuniorEditor::get_need_pics(&material.pics, Duration::from_millis(8_300));
+        let res = JuniorEditor::get_need_pics(&material.pic_urls(), Duration::from_millis(8_300));
         assert_eq!(5, res.len());
 
         let expected_pics = vec![
@@ -268,8 +289,30 @@ mod tests {
         assert_eq!(expected_pics, res);
     }
 
+    #[test]
+    fn edit_video_no_pics() {
+        let res = JuniorEditor::get_need_pics(&vec![], Duration::from_millis(5300));
+        assert!(res.is_empty());
+    }
+
+    struct FakeTool;
+
+    #[async_trait::async_trait]
+    impl VideoEditTool for FakeTool {
+        async fn compose_images(
+            &self,
+            file_list: &PathBuf,
+            _: &PathBuf,
+            _: &dyn ProgressReporter,
+            _: &CancellationToken,
+        ) -> Result<(), VideoEditToolError> {
+            assert!(file_list.exists());
+            Ok(())
+        }
+    }
+
     #[tokio::test]
-    async fn save_http_pics_two_png() {
+    async fn save_http_pic_png() {
         let mock_response = vec![0x52, 0x49, 0x46, 0x46];
 
         let mut server = mockito::Server::new_async().await;
@@ -284,33 +327,16 @@ mod tests {
             .with_body(mock_response)
             .create();
 
-        struct FakeTool;
-
-        #[async_trait::async_trait]
-        impl VideoEditTool for FakeTool {
-            async fn compose_images(
-                &self,
-                _: &PathBuf,
-                _: &PathBuf,
-            ) -> Result<(), VideoEditToolError> {
-                Ok(())
-            }
-        }
-
+        let dir = TempDir::new("pics").unwrap();
         let editor = JuniorEditor::new(FakeTool);
 
-        let pics = vec![format!("{url}/images"), format!("{url}/images")];
-
-        let paths = editor.save_pics(pics).await;
-        assert!(paths.is_ok());
-
-        let paths = paths.unwrap();
+        let path = editor
+            .save_pic(&format!("{url}/images"), dir.path().join("pic"))
+            .await
+            .unwrap();
 
-        for path in &paths {
-            _ = fs::remove_file(path).await;
-        }
-
-        assert_eq!(2, paths.len());
+        assert_eq!(dir.path().join("pic.png"), path);
+        assert!(path.exists());
     }
 
     #[tokio::test]
@@ -337,33 +363,56 @@ mod tests {
             .with_body(&mock_response)
             .create();
 
-        struct FakeTool;
-
-        #[async_trait::async_trait]
-        impl VideoEditTool for FakeTool {
-            async fn compose_images(
-                &self,
-                _: &PathBuf,
-                _: &PathBuf,
-            ) -> Result<(), VideoEditToolError> {
-                Ok(())
-            }
-        }
-
+        let dir = TempDir::new("pics").unwrap();
         let editor = JuniorEditor::new(FakeTool);
 
-        let pics = vec![format!("{url}/image_png"), format!("{url}/image_jpg")];
+        let png = editor
+            .save_pic(&format!("{url}/image_png"), dir.path().join("a"))
+            .await
+            .unwrap();
+        let jpg = editor
+            .save_pic(&format!("{url}/image_jpg"), dir.path().join("b"))
+            .await
+            .unwrap();
+
+        assert_eq!(Some("png"), png.extension().and_then(|e| e.to_str()));
+        assert_eq!(Some("jpg"), jpg.extension().and_then(|e| e.to_str()));
+    }
 
-        let paths = editor.save_pics(pics).await;
-        assert!(paths.is_ok());
+    #[tokio::test]
+    async fn save_local_pic() {
+        let dir = TempDir::new("pics").unwrap();
+        let local = dir.path().join("local.jpeg");
+        fs::write(&local, b"jpeg").await.unwrap();
+        let url = Url::from_file_path(&local).unwrap().to_string();
 
-        let paths = paths.unwrap();
+        let editor = JuniorEditor::new(FakeTool);
+        let path = editor.save_pic(&url, dir.path().join("pic")).await.unwrap();
 
-        for path in &paths {
-            _ = fs::remove_file(path).await;
-        }
+        assert_eq!(dir.path().join("pic.jpeg"), path);
+        assert_eq!(b"jpeg".to_vec(), fs::read(&path).await.unwrap());
+    }
+
+    #[tokio::test]
+    async fn compose_pics_keeps_pics() {
+        let dir = TempDir::new("pics").unwrap();
+        let pic = dir.path().join("pic.png");
+        fs::write(&pic, b"png").await.unwrap();
 
-        assert_eq!(2, paths.len());
+        let editor = JuniorEditor::new(FakeTool);
+        editor
+            .compose_pics(
+                &vec![pic.clone()],
+                &dir.path().join("slideshow.mp4"),
+                &NoopReporter,
+                &CancellationToken::new(),
+            )
+            .await
+            .unwrap();
+
+        // the pictures are cached by the director, only the file list is removed
+        assert!(pic.exists());
+        assert_eq!(1, std::fs::read_dir(dir.path()).unwrap().count());
     }
 
     // #[tokio::test]
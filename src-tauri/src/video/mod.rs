@@ -1,6 +1,7 @@
-use crate::{mpeg::VideoEditToolError, news::NewsMaterial};
+use crate::{mpeg::VideoEditToolError, progress::ProgressReporter};
 use std::{io, path::PathBuf, time::Duration};
 use thiserror::Error;
+use tokio_util::sync::CancellationToken;
 
 pub mod junior_editor;
 
@@ -8,7 +9,7 @@ pub type VideoEditorResult<T> = Result<T, VideoEditorError>;
 
 #[derive(Error, Debug)]
 pub enum VideoEditorError {
-     #[error("tts has not set")]
+    #[error("tts has not set")]
     NoSet,
     #[error("network request error: {0}")]
     NetWork(String),
@@ -19,15 +20,32 @@ pub enum VideoEditorError {
     #[error("duration empty or video editor has not set")]
     Duration,
     #[error("video editor tool error:  {0}")]
-    Tool(#[from]VideoEditToolError)
+    Tool(#[from] VideoEditToolError),
+    #[error("cancelled")]
+    Cancelled,
 }
 
 #[async_trait::async_trait]
 pub trait VideoEditor {
-    /// Edit the video Pics and return the path of the edited video.
-    /// The `dur` is the duration of the video, which is used to determine how many pictures are needed.
-    /// The `material` contains the information of the news material, including the pictures.
-    /// The edited video will be in 9:16 aspect ratio.
-    /// The Video without subtitle, dubbing
-    async fn do_edit(&self, material: &NewsMaterial, dur: Duration) -> VideoEditorResult<PathBuf>;
+    /// The settings the video depends on, part of the cache key of the stage.
+    fn cache_key(&self) -> String {
+        String::new()
+    }
+
+    /// The pictures of `pics`, in order and possibly repeated, a video of `dur` shows.
+    fn pick_pics(&self, pics: &Vec<String>, dur: Duration) -> Vec<String>;
+
+    /// Download the picture at `url` to `file_path`, returns the path with the extension of the image format.
+    async fn save_pic(&self, url: &String, file_path: PathBuf) -> VideoEditorResult<PathBuf>;
+
+    /// Render the pictures, in order, into a video at `output`,
+    /// without subtitle or dubbing, reporting the render progress to `progress`.
+    /// Stops when `cancel` is cancelled.
+    async fn compose_pics(
+        &self,
+        pics: &Vec<PathBuf>,
+        output: &PathBuf,
+        progress: &dyn ProgressReporter,
+        cancel: &CancellationToken,
+    ) -> VideoEditorResult<()>;
 }
@@ -0,0 +1,30 @@
+import { stageNames, type Stage } from "./progress";
+
+export type ErrorKind =
+    | "config"
+    | "source"
+    | "material"
+    | "tts"
+    | "subtitle"
+    | "video"
+    | "ffmpeg"
+    | "io"
+    | "notFound"
+    | "cancelled";
+
+/** What a command rejects with. */
+export type CommandError = {
+    kind: ErrorKind,
+    message: string,
+    /** the stage of the pipeline that failed, if any */
+    stage: Stage | null,
+};
+
+export function errorMessage(error: unknown): string {
+    const e = error as CommandError;
+    if (e && typeof e.message === "string") {
+        return e.stage ? `${stageNames[e.stage]}失败: ${e.message}` : e.message;
+    }
+
+    return String(error);
+}
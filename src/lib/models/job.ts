@@ -0,0 +1,28 @@
+import type { Stage } from "./progress";
+import type { NewsTitle } from "./newsTitle";
+import type { LibraryEntry } from "./library";
+
+export const JOB_EVENT = "job-finished";
+export const JOB_UPDATED_EVENT = "job-updated";
+
+export type JobStatus =
+    | { status: "queued" }
+    | { status: "running", stage: Stage }
+    | { status: "done", path: string }
+    | { status: "failed", message: string }
+    | { status: "cancelled" };
+
+export type JobFinished = { id: string, url: string } & JobStatus;
+
+export type Job = {
+    id: string,
+    newsTitle: NewsTitle,
+    /** unix seconds */
+    createdAt: number,
+    updatedAt: number,
+} & JobStatus;
+
+export type Enqueued =
+    | { kind: "queued", id: string }
+    | { kind: "pending", id: string }
+    | { kind: "rendered", entry: LibraryEntry };
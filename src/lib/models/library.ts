@@ -0,0 +1,32 @@
+import type { NewsTitle } from "./newsTitle";
+
+export type NewsPic = {
+    url: string,
+    caption: string | null,
+    credit: string | null,
+};
+
+/** What made the summary of a video. */
+export type Provenance = {
+    model: string,
+    promptVersion: number,
+    /** unix seconds */
+    createdAt: number,
+};
+
+export type LibraryEntry = {
+    id: string,
+    title: string,
+    newsTitle: NewsTitle,
+    summary: string[],
+    pics: NewsPic[],
+    provenance?: Provenance | null,
+    voice: string | null,
+    /** seconds of each summary sentence */
+    durations: number[],
+    /** seconds */
+    duration: number,
+    path: string,
+    /** unix seconds */
+    createdAt: number,
+};
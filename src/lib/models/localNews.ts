@@ -0,0 +1,11 @@
+/** A news from a local document or a pasted text, for `start_local_video`. */
+export type LocalNewsInput = {
+    /** the first heading or line of the text when absent */
+    title?: string,
+    /** an HTML, Markdown or text file */
+    file?: string,
+    /** Markdown or plain text, used when there is no file */
+    text?: string,
+    /** the pictures on disk */
+    images: string[],
+};
@@ -1,9 +1,37 @@
 
 
+export type NewsMeta = {
+    /** unix seconds */
+    publishedAt?: number | null,
+    /** unix seconds, when the list was fetched */
+    crawledAt?: number | null,
+    author?: string | null,
+    /** the channel or category */
+    channel?: string | null,
+    tags?: string[],
+    language?: string | null,
+    summary?: string | null,
+};
+
 export type NewsTitle = {
+    /** 1 for the titles saved before the model had a version */
+    version?: number,
     source: string,
     title: string,
     url: string,
     pics: string[],
     videos: string[],
+    meta?: NewsMeta,
+};
+
+/** A page of a channel or of search results, from 1. */
+export type NewsPage = {
+    titles: NewsTitle[],
+    page: number,
+    hasNext: boolean,
+};
+
+export type Channel = {
+    id: string,
+    name: string,
 };
\ No newline at end of file
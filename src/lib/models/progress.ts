@@ -0,0 +1,25 @@
+export const PROGRESS_EVENT = "director-progress";
+
+export type Stage = "material" | "dubbing" | "subtitle" | "video" | "compose";
+
+export type ProgressEvent =
+    | { kind: "stageStarted", stage: Stage }
+    | { kind: "stageFinished", stage: Stage }
+    | { kind: "stageCached", stage: Stage }
+    | { kind: "sentenceSynthesized", index: number, total: number }
+    | { kind: "imageDownloaded", index: number, total: number }
+    | { kind: "ffmpeg", frame: number | null, time: number | null };
+
+export type ProgressPayload = {
+    job: string,
+    url: string,
+    event: ProgressEvent,
+};
+
+export const stageNames: Record<Stage, string> = {
+    material: "总结新闻",
+    dubbing: "配音",
+    subtitle: "生成字幕",
+    video: "生成画面",
+    compose: "合成视频",
+};
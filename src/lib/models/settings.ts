@@ -0,0 +1,55 @@
+/** Shown in place of a secret that is set, sent back it keeps the secret. */
+export const MASK = "********";
+
+export type Settings = {
+    OPENAI_KEY: string,
+    TTS_URL: string,
+    ALI_DASHSCOPE_API_KEY: string,
+    VOICE: string,
+    TTS_MODEL: string,
+    LLM_MODEL: string,
+    LLM_PROMPT: string | null,
+    /** `720x1280` */
+    RESOLUTION: string,
+    SUBTITLE_STYLE: string | null,
+    WATERMARK: string | null,
+    /** the profile used when none is given */
+    DEFAULT_PROFILE: string | null,
+    PROFILES: Record<string, ProfileSettings>,
+    SOURCES: string[],
+    FEEDS: Record<string, { URL: string, DISPLAY_NAME?: string | null }>,
+    OUTPUT_DIR: string | null,
+    FFMPEG_PATH: string | null,
+    LLM_CONCURRENCY: number,
+    TTS_CONCURRENCY: number,
+    FFMPEG_CONCURRENCY: number,
+    /** seconds */
+    HOT_NEWS_TTL: number,
+};
+
+/** The keys a profile overrides, the others come from the top level keys. */
+export type ProfileSettings = Partial<Record<
+    "VOICE" | "TTS_MODEL" | "LLM_MODEL" | "LLM_PROMPT" | "RESOLUTION" | "SUBTITLE_STYLE" | "WATERMARK",
+    string
+>>;
+
+export type ConfigLayer =
+    | { kind: "default" }
+    | { kind: "file", path: string }
+    | { kind: "secret", store: string }
+    | { kind: "env", var: string }
+    | { kind: "override" };
+
+export type SettingsView = {
+    settings: Settings,
+    sources: Record<string, ConfigLayer>,
+    /** the file the updates are saved to */
+    file: string,
+    /** where the API keys are saved, `null` when they are in the config file */
+    secretStore: string | null,
+};
+
+/** The keys `update_settings` can change, an empty `OUTPUT_DIR` resets it. */
+export type SettingsUpdate = Partial<Pick<Settings,
+    "OPENAI_KEY" | "ALI_DASHSCOPE_API_KEY" | "TTS_URL" | "VOICE" | "TTS_MODEL" | "LLM_MODEL"
+> & { OUTPUT_DIR: string }>;
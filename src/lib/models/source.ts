@@ -0,0 +1,8 @@
+export type SourceCapability = "hotList" | "channels" | "search" | "localInput";
+
+export type SourceInfo = {
+    /** the name `get_hot_news_list` takes */
+    name: string,
+    displayName: string,
+    capabilities: SourceCapability[],
+};